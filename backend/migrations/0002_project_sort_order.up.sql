@@ -0,0 +1,3 @@
+ALTER TABLE projects ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0;
+
+UPDATE projects SET sort_order = id * 1000 WHERE sort_order = 0;
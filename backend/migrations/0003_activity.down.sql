@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_activity_task;
+DROP INDEX IF EXISTS idx_activity_project;
+DROP TABLE IF EXISTS activity;
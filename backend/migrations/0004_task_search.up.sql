@@ -0,0 +1,44 @@
+CREATE VIRTUAL TABLE IF NOT EXISTS tasks_fts USING fts5(
+    title,
+    description,
+    subtasks,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER IF NOT EXISTS tasks_fts_after_insert AFTER INSERT ON tasks BEGIN
+    INSERT INTO tasks_fts (rowid, title, description, subtasks)
+    VALUES (new.id, new.title, COALESCE(new.description, ''), '');
+END;
+
+CREATE TRIGGER IF NOT EXISTS tasks_fts_after_update AFTER UPDATE OF title, description ON tasks BEGIN
+    UPDATE tasks_fts SET title = new.title, description = COALESCE(new.description, '')
+    WHERE rowid = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS tasks_fts_after_delete AFTER DELETE ON tasks BEGIN
+    DELETE FROM tasks_fts WHERE rowid = old.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS subtasks_fts_after_insert AFTER INSERT ON subtasks BEGIN
+    UPDATE tasks_fts
+    SET subtasks = (SELECT COALESCE(group_concat(title, ' '), '') FROM subtasks WHERE task_id = new.task_id)
+    WHERE rowid = new.task_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS subtasks_fts_after_update AFTER UPDATE OF title ON subtasks BEGIN
+    UPDATE tasks_fts
+    SET subtasks = (SELECT COALESCE(group_concat(title, ' '), '') FROM subtasks WHERE task_id = new.task_id)
+    WHERE rowid = new.task_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS subtasks_fts_after_delete AFTER DELETE ON subtasks BEGIN
+    UPDATE tasks_fts
+    SET subtasks = (SELECT COALESCE(group_concat(title, ' '), '') FROM subtasks WHERE task_id = old.task_id)
+    WHERE rowid = old.task_id;
+END;
+
+INSERT INTO tasks_fts (rowid, title, description, subtasks)
+SELECT t.id, t.title, COALESCE(t.description, ''),
+       COALESCE((SELECT group_concat(s.title, ' ') FROM subtasks s WHERE s.task_id = t.id), '')
+FROM tasks t
+WHERE t.id NOT IN (SELECT rowid FROM tasks_fts);
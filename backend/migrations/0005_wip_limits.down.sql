@@ -0,0 +1,3 @@
+ALTER TABLE projects DROP COLUMN wip_mode;
+
+ALTER TABLE columns DROP COLUMN wip_limit;
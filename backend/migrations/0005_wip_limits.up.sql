@@ -0,0 +1,4 @@
+ALTER TABLE columns ADD COLUMN wip_limit INTEGER CHECK (wip_limit IS NULL OR wip_limit > 0);
+
+-- 'soft' accepts tasks into a full column and returns a warning; 'hard' rejects them.
+ALTER TABLE projects ADD COLUMN wip_mode TEXT NOT NULL DEFAULT 'soft' CHECK (wip_mode IN ('soft', 'hard'));
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_tasks_due_at;
+
+ALTER TABLE tasks DROP COLUMN start_at;
+
+ALTER TABLE tasks DROP COLUMN due_at;
@@ -0,0 +1,6 @@
+-- Stored in the same UTC 'YYYY-MM-DD HH:MM:SS' form as created_at so they compare as text.
+ALTER TABLE tasks ADD COLUMN due_at TEXT;
+
+ALTER TABLE tasks ADD COLUMN start_at TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_tasks_due_at ON tasks(due_at) WHERE due_at IS NOT NULL;
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_calendar_feeds_project;
+
+DROP TABLE IF EXISTS calendar_feeds;
@@ -0,0 +1,10 @@
+-- One secret feed URL per project, plus one (project_id NULL) covering every project.
+-- Calendar clients can't send X-API-Key, so the token in the URL is the credential.
+CREATE TABLE IF NOT EXISTS calendar_feeds (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER REFERENCES projects(id) ON DELETE CASCADE,
+    token TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_calendar_feeds_project ON calendar_feeds(ifnull(project_id, 0));
@@ -0,0 +1,9 @@
+DROP INDEX IF EXISTS idx_tasks_assignee;
+
+ALTER TABLE tasks DROP COLUMN assignee_id;
+
+DROP INDEX IF EXISTS idx_api_tokens_user;
+
+DROP TABLE IF EXISTS api_tokens;
+
+DROP TABLE IF EXISTS users;
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_project_members_user;
+
+DROP TABLE IF EXISTS project_members;
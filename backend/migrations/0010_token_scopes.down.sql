@@ -0,0 +1,24 @@
+-- Tokens without a user, and any restrictions, have no place in the old table.
+CREATE TABLE api_tokens_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    prefix TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    last_used_at TEXT,
+    revoked_at TEXT
+);
+
+INSERT INTO api_tokens_old (id, user_id, name, token_hash, prefix, created_at, last_used_at, revoked_at)
+SELECT id, user_id, name, token_hash, prefix, created_at, last_used_at, revoked_at
+FROM api_tokens
+WHERE user_id IS NOT NULL AND scopes IS NULL AND project_ids IS NULL AND expires_at IS NULL;
+
+DROP INDEX IF EXISTS idx_api_tokens_user;
+
+DROP TABLE api_tokens;
+
+ALTER TABLE api_tokens_old RENAME TO api_tokens;
+
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user ON api_tokens(user_id);
@@ -0,0 +1,30 @@
+-- Tokens can now be narrowed for automation:
+--   scopes       space-separated, e.g. 'tasks:write projects:read'; NULL means unrestricted
+--   project_ids  JSON array of the only projects the token may touch; NULL means all
+--   expires_at   UTC 'YYYY-MM-DD HH:MM:SS'; NULL never expires
+-- user_id becomes optional: tokens minted with the shared admin key belong to no user and
+-- act as that key, within their scopes and projects.
+CREATE TABLE api_tokens_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    prefix TEXT NOT NULL,
+    scopes TEXT,
+    project_ids TEXT,
+    expires_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    last_used_at TEXT,
+    revoked_at TEXT
+);
+
+INSERT INTO api_tokens_new (id, user_id, name, token_hash, prefix, created_at, last_used_at, revoked_at)
+SELECT id, user_id, name, token_hash, prefix, created_at, last_used_at, revoked_at FROM api_tokens;
+
+DROP INDEX IF EXISTS idx_api_tokens_user;
+
+DROP TABLE api_tokens;
+
+ALTER TABLE api_tokens_new RENAME TO api_tokens;
+
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user ON api_tokens(user_id);
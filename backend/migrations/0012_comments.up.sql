@@ -0,0 +1,14 @@
+-- Discussion on a task, kept apart from its description. `body` is markdown, stored as
+-- written. `author` is the actor name so comments made with the admin key or a
+-- user-less token still say who wrote them; `author_id` links to the user when there is one.
+CREATE TABLE IF NOT EXISTS comments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    author_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+    author TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_comments_task ON comments(task_id, id);
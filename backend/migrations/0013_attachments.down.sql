@@ -0,0 +1,6 @@
+DROP TRIGGER IF EXISTS attachments_orphan_blob;
+DROP TABLE IF EXISTS orphaned_blobs;
+
+DROP INDEX IF EXISTS idx_attachments_sha256;
+DROP INDEX IF EXISTS idx_attachments_task;
+DROP TABLE IF EXISTS attachments;
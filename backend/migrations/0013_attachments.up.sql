@@ -0,0 +1,26 @@
+-- Files attached to tasks. Contents live on disk under their SHA-256, so identical
+-- uploads share one file; this table holds the metadata.
+CREATE TABLE IF NOT EXISTS attachments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    filename TEXT NOT NULL,
+    mime_type TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    uploaded_by TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_task ON attachments(task_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_sha256 ON attachments(sha256);
+
+-- Contents that may no longer be referenced. Filled by the trigger below, which also
+-- fires for rows removed by ON DELETE CASCADE, and emptied by the garbage collector.
+CREATE TABLE IF NOT EXISTS orphaned_blobs (
+    sha256 TEXT PRIMARY KEY
+);
+
+CREATE TRIGGER IF NOT EXISTS attachments_orphan_blob AFTER DELETE ON attachments
+BEGIN
+    INSERT OR IGNORE INTO orphaned_blobs (sha256) VALUES (OLD.sha256);
+END;
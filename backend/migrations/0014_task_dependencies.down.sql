@@ -0,0 +1,4 @@
+ALTER TABLE projects DROP COLUMN blocker_mode;
+
+DROP INDEX IF EXISTS idx_task_dependencies_target;
+DROP TABLE IF EXISTS task_dependencies;
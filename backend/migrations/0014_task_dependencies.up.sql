@@ -0,0 +1,16 @@
+-- Links between tasks, read as "<source> <kind> <target>": the source blocks, relates to
+-- or duplicates the target. "Blocked by" is the same row seen from the target.
+CREATE TABLE IF NOT EXISTS task_dependencies (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    target_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL CHECK (kind IN ('blocks', 'relates_to', 'duplicates')),
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    CHECK (source_id <> target_id),
+    UNIQUE (source_id, target_id, kind)
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_dependencies_target ON task_dependencies(target_id, kind);
+
+-- 'soft' lets a task move ahead while its blockers are open and returns a warning; 'hard' rejects the move.
+ALTER TABLE projects ADD COLUMN blocker_mode TEXT NOT NULL DEFAULT 'soft' CHECK (blocker_mode IN ('soft', 'hard'));
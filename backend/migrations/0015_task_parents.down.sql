@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_tasks_parent;
+
+ALTER TABLE tasks DROP COLUMN parent_id;
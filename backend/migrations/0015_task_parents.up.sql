@@ -0,0 +1,5 @@
+-- Tasks can sit under a larger task (an epic), to any depth. Deleting a parent moves its
+-- children up to the top level rather than deleting them.
+ALTER TABLE tasks ADD COLUMN parent_id INTEGER REFERENCES tasks(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_tasks_parent ON tasks(parent_id) WHERE parent_id IS NOT NULL;
@@ -0,0 +1,29 @@
+-- What a column means, independent of its name and position: finished work is whatever
+-- sits in a 'done' column, and 'archived' holds tasks that were dropped.
+ALTER TABLE columns ADD COLUMN kind TEXT NOT NULL DEFAULT 'todo'
+    CHECK (kind IN ('backlog', 'todo', 'in_progress', 'done', 'archived'));
+
+UPDATE columns SET kind = 'backlog' WHERE name = 'Backlog' COLLATE NOCASE;
+UPDATE columns SET kind = 'in_progress' WHERE name COLLATE NOCASE IN ('In Progress', 'Testing');
+UPDATE columns SET kind = 'done' WHERE name = 'Done' COLLATE NOCASE;
+
+-- Until now the last column of a board counted as done; keep that where no column is named Done.
+UPDATE columns SET kind = 'done'
+WHERE id IN (
+    SELECT (SELECT lc.id FROM columns lc WHERE lc.project_id = p.id ORDER BY lc.sort_order DESC LIMIT 1)
+    FROM projects p
+    WHERE NOT EXISTS (SELECT 1 FROM columns c WHERE c.project_id = p.id AND c.kind = 'done')
+);
+
+-- When the task last entered a 'done' column; NULL while it is anywhere else.
+ALTER TABLE tasks ADD COLUMN completed_at TEXT;
+
+UPDATE tasks SET completed_at = COALESCE(
+    (SELECT MAX(a.created_at) FROM activity a
+     WHERE a.entity_type = 'task' AND a.entity_id = tasks.id AND a.action = 'move'
+       AND json_extract(a.after_json, '$.column_id') = tasks.column_id),
+    created_at
+)
+WHERE column_id IN (SELECT id FROM columns WHERE kind = 'done');
+
+CREATE INDEX IF NOT EXISTS idx_tasks_completed_at ON tasks(completed_at) WHERE completed_at IS NOT NULL;
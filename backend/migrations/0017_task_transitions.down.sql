@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS task_transitions_after_move;
+DROP TRIGGER IF EXISTS task_transitions_after_insert;
+
+DROP INDEX IF EXISTS idx_task_transitions_task;
+DROP TABLE IF EXISTS task_transitions;
@@ -0,0 +1,47 @@
+-- Every column a task has entered and when, for lead and cycle time metrics. Rows are
+-- written by the triggers below, so every way of creating or moving a task is covered.
+-- `from_column_id` is NULL for the column a task was created in.
+CREATE TABLE IF NOT EXISTS task_transitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL REFERENCES tasks(id) ON DELETE CASCADE,
+    from_column_id INTEGER REFERENCES columns(id) ON DELETE SET NULL,
+    to_column_id INTEGER REFERENCES columns(id) ON DELETE SET NULL,
+    transitioned_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_task_transitions_task ON task_transitions(task_id, transitioned_at);
+
+CREATE TRIGGER IF NOT EXISTS task_transitions_after_insert AFTER INSERT ON tasks
+BEGIN
+    INSERT INTO task_transitions (task_id, from_column_id, to_column_id, transitioned_at)
+    VALUES (NEW.id, NULL, NEW.column_id, NEW.created_at);
+END;
+
+CREATE TRIGGER IF NOT EXISTS task_transitions_after_move AFTER UPDATE OF column_id ON tasks
+WHEN OLD.column_id <> NEW.column_id
+BEGIN
+    INSERT INTO task_transitions (task_id, from_column_id, to_column_id)
+    VALUES (NEW.id, OLD.column_id, NEW.column_id);
+END;
+
+-- Rebuild what history we can from the activity log's task creations and moves.
+INSERT INTO task_transitions (task_id, from_column_id, to_column_id, transitioned_at)
+SELECT t.id,
+       CASE WHEN a.action = 'move'
+            THEN (SELECT c.id FROM columns c WHERE c.id = json_extract(a.before_json, '$.column_id')) END,
+       (SELECT c.id FROM columns c WHERE c.id = json_extract(a.after_json, '$.column_id')),
+       a.created_at
+FROM activity a
+INNER JOIN tasks t ON t.id = a.entity_id
+WHERE a.entity_type = 'task' AND a.action IN ('create', 'move')
+ORDER BY a.id;
+
+-- Tasks older than the activity log start in the column they first left, or are in now.
+INSERT INTO task_transitions (task_id, from_column_id, to_column_id, transitioned_at)
+SELECT t.id,
+       NULL,
+       COALESCE((SELECT tr.from_column_id FROM task_transitions tr WHERE tr.task_id = t.id
+                 ORDER BY tr.transitioned_at, tr.id LIMIT 1), t.column_id),
+       t.created_at
+FROM tasks t
+WHERE NOT EXISTS (SELECT 1 FROM activity a WHERE a.entity_type = 'task' AND a.entity_id = t.id AND a.action = 'create');
@@ -0,0 +1,33 @@
+-- Deliveries keep a copy of their webhook's url and secret, and outlive the webhook: a
+-- deleted project takes its webhooks with it, and its project.deleted deliveries must
+-- still go out. Deleting a webhook on its own clears its deliveries explicitly.
+CREATE TABLE webhook_deliveries_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    webhook_id INTEGER REFERENCES webhooks(id) ON DELETE SET NULL,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK(status IN ('pending', 'delivered', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%d %H:%M:%f', 'now')),
+    response_status INTEGER,
+    last_error TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    delivered_at TEXT
+);
+
+INSERT INTO webhook_deliveries_new (id, webhook_id, url, secret, event, payload, status, attempts,
+                                    next_attempt_at, response_status, last_error, created_at, delivered_at)
+SELECT d.id, d.webhook_id, w.url, w.secret, d.event, d.payload, d.status, d.attempts,
+       d.next_attempt_at, d.response_status, d.last_error, d.created_at, d.delivered_at
+FROM webhook_deliveries d
+INNER JOIN webhooks w ON w.id = d.webhook_id;
+
+DROP INDEX IF EXISTS idx_webhook_deliveries_webhook;
+DROP INDEX IF EXISTS idx_webhook_deliveries_due;
+DROP TABLE webhook_deliveries;
+ALTER TABLE webhook_deliveries_new RENAME TO webhook_deliveries;
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due ON webhook_deliveries(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id, id);
@@ -0,0 +1,211 @@
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite};
+
+use crate::error::ApiError;
+use crate::middleware::CurrentUser;
+
+/// What a member may do on a project. Each role includes everything below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "viewer" => Some(Role::Viewer),
+            "editor" => Some(Role::Editor),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// What an API token may be used for. Tokens without scopes may do anything their
+/// user's roles allow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Read boards, columns, tasks and their history.
+    ProjectsRead,
+    /// Create, change and delete projects, columns, tags and linked paths.
+    ProjectsWrite,
+    /// Create, change, move and delete tasks, subtasks, comments, attachments and dependencies.
+    TasksWrite,
+    /// Manage users, tokens, project members, calendar feeds and webhooks.
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 4] = [Scope::ProjectsRead, Scope::ProjectsWrite, Scope::TasksWrite, Scope::Admin];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::ProjectsRead => "projects:read",
+            Scope::ProjectsWrite => "projects:write",
+            Scope::TasksWrite => "tasks:write",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Scope::ALL.into_iter().find(|scope| scope.as_str() == value)
+    }
+
+    /// Writing anything on a board implies being able to read the board.
+    pub fn grants(self, needed: Scope) -> bool {
+        self == needed || (needed == Scope::ProjectsRead && self != Scope::Admin)
+    }
+
+    /// The scope a request needs, from its method and path. `None` means any token may
+    /// make it.
+    pub fn required_for(method: &Method, path: &str) -> Option<Scope> {
+        let segments: Vec<&str> = path
+            .trim_start_matches("/api/")
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+        let write = !matches!(*method, Method::GET | Method::HEAD);
+
+        match segments.as_slice() {
+            ["users", "me"] => None,
+            ["users"] if !write => Some(Scope::ProjectsRead),
+            ["users", ..] | ["tokens", ..] | ["calendar-feed"] | ["webhooks", ..] => Some(Scope::Admin),
+            ["projects", _, "members" | "calendar-feed" | "webhooks", ..] => Some(Scope::Admin),
+            _ if !write => Some(Scope::ProjectsRead),
+            ["tasks", ..] | ["subtasks", ..] | ["comments", ..] | ["attachments", ..] | ["dependencies", ..]
+            | ["projects", _, "tasks"] => {
+                Some(Scope::TasksWrite)
+            }
+            _ => Some(Scope::ProjectsWrite),
+        }
+    }
+}
+
+/// Checks that the caller holds at least `needed` on the project and returns the role
+/// they have. The shared admin key passes every check.
+///
+/// Callers who aren't members, or whose token is limited to other projects, get the
+/// same 404 as for a missing project, so boards they can't see don't reveal that they
+/// exist.
+pub async fn require_role<'e, E>(
+    executor: E,
+    user: &CurrentUser,
+    project_id: i64,
+    needed: Role,
+) -> Result<Role, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    if !user.may_access_project(project_id) {
+        return Err(ApiError::not_found("project_not_found", "Project not found"));
+    }
+
+    let Some(user_id) = user.id else {
+        return Ok(Role::Admin);
+    };
+
+    let role = sqlx::query_scalar!(
+        r#"SELECT role FROM project_members WHERE project_id = ? AND user_id = ?"#,
+        project_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .and_then(|role| Role::parse(&role))
+    .ok_or_else(|| ApiError::not_found("project_not_found", "Project not found"))?;
+
+    if role < needed {
+        return Err(ApiError::forbidden(
+            "insufficient_role",
+            format!("This requires the {} role on the project", needed.as_str()),
+        ));
+    }
+
+    Ok(role)
+}
+
+/// Editors can create tags, which are shared by every project, if they edit any project.
+pub async fn require_editor_anywhere<'e, E>(executor: E, user: &CurrentUser) -> Result<(), ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let Some(user_id) = user.id else {
+        return Ok(());
+    };
+
+    let is_editor = sqlx::query_scalar!(
+        r#"SELECT EXISTS(
+               SELECT 1 FROM project_members WHERE user_id = ? AND role IN ('editor', 'admin')
+           ) as "is_editor!: bool""#,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    if !is_editor {
+        return Err(ApiError::forbidden(
+            "insufficient_role",
+            "This requires the editor role on at least one project",
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn project_of_column<'e, E>(executor: E, column_id: i64) -> Result<i64, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_scalar!(r#"SELECT project_id FROM columns WHERE id = ?"#, column_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| ApiError::not_found("column_not_found", "Column not found"))
+}
+
+pub async fn project_of_task<'e, E>(executor: E, task_id: i64) -> Result<i64, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_scalar!(r#"SELECT project_id FROM tasks WHERE id = ?"#, task_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| ApiError::not_found("task_not_found", "Task not found"))
+}
+
+pub async fn project_of_subtask<'e, E>(executor: E, subtask_id: i64) -> Result<i64, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_scalar!(
+        r#"SELECT t.project_id FROM subtasks s INNER JOIN tasks t ON t.id = s.task_id WHERE s.id = ?"#,
+        subtask_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| ApiError::not_found("subtask_not_found", "Subtask not found"))
+}
+
+pub async fn project_of_comment<'e, E>(executor: E, comment_id: i64) -> Result<i64, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_scalar!(
+        r#"SELECT t.project_id FROM comments c INNER JOIN tasks t ON t.id = c.task_id WHERE c.id = ?"#,
+        comment_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| ApiError::not_found("comment_not_found", "Comment not found"))
+}
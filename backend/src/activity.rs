@@ -0,0 +1,80 @@
+use serde::Serialize;
+use sqlx::{Executor, Sqlite};
+
+use crate::middleware::Actor;
+
+/// A mutation to be written to the `activity` table.
+///
+/// Build one with [`NewActivity::new`] and hand it to [`record`] inside the same
+/// transaction as the change it describes.
+pub struct NewActivity<'a> {
+    pub actor: &'a Actor,
+    pub action: &'a str,
+    pub entity_type: &'a str,
+    pub entity_id: i64,
+    pub project_id: Option<i64>,
+    pub task_id: Option<i64>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl<'a> NewActivity<'a> {
+    pub fn new(actor: &'a Actor, action: &'a str, entity_type: &'a str, entity_id: i64) -> Self {
+        Self {
+            actor,
+            action,
+            entity_type,
+            entity_id,
+            project_id: None,
+            task_id: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn project(mut self, project_id: i64) -> Self {
+        self.project_id = Some(project_id);
+        self
+    }
+
+    /// Ties the entry to a task so it shows up in that task's history.
+    pub fn task(mut self, task_id: i64) -> Self {
+        self.task_id = Some(task_id);
+        self
+    }
+
+    pub fn before<T: Serialize>(mut self, value: &T) -> Self {
+        self.before = serde_json::to_value(value).ok();
+        self
+    }
+
+    pub fn after<T: Serialize>(mut self, value: &T) -> Self {
+        self.after = serde_json::to_value(value).ok();
+        self
+    }
+}
+
+pub async fn record<'e, E>(executor: E, entry: NewActivity<'_>) -> Result<(), sqlx::Error>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let before_json = entry.before.map(|v| v.to_string());
+    let after_json = entry.after.map(|v| v.to_string());
+
+    sqlx::query!(
+        r#"INSERT INTO activity (project_id, task_id, actor, action, entity_type, entity_id, before_json, after_json)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?)"#,
+        entry.project_id,
+        entry.task_id,
+        entry.actor.0,
+        entry.action,
+        entry.entity_type,
+        entry.entity_id,
+        before_json,
+        after_json
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
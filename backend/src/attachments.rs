@@ -0,0 +1,198 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+const DEFAULT_MAX_ATTACHMENT_MB: u64 = 25;
+
+/// Attachment contents on local disk, one file per distinct content, stored as
+/// `<root>/<first two hex digits>/<sha256>`. Metadata lives in the `attachments` table.
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    root: PathBuf,
+    max_bytes: u64,
+}
+
+/// An upload being written to a temporary file and hashed as it arrives.
+pub struct StagedBlob {
+    file: tokio::fs::File,
+    path: PathBuf,
+    hasher: Sha256,
+    size: u64,
+}
+
+/// A fully received upload, ready to be moved into the store with [`BlobStore::commit`].
+pub struct ReceivedBlob {
+    pub sha256: String,
+    pub size: u64,
+    path: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(root: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        Self {
+            root: root.into(),
+            max_bytes,
+        }
+    }
+
+    /// `KANBAN_ATTACHMENTS_DIR`, or an `attachments` directory next to the SQLite file.
+    /// Uploads are limited to `KANBAN_MAX_ATTACHMENT_MB` megabytes (default 25).
+    pub fn from_env(database_url: &str) -> Self {
+        let root = match std::env::var("KANBAN_ATTACHMENTS_DIR") {
+            Ok(dir) if !dir.trim().is_empty() => PathBuf::from(dir),
+            _ => {
+                let db_path = database_url
+                    .trim_start_matches("sqlite://")
+                    .trim_start_matches("sqlite:")
+                    .split('?')
+                    .next()
+                    .unwrap_or_default();
+                Path::new(db_path)
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or_else(|| Path::new("."))
+                    .join("attachments")
+            }
+        };
+        let max_mb = std::env::var("KANBAN_MAX_ATTACHMENT_MB")
+            .ok()
+            .and_then(|mb| mb.trim().parse().ok())
+            .unwrap_or(DEFAULT_MAX_ATTACHMENT_MB);
+
+        Self::new(root, max_mb * 1024 * 1024)
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    pub fn path_for(&self, sha256: &str) -> PathBuf {
+        self.root.join(&sha256[..2]).join(sha256)
+    }
+
+    /// Temporary files sit inside the store so the final rename stays on one filesystem.
+    pub async fn stage(&self) -> std::io::Result<StagedBlob> {
+        let tmp_dir = self.root.join("tmp");
+        tokio::fs::create_dir_all(&tmp_dir).await?;
+
+        let mut suffix = [0u8; 8];
+        rand::thread_rng().fill_bytes(&mut suffix);
+        let name: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+        let path = tmp_dir.join(format!("upload-{}", name));
+        let file = tokio::fs::File::create(&path).await?;
+
+        Ok(StagedBlob {
+            file,
+            path,
+            hasher: Sha256::new(),
+            size: 0,
+        })
+    }
+
+    /// Moves a received upload to its content address. A file already there has the
+    /// same contents, so replacing it is harmless.
+    pub async fn commit(&self, blob: &ReceivedBlob) -> std::io::Result<()> {
+        let target = self.path_for(&blob.sha256);
+        if let Some(dir) = target.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::rename(&blob.path, &target).await
+    }
+}
+
+impl StagedBlob {
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub async fn write(&mut self, chunk: &[u8]) -> std::io::Result<()> {
+        self.hasher.update(chunk);
+        self.size += chunk.len() as u64;
+        self.file.write_all(chunk).await
+    }
+
+    pub async fn finish(mut self) -> std::io::Result<ReceivedBlob> {
+        self.file.flush().await?;
+        let sha256 = self.hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+
+        Ok(ReceivedBlob {
+            sha256,
+            size: self.size,
+            path: self.path,
+        })
+    }
+
+    pub async fn discard(self) {
+        drop(self.file);
+        let _ = tokio::fs::remove_file(&self.path).await;
+    }
+}
+
+impl ReceivedBlob {
+    pub async fn discard(self) {
+        let _ = tokio::fs::remove_file(&self.path).await;
+    }
+}
+
+/// Deletes the files of contents no attachment refers to any more, as queued in
+/// `orphaned_blobs` when attachments are deleted directly or with their task or project.
+/// Returns how many files were removed; failures are logged and left for the next run.
+///
+/// Each check runs in a write transaction, so it can't interleave with an upload of the
+/// same contents, which inserts its row and moves its file while holding the write lock.
+pub async fn collect_garbage(db: &SqlitePool, store: &BlobStore) -> usize {
+    let candidates = match sqlx::query_scalar!(r#"SELECT sha256 as "sha256!" FROM orphaned_blobs"#)
+        .fetch_all(db)
+        .await
+    {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            tracing::warn!("Failed to list orphaned attachment blobs: {}", e);
+            return 0;
+        }
+    };
+
+    let mut removed = 0;
+    for sha256 in candidates {
+        match remove_if_orphaned(db, store, &sha256).await {
+            Ok(true) => removed += 1,
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to remove attachment blob {}: {}", sha256, e),
+        }
+    }
+
+    removed
+}
+
+async fn remove_if_orphaned(db: &SqlitePool, store: &BlobStore, sha256: &str) -> Result<bool, String> {
+    let mut tx = db.begin().await.map_err(|e| e.to_string())?;
+
+    sqlx::query!(r#"DELETE FROM orphaned_blobs WHERE sha256 = ?"#, sha256)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let in_use = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM attachments WHERE sha256 = ?) as "in_use!: bool""#,
+        sha256
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let removed = if in_use {
+        false
+    } else {
+        match tokio::fs::remove_file(store.path_for(sha256)).await {
+            Ok(()) => true,
+            Err(e) if e.kind() == ErrorKind::NotFound => false,
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    Ok(removed)
+}
@@ -1,59 +1,221 @@
-use sqlx::{sqlite::SqliteConnectOptions, sqlite::SqlitePoolOptions, SqlitePool};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    SqlitePool,
+};
 use std::path::Path;
 use std::str::FromStr;
 
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    up: &'static str,
+    down: &'static str,
+}
+
+macro_rules! migration {
+    ($version:expr, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!("../migrations/", $name, ".up.sql")),
+            down: include_str!(concat!("../migrations/", $name, ".down.sql")),
+        }
+    };
+}
+
+/// Every schema change, in order. Append new entries; never edit or renumber applied ones.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_initial"),
+    migration!(2, "0002_project_sort_order"),
+    migration!(3, "0003_activity"),
+    migration!(4, "0004_task_search"),
+    migration!(5, "0005_wip_limits"),
+    migration!(6, "0006_task_dates"),
+    migration!(7, "0007_calendar_feeds"),
+    migration!(8, "0008_users"),
+    migration!(9, "0009_project_members"),
+    migration!(10, "0010_token_scopes"),
+    migration!(11, "0011_webhooks"),
+    migration!(12, "0012_comments"),
+    migration!(13, "0013_attachments"),
+    migration!(14, "0014_task_dependencies"),
+    migration!(15, "0015_task_parents"),
+    migration!(16, "0016_column_kinds"),
+    migration!(17, "0017_task_transitions"),
+    migration!(18, "0018_webhook_delivery_targets"),
+];
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub name: &'static str,
+    pub applied_at: Option<String>,
+}
+
 pub async fn init_db(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
+    let pool = connect(database_url).await?;
+
+    run_migrations(&pool).await?;
+
+    tracing::info!("Database initialized successfully");
+
+    Ok(pool)
+}
+
+/// Opens the database without touching its schema, for the `migrate` subcommand.
+pub async fn connect(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
     let file_path = if database_url.starts_with("sqlite://") {
         database_url.trim_start_matches("sqlite://")
     } else {
         database_url
     };
-    
+
     if let Some(parent) = Path::new(file_path).parent() {
         std::fs::create_dir_all(parent).expect("Failed to create data directory");
     }
 
     let options = SqliteConnectOptions::from_str(database_url)?
-        .create_if_missing(true);
-    
-    let pool = SqlitePoolOptions::new()
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .foreign_keys(true);
+
+    SqlitePoolOptions::new()
         .max_connections(5)
         .connect_with(options)
-        .await?;
+        .await
+}
+
+/// Applies every pending migration, each in its own transaction, and returns the
+/// versions that were applied.
+pub async fn run_migrations(pool: &SqlitePool) -> Result<Vec<i64>, sqlx::Error> {
+    ensure_migrations_table(pool).await?;
+
+    let applied = applied_versions(pool).await?;
+    let mut newly_applied = Vec::new();
 
-    let schema = include_str!("../schema.sql");
-    
-    for statement in schema.split(';').map(|s| s.trim()).filter(|s| !s.is_empty()) {
-        sqlx::raw_sql(statement).execute(&pool).await?;
+    for migration in MIGRATIONS.iter().filter(|m| !applied.contains(&m.version)) {
+        let mut tx = pool.begin().await?;
+
+        sqlx::raw_sql(migration.up).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_migrations (version, name) VALUES (?, ?)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        tracing::info!("Migration: Applied {}", migration.name);
+        newly_applied.push(migration.version);
     }
 
-    run_migrations(&pool).await?;
+    Ok(newly_applied)
+}
 
-    tracing::info!("Database initialized successfully");
+/// Reverts the most recently applied migration, returning its version.
+pub async fn revert_last_migration(pool: &SqlitePool) -> Result<Option<i64>, sqlx::Error> {
+    ensure_migrations_table(pool).await?;
 
-    Ok(pool)
+    let Some(&latest) = applied_versions(pool).await?.last() else {
+        return Ok(None);
+    };
+    let Some(migration) = MIGRATIONS.iter().find(|m| m.version == latest) else {
+        return Err(sqlx::Error::Protocol(format!(
+            "Database is at version {} which this build does not know about",
+            latest
+        )));
+    };
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::raw_sql(migration.down).execute(&mut *tx).await?;
+    sqlx::query("DELETE FROM schema_migrations WHERE version = ?")
+        .bind(migration.version)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    tracing::info!("Migration: Reverted {}", migration.name);
+
+    Ok(Some(migration.version))
+}
+
+pub async fn migration_status(pool: &SqlitePool) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+    ensure_migrations_table(pool).await?;
+
+    let rows: Vec<(i64, String)> =
+        sqlx::query_as("SELECT version, applied_at FROM schema_migrations")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(MIGRATIONS
+        .iter()
+        .map(|m| MigrationStatus {
+            name: m.name,
+            applied_at: rows
+                .iter()
+                .find(|(version, _)| *version == m.version)
+                .map(|(_, applied_at)| applied_at.clone()),
+        })
+        .collect())
 }
 
-async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
-    let has_sort_order: bool = sqlx::query_scalar(
-        "SELECT COUNT(*) > 0 FROM pragma_table_info('projects') WHERE name = 'sort_order'"
+async fn applied_versions(pool: &SqlitePool) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT version FROM schema_migrations ORDER BY version")
+        .fetch_all(pool)
+        .await
+}
+
+async fn ensure_migrations_table(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let has_table: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'"
     )
     .fetch_one(pool)
     .await?;
 
-    if !has_sort_order {
-        sqlx::raw_sql("ALTER TABLE projects ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0")
-            .execute(pool)
-            .await?;
-        
-        sqlx::raw_sql(
-            "UPDATE projects SET sort_order = id * 1000 WHERE sort_order = 0"
+    if has_table {
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::raw_sql(
+        "CREATE TABLE schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )"
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Databases from before versioned migrations were built from a single schema.sql
+    // plus a sort_order probe. Record what they already contain so it isn't re-applied;
+    // they don't have the later tables and columns yet, so those migrations apply normally.
+    let has_projects: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'projects'"
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if has_projects {
+        let has_sort_order: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('projects') WHERE name = 'sort_order'"
         )
-        .execute(pool)
+        .fetch_one(&mut *tx)
         .await?;
-        
-        tracing::info!("Migration: Added sort_order column to projects table");
+
+        let baseline = if has_sort_order { 2 } else { 1 };
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= baseline) {
+            sqlx::query("INSERT INTO schema_migrations (version, name) VALUES (?, ?)")
+                .bind(migration.version)
+                .bind(migration.name)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tracing::info!("Migration: Adopted existing database at version {}", baseline);
     }
 
-    Ok(())
+    tx.commit().await
 }
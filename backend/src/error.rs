@@ -0,0 +1,171 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use sqlx::error::ErrorKind;
+
+/// A problem with one field of the request, reported in `details`.
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    /// The line of an uploaded file the problem is on, for imports.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u64>,
+    pub field: String,
+    pub message: String,
+}
+
+/// The error type every handler returns.
+///
+/// It serialises as `{"error": "<message>", "code": "<code>", "details": [...]}`.
+/// `code` is stable and meant for programs to branch on; `error` is for people.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    pub details: Vec<FieldError>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+    code: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    details: Vec<FieldError>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            details: Vec::new(),
+        }
+    }
+
+    pub fn not_found(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, code, message)
+    }
+
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    pub fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, code, message)
+    }
+
+    pub fn unauthorized(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, code, message)
+    }
+
+    pub fn forbidden(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, code, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", message)
+    }
+
+    /// A request body field failed validation (422 `validation_failed`).
+    pub fn validation(field: &str, message: &str) -> Self {
+        Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "validation_failed",
+            format!("{} {}", field, message),
+        )
+        .with_detail(field, message)
+    }
+
+    /// A query string parameter could not be understood (400 `invalid_query`).
+    pub fn invalid_query(field: &str, message: impl Into<String>) -> Self {
+        let message = message.into();
+        Self::bad_request("invalid_query", message.clone()).with_detail(field, &message)
+    }
+
+    pub fn with_detail(mut self, field: &str, message: &str) -> Self {
+        self.details.push(FieldError {
+            line: None,
+            field: field.to_string(),
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_line_detail(mut self, line: u64, field: &str, message: &str) -> Self {
+        self.details.push(FieldError {
+            line: Some(line),
+            field: field.to_string(),
+            message: message.to_string(),
+        });
+        self
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.message,
+            code: self.code,
+            details: self.details,
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        if let sqlx::Error::Database(db) = &e {
+            match db.kind() {
+                ErrorKind::UniqueViolation => return unique_violation(db.message()),
+                ErrorKind::CheckViolation => return check_violation(db.message()),
+                ErrorKind::ForeignKeyViolation => {
+                    return Self::new(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        "invalid_reference",
+                        "A referenced record does not exist",
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        tracing::error!("Database error: {}", e);
+        Self::internal(format!("Database error: {}", e))
+    }
+}
+
+/// SQLite reports `UNIQUE constraint failed: <table>.<column>[, ...]`.
+fn unique_violation(message: &str) -> ApiError {
+    let columns = message.split_once(": ").map(|(_, c)| c).unwrap_or_default();
+
+    match columns {
+        "projects.name" => ApiError::conflict("project_name_taken", "A project with this name already exists")
+            .with_detail("name", "is already taken"),
+        "users.name" => ApiError::conflict("user_name_taken", "A user with this name already exists")
+            .with_detail("name", "is already taken"),
+        "tags.name" => ApiError::conflict("tag_name_taken", "A tag with this name already exists")
+            .with_detail("name", "is already taken"),
+        _ => columns.split(", ").fold(
+            ApiError::conflict("already_exists", "A record with these values already exists"),
+            |error, column| {
+                let field = column.rsplit('.').next().unwrap_or(column);
+                error.with_detail(field, "is already taken")
+            },
+        ),
+    }
+}
+
+/// SQLite reports `CHECK constraint failed: <expression>`; the expression starts with the column.
+fn check_violation(message: &str) -> ApiError {
+    let field = message
+        .split_once(": ")
+        .and_then(|(_, expr)| expr.split(|c: char| !c.is_alphanumeric() && c != '_').next())
+        .filter(|field| !field.is_empty())
+        .unwrap_or("value");
+
+    ApiError::validation(field, "has an invalid value")
+}
@@ -0,0 +1,98 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+const CHANNEL_CAPACITY: usize = 256;
+const BUFFER_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BoardEvent {
+    pub id: u64,
+    /// `None` for global entities such as tags, which are relevant to every board.
+    pub project_id: Option<i64>,
+    pub kind: String,
+    pub data: serde_json::Value,
+}
+
+impl BoardEvent {
+    pub fn visible_to(&self, project_id: i64) -> bool {
+        self.project_id.is_none_or(|id| id == project_id)
+    }
+}
+
+/// In-process fan-out of board mutations, with a bounded history so that
+/// reconnecting clients can resume from `Last-Event-ID`.
+#[derive(Clone)]
+pub struct EventBus {
+    inner: Arc<EventBusInner>,
+}
+
+struct EventBusInner {
+    sender: broadcast::Sender<BoardEvent>,
+    buffer: Mutex<VecDeque<BoardEvent>>,
+    next_id: AtomicU64,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            inner: Arc::new(EventBusInner {
+                sender,
+                buffer: Mutex::new(VecDeque::with_capacity(BUFFER_CAPACITY)),
+                next_id: AtomicU64::new(1),
+            }),
+        }
+    }
+
+    pub fn publish<T: Serialize>(&self, project_id: Option<i64>, kind: &str, data: &T) {
+        let data = match serde_json::to_value(data) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::warn!("Failed to serialize {} event: {}", kind, e);
+                return;
+            }
+        };
+
+        // Id allocation and buffering happen under the lock so the buffer stays ordered.
+        let mut buffer = self.inner.buffer.lock().unwrap();
+        let event = BoardEvent {
+            id: self.inner.next_id.fetch_add(1, Ordering::SeqCst),
+            project_id,
+            kind: kind.to_string(),
+            data,
+        };
+
+        if buffer.len() == BUFFER_CAPACITY {
+            buffer.pop_front();
+        }
+        buffer.push_back(event.clone());
+
+        // An error only means nobody is listening right now.
+        let _ = self.inner.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BoardEvent> {
+        self.inner.sender.subscribe()
+    }
+
+    /// Buffered events for a project with an id greater than `last_event_id`.
+    pub fn replay_since(&self, project_id: i64, last_event_id: u64) -> Vec<BoardEvent> {
+        self.inner
+            .buffer
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.id > last_event_id && e.visible_to(project_id))
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,151 @@
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::projects::AppState;
+use crate::access::{require_role, Role};
+use crate::error::ApiError;
+use crate::middleware::CurrentUser;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Serialize)]
+pub struct ActivityEntry {
+    pub id: i64,
+    pub project_id: Option<i64>,
+    pub task_id: Option<i64>,
+    pub actor: String,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: i64,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub created_at: String,
+}
+
+/// Newest entries first; pass `next_before` back as `before` to fetch the next page.
+#[derive(Debug, Serialize)]
+pub struct ActivityPage {
+    pub items: Vec<ActivityEntry>,
+    pub next_before: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    pub limit: Option<i64>,
+    pub before: Option<i64>,
+}
+
+struct ActivityRow {
+    id: i64,
+    project_id: Option<i64>,
+    task_id: Option<i64>,
+    actor: String,
+    action: String,
+    entity_type: String,
+    entity_id: i64,
+    before_json: Option<String>,
+    after_json: Option<String>,
+    created_at: String,
+}
+
+impl From<ActivityRow> for ActivityEntry {
+    fn from(row: ActivityRow) -> Self {
+        let parse = |json: Option<String>| json.and_then(|s| serde_json::from_str(&s).ok());
+        ActivityEntry {
+            id: row.id,
+            project_id: row.project_id,
+            task_id: row.task_id,
+            actor: row.actor,
+            action: row.action,
+            entity_type: row.entity_type,
+            entity_id: row.entity_id,
+            before: parse(row.before_json),
+            after: parse(row.after_json),
+            created_at: row.created_at,
+        }
+    }
+}
+
+fn into_page(mut rows: Vec<ActivityRow>, limit: i64) -> ActivityPage {
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    let next_before = if has_more { rows.last().map(|r| r.id) } else { None };
+
+    ActivityPage {
+        items: rows.into_iter().map(ActivityEntry::from).collect(),
+        next_before,
+    }
+}
+
+pub async fn list_project_activity(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<ActivityPage>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let fetch = limit + 1;
+
+    let rows = sqlx::query_as!(
+        ActivityRow,
+        r#"SELECT id as "id!", project_id, task_id, actor, action, entity_type, entity_id, before_json, after_json, created_at
+           FROM activity
+           WHERE project_id = ? AND (? IS NULL OR id < ?)
+           ORDER BY id DESC
+           LIMIT ?"#,
+        project_id,
+        query.before,
+        query.before,
+        fetch
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(into_page(rows, limit)))
+}
+
+/// History outlives the task, so access is decided by the project its entries belong to.
+pub async fn get_task_history(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(task_id): Path<i64>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<ActivityPage>, ApiError> {
+    let project_id = sqlx::query_scalar!(
+        r#"SELECT project_id as "project_id!" FROM activity
+           WHERE task_id = ? AND project_id IS NOT NULL
+           LIMIT 1"#,
+        task_id
+    )
+    .fetch_optional(&state.db)
+    .await?;
+    if let Some(project_id) = project_id {
+        require_role(&state.db, &user, project_id, Role::Viewer).await?;
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let fetch = limit + 1;
+
+    let rows = sqlx::query_as!(
+        ActivityRow,
+        r#"SELECT id as "id!", project_id, task_id, actor, action, entity_type, entity_id, before_json, after_json, created_at
+           FROM activity
+           WHERE task_id = ? AND (? IS NULL OR id < ?)
+           ORDER BY id DESC
+           LIMIT ?"#,
+        task_id,
+        query.before,
+        query.before,
+        fetch
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(into_page(rows, limit)))
+}
@@ -0,0 +1,281 @@
+use axum::{
+    body::Body,
+    extract::{multipart::MultipartError, Multipart, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::Serialize;
+use sqlx::{Executor, Sqlite};
+use tokio_util::io::ReaderStream;
+
+use super::projects::AppState;
+use crate::access::{project_of_task, require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::attachments::collect_garbage;
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+use crate::webhooks;
+
+#[derive(Debug, Serialize)]
+pub struct Attachment {
+    pub id: i64,
+    pub task_id: i64,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub sha256: String,
+    pub uploaded_by: String,
+    pub created_at: String,
+}
+
+fn storage_error(e: std::io::Error) -> ApiError {
+    tracing::error!("Attachment storage error: {}", e);
+    ApiError::internal(format!("Failed to store attachment: {}", e))
+}
+
+fn multipart_error(e: MultipartError) -> ApiError {
+    ApiError::bad_request("invalid_multipart", e.body_text())
+}
+
+/// Keeps only the last path component, since some clients send the full local path.
+fn clean_filename(name: Option<&str>) -> String {
+    let name = name
+        .unwrap_or_default()
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    let name = name.trim();
+
+    if name.is_empty() {
+        "attachment".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// `attachment` disposition with an ASCII fallback name and the exact name per RFC 5987.
+fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| if c.is_ascii() && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+async fn find_attachment<'e, E>(executor: E, id: i64) -> Result<(Attachment, i64), ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let row = sqlx::query!(
+        r#"SELECT a.id as "id!", a.task_id, a.filename, a.mime_type, a.size, a.sha256, a.uploaded_by,
+                  a.created_at, t.project_id
+           FROM attachments a
+           INNER JOIN tasks t ON t.id = a.task_id
+           WHERE a.id = ?"#,
+        id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| ApiError::not_found("attachment_not_found", "Attachment not found"))?;
+
+    let attachment = Attachment {
+        id: row.id,
+        task_id: row.task_id,
+        filename: row.filename,
+        mime_type: row.mime_type,
+        size: row.size,
+        sha256: row.sha256,
+        uploaded_by: row.uploaded_by,
+        created_at: row.created_at,
+    };
+
+    Ok((attachment, row.project_id))
+}
+
+pub async fn list_attachments(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(task_id): Path<i64>,
+) -> Result<Json<Vec<Attachment>>, ApiError> {
+    let project_id = project_of_task(&state.db, task_id).await?;
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let attachments = sqlx::query_as!(
+        Attachment,
+        r#"SELECT id as "id!", task_id, filename, mime_type, size, sha256, uploaded_by, created_at
+           FROM attachments WHERE task_id = ? ORDER BY id"#,
+        task_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(attachments))
+}
+
+/// Takes a multipart form with the file in a part named `file`. The upload is streamed
+/// to disk, so the size limit applies without holding the file in memory.
+pub async fn upload_attachment(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(task_id): Path<i64>,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<Attachment>), ApiError> {
+    let project_id = project_of_task(&state.db, task_id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    let mut field = loop {
+        match multipart.next_field().await.map_err(multipart_error)? {
+            Some(field) if field.name() == Some("file") => break field,
+            Some(_) => continue,
+            None => return Err(ApiError::validation("file", "is required")),
+        }
+    };
+    let filename = clean_filename(field.file_name());
+    let mime_type = field
+        .content_type()
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    let mut staged = state.blobs.stage().await.map_err(storage_error)?;
+    loop {
+        let chunk = match field.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                staged.discard().await;
+                return Err(multipart_error(e));
+            }
+        };
+        if staged.size() + chunk.len() as u64 > state.blobs.max_bytes() {
+            staged.discard().await;
+            return Err(ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "attachment_too_large",
+                format!("Attachments are limited to {} MB", state.blobs.max_bytes() / (1024 * 1024)),
+            ));
+        }
+        if let Err(e) = staged.write(&chunk).await {
+            staged.discard().await;
+            return Err(storage_error(e));
+        }
+    }
+    let blob = staged.finish().await.map_err(storage_error)?;
+    let size = blob.size as i64;
+
+    let mut tx = state.db.begin().await?;
+
+    let attachment = sqlx::query_as!(
+        Attachment,
+        r#"INSERT INTO attachments (task_id, filename, mime_type, size, sha256, uploaded_by)
+           VALUES (?, ?, ?, ?, ?, ?)
+           RETURNING id as "id!", task_id, filename, mime_type, size, sha256, uploaded_by, created_at"#,
+        task_id,
+        filename,
+        mime_type,
+        size,
+        blob.sha256,
+        actor.0
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "create", "attachment", attachment.id)
+            .project(project_id)
+            .task(task_id)
+            .after(&attachment),
+    )
+    .await?;
+
+    // Moved into place while the row's write lock is held; see `collect_garbage`.
+    if let Err(e) = state.blobs.commit(&blob).await {
+        blob.discard().await;
+        return Err(storage_error(e));
+    }
+
+    webhooks::record_event(&mut tx, project_id, "attachment.created", &attachment).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project_id), "attachment.created", &attachment);
+
+    Ok((StatusCode::CREATED, Json(attachment)))
+}
+
+pub async fn download_attachment(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(id): Path<i64>,
+) -> Result<Response, ApiError> {
+    let (attachment, project_id) = find_attachment(&state.db, id).await?;
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let file = tokio::fs::File::open(state.blobs.path_for(&attachment.sha256))
+        .await
+        .map_err(|e| {
+            tracing::error!("Attachment {} has no readable contents: {}", id, e);
+            ApiError::internal("Attachment contents are missing")
+        })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachment.mime_type.clone()),
+            (header::CONTENT_LENGTH, attachment.size.to_string()),
+            (header::CONTENT_DISPOSITION, content_disposition(&attachment.filename)),
+            // Uploaded files are served as downloads, never rendered as our own pages.
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+pub async fn delete_attachment(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = state.db.begin().await?;
+
+    let (existing, project_id) = find_attachment(&mut *tx, id).await?;
+    require_role(&mut *tx, &user, project_id, Role::Editor).await?;
+
+    sqlx::query!(r#"DELETE FROM attachments WHERE id = ?"#, id)
+        .execute(&mut *tx)
+        .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "delete", "attachment", id)
+            .project(project_id)
+            .task(existing.task_id)
+            .before(&existing),
+    )
+    .await?;
+
+    let data = serde_json::json!({ "id": id, "task_id": existing.task_id });
+    webhooks::record_event(&mut tx, project_id, "attachment.deleted", &data).await?;
+
+    tx.commit().await?;
+
+    collect_garbage(&state.db, &state.blobs).await;
+
+    state.events.publish(Some(project_id), "attachment.deleted", &data);
+
+    Ok(StatusCode::NO_CONTENT)
+}
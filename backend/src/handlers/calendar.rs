@@ -0,0 +1,412 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::projects::AppState;
+use crate::access::{require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+
+/// A secret calendar subscription URL. `project_id` is `None` for the feed that
+/// covers every project.
+#[derive(Debug, Serialize)]
+pub struct CalendarFeed {
+    pub id: i64,
+    pub project_id: Option<i64>,
+    pub token: String,
+    pub path: String,
+    pub created_at: String,
+}
+
+struct FeedRow {
+    id: i64,
+    project_id: Option<i64>,
+    token: String,
+    created_at: String,
+}
+
+impl From<FeedRow> for CalendarFeed {
+    fn from(row: FeedRow) -> Self {
+        let path = match row.project_id {
+            Some(project_id) => format!("/api/projects/{}/calendar.ics?token={}", project_id, row.token),
+            None => format!("/api/calendar.ics?token={}", row.token),
+        };
+        CalendarFeed {
+            id: row.id,
+            project_id: row.project_id,
+            token: row.token,
+            path,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedQuery {
+    pub token: Option<String>,
+    /// `event` (default) renders deadlines as VEVENTs, which every calendar app shows;
+    /// `todo` renders VTODOs with start, due and completion status.
+    pub kind: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum EntryKind {
+    Event,
+    Todo,
+}
+
+struct CalendarTask {
+    id: i64,
+    project_name: String,
+    column_name: String,
+    title: String,
+    description: Option<String>,
+    priority: String,
+    created_at: String,
+    start_at: Option<String>,
+    due_at: String,
+    completed_at: Option<String>,
+    /// The `kind` of the task's column.
+    column_kind: String,
+}
+
+async fn ensure_project_exists(db: &SqlitePool, project_id: i64) -> Result<(), ApiError> {
+    sqlx::query_scalar!(r#"SELECT id FROM projects WHERE id = ?"#, project_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| ApiError::not_found("project_not_found", "Project not found"))?;
+    Ok(())
+}
+
+async fn find_feed(db: &SqlitePool, project_id: Option<i64>) -> Result<Option<FeedRow>, ApiError> {
+    let feed = sqlx::query_as!(
+        FeedRow,
+        r#"SELECT id as "id!", project_id, token, created_at
+           FROM calendar_feeds
+           WHERE project_id IS ?"#,
+        project_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(feed)
+}
+
+/// Issues a fresh token for the feed, replacing (and so revoking) any previous one.
+async fn rotate_feed(
+    state: &AppState,
+    actor: &Actor,
+    project_id: Option<i64>,
+) -> Result<CalendarFeed, ApiError> {
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query!(r#"DELETE FROM calendar_feeds WHERE project_id IS ?"#, project_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let feed = sqlx::query_as!(
+        FeedRow,
+        r#"INSERT INTO calendar_feeds (project_id, token)
+           VALUES (?, lower(hex(randomblob(24))))
+           RETURNING id as "id!", project_id, token, created_at"#,
+        project_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // The token is a credential, so only the fact of rotation is logged.
+    let mut entry = NewActivity::new(actor, "rotate", "calendar_feed", feed.id);
+    if let Some(project_id) = project_id {
+        entry = entry.project(project_id);
+    }
+    activity::record(&mut *tx, entry).await?;
+
+    tx.commit().await?;
+
+    Ok(feed.into())
+}
+
+async fn remove_feed(db: &SqlitePool, project_id: Option<i64>) -> Result<StatusCode, ApiError> {
+    let result = sqlx::query!(r#"DELETE FROM calendar_feeds WHERE project_id IS ?"#, project_id)
+        .execute(db)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError::not_found("calendar_feed_not_found", "Calendar feed not enabled"));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Feed management needs the project's admin role, since a feed URL grants read access.
+pub async fn get_project_feed(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+) -> Result<Json<CalendarFeed>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Admin).await?;
+    ensure_project_exists(&state.db, project_id).await?;
+
+    find_feed(&state.db, Some(project_id))
+        .await?
+        .map(|feed| Json(feed.into()))
+        .ok_or_else(|| ApiError::not_found("calendar_feed_not_found", "Calendar feed not enabled"))
+}
+
+/// Enables the project's feed, or rotates its token if it already exists.
+pub async fn rotate_project_feed(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(project_id): Path<i64>,
+) -> Result<Json<CalendarFeed>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Admin).await?;
+    ensure_project_exists(&state.db, project_id).await?;
+
+    Ok(Json(rotate_feed(&state, &actor, Some(project_id)).await?))
+}
+
+pub async fn delete_project_feed(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Admin).await?;
+
+    remove_feed(&state.db, Some(project_id)).await
+}
+
+/// The all-projects feed spans boards no single member may see, so only the admin key
+/// manages it.
+pub async fn get_global_feed(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<Json<CalendarFeed>, ApiError> {
+    user.ensure_admin()?;
+
+    find_feed(&state.db, None)
+        .await?
+        .map(|feed| Json(feed.into()))
+        .ok_or_else(|| ApiError::not_found("calendar_feed_not_found", "Calendar feed not enabled"))
+}
+
+/// Enables the all-projects feed, or rotates its token if it already exists.
+pub async fn rotate_global_feed(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+) -> Result<Json<CalendarFeed>, ApiError> {
+    user.ensure_admin()?;
+
+    Ok(Json(rotate_feed(&state, &actor, None).await?))
+}
+
+pub async fn delete_global_feed(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<StatusCode, ApiError> {
+    user.ensure_admin()?;
+
+    remove_feed(&state.db, None).await
+}
+
+/// `GET /api/projects/{id}/calendar.ics?token=...`, served outside the API key check.
+pub async fn project_calendar(
+    State(state): State<AppState>,
+    Path(project_id): Path<i64>,
+    Query(query): Query<FeedQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    render_feed(&state.db, Some(project_id), query).await
+}
+
+/// `GET /api/calendar.ics?token=...`, served outside the API key check.
+pub async fn global_calendar(
+    State(state): State<AppState>,
+    Query(query): Query<FeedQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    render_feed(&state.db, None, query).await
+}
+
+async fn render_feed(
+    db: &SqlitePool,
+    project_id: Option<i64>,
+    query: FeedQuery,
+) -> Result<impl IntoResponse, ApiError> {
+    let kind = match query.kind.as_deref() {
+        None | Some("event") => EntryKind::Event,
+        Some("todo") => EntryKind::Todo,
+        Some(_) => return Err(ApiError::invalid_query("kind", "kind must be 'event' or 'todo'")),
+    };
+
+    // A project token only opens its own project's feed, and the all-projects token
+    // only opens the all-projects feed.
+    let feed = find_feed(db, project_id).await?;
+    let authorised = match (&feed, query.token.as_deref()) {
+        (Some(feed), Some(token)) => feed.token == token,
+        _ => false,
+    };
+    if !authorised {
+        return Err(ApiError::unauthorized("invalid_feed_token", "Invalid or missing calendar feed token"));
+    }
+
+    let tasks = sqlx::query_as!(
+        CalendarTask,
+        r#"SELECT t.id as "id!", p.name as "project_name!", c.name as "column_name!",
+                  t.title as "title!", t.description, t.priority as "priority!",
+                  t.created_at as "created_at!", t.start_at, t.due_at as "due_at!", t.completed_at,
+                  c.kind as "column_kind!"
+           FROM tasks t
+           INNER JOIN projects p ON p.id = t.project_id
+           INNER JOIN columns c ON c.id = t.column_id
+           WHERE t.due_at IS NOT NULL AND (? IS NULL OR t.project_id = ?)
+           ORDER BY t.due_at ASC, t.id ASC"#,
+        project_id,
+        project_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let body = render_calendar(&tasks, kind);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        body,
+    ))
+}
+
+fn render_calendar(tasks: &[CalendarTask], kind: EntryKind) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//kanban-board//Task deadlines//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "X-WR-CALNAME:Kanban deadlines".to_string(),
+    ];
+
+    for task in tasks {
+        let Some(due) = ical_timestamp(&task.due_at) else {
+            continue;
+        };
+
+        match kind {
+            EntryKind::Event => lines.push("BEGIN:VEVENT".to_string()),
+            EntryKind::Todo => lines.push("BEGIN:VTODO".to_string()),
+        }
+        // Task ids never change or get reused, so the UID is stable across moves and edits.
+        lines.push(format!("UID:task-{}@kanban-board", task.id));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        if let Some(created) = ical_timestamp(&task.created_at) {
+            lines.push(format!("CREATED:{}", created));
+        }
+        // VEVENT has no "completed" status, so finished deadlines say so in the title.
+        let summary = if kind == EntryKind::Event && task.column_kind == "done" {
+            format!("Done: {}", task.title)
+        } else {
+            task.title.clone()
+        };
+        lines.push(format!("SUMMARY:{}", escape_text(&summary)));
+        if let Some(description) = task.description.as_deref().filter(|d| !d.is_empty()) {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        lines.push(format!(
+            "CATEGORIES:{},{}",
+            escape_text(&task.project_name),
+            escape_text(&task.column_name)
+        ));
+        lines.push(format!("PRIORITY:{}", ical_priority(&task.priority)));
+
+        match kind {
+            EntryKind::Event => {
+                // A deadline is a point in time: with no DTEND the event ends where it starts.
+                lines.push(format!("DTSTART:{}", due));
+                let status = match task.column_kind.as_str() {
+                    "archived" => "CANCELLED",
+                    "backlog" => "TENTATIVE",
+                    _ => "CONFIRMED",
+                };
+                lines.push(format!("STATUS:{}", status));
+                lines.push("TRANSP:TRANSPARENT".to_string());
+                lines.push("END:VEVENT".to_string());
+            }
+            EntryKind::Todo => {
+                if let Some(start) = task.start_at.as_deref().and_then(ical_timestamp) {
+                    lines.push(format!("DTSTART:{}", start));
+                }
+                lines.push(format!("DUE:{}", due));
+                let status = match task.column_kind.as_str() {
+                    "done" => "COMPLETED",
+                    "archived" => "CANCELLED",
+                    "backlog" => "NEEDS-ACTION",
+                    _ => "IN-PROCESS",
+                };
+                lines.push(format!("STATUS:{}", status));
+                if let Some(completed) = task.completed_at.as_deref().and_then(ical_timestamp) {
+                    lines.push(format!("COMPLETED:{}", completed));
+                }
+                lines.push("END:VTODO".to_string());
+            }
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold_line(line)).collect()
+}
+
+/// Stored timestamps are UTC `YYYY-MM-DD HH:MM:SS`.
+fn ical_timestamp(value: &str) -> Option<String> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|dt| dt.format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+/// RFC 5545 priorities run from 1 (highest) to 9 (lowest).
+fn ical_priority(priority: &str) -> u8 {
+    match priority {
+        "urgent" => 1,
+        "high" => 3,
+        "medium" => 5,
+        "low" => 9,
+        _ => 0,
+    }
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+        .replace('\r', "")
+}
+
+/// Content lines are CRLF-terminated and folded at 75 octets without splitting a character.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 4);
+    let mut width = 0;
+
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += len;
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
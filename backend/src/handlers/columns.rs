@@ -1,32 +1,144 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::{Executor, Sqlite};
 
 use super::projects::AppState;
+use crate::access::{project_of_column, require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::attachments::collect_garbage;
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+use crate::webhooks;
 
 #[derive(Debug, Serialize)]
 pub struct Column {
-    id: i64,
-    project_id: i64,
-    name: String,
-    sort_order: i64,
-    created_at: String,
+    pub id: i64,
+    pub project_id: i64,
+    pub name: String,
+    pub sort_order: i64,
+    pub wip_limit: Option<i64>,
+    pub kind: String,
+    pub created_at: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateColumnRequest {
     name: String,
+    wip_limit: Option<i64>,
+    /// Defaults to `todo`.
+    kind: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateColumnRequest {
-    name: String,
+    name: Option<String>,
+    /// Absent leaves the limit alone; `null` removes it.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    wip_limit: Option<Option<i64>>,
+    kind: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+/// What a column means for the work in it. Tasks in a `done` column are finished,
+/// whatever the column is called or wherever it sits on the board.
+pub const COLUMN_KINDS: [&str; 5] = ["backlog", "todo", "in_progress", "done", "archived"];
+
+/// Guesses what a column is for from its name, for imports that only carry names.
+pub fn guess_kind(name: &str) -> &'static str {
+    let name = name.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| name.contains(w));
+    if has(&["done", "complete", "finished", "shipped", "released"]) {
+        "done"
+    } else if has(&["backlog", "icebox", "someday", "ideas"]) {
+        "backlog"
+    } else if has(&["doing", "progress", "review", "testing", "wip"]) {
+        "in_progress"
+    } else {
+        "todo"
+    }
+}
+
+fn validate_kind(kind: &str) -> Result<(), ApiError> {
+    if !COLUMN_KINDS.contains(&kind) {
+        return Err(ApiError::validation(
+            "kind",
+            &format!("must be one of {}", COLUMN_KINDS.join(", ")),
+        ));
+    }
+    Ok(())
+}
+
+/// Lets an `Option<Option<T>>` field tell an absent key (`None`) from an explicit `null` (`Some(None)`).
+pub(crate) fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Returned alongside a task when it entered a column that is over its WIP limit
+/// and the project is in `soft` mode.
+#[derive(Debug, Clone, Serialize)]
+pub struct WipWarning {
+    pub code: &'static str,
+    pub message: String,
+    pub column_id: i64,
+    pub wip_limit: i64,
+    pub task_count: i64,
+}
+
+/// Checks whether `column_id` can take one more task. Returns a warning in `soft`
+/// mode and a 409 `wip_limit_exceeded` error in `hard` mode when it is full.
+pub(crate) async fn check_wip_limit<'e, E>(
+    executor: E,
+    column_id: i64,
+) -> Result<Option<WipWarning>, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let column = sqlx::query!(
+        r#"SELECT c.name, c.wip_limit, p.wip_mode,
+                  (SELECT COUNT(*) FROM tasks t WHERE t.column_id = c.id) as "task_count!: i64"
+           FROM columns c
+           INNER JOIN projects p ON p.id = c.project_id
+           WHERE c.id = ?"#,
+        column_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    let Some(column) = column else {
+        return Ok(None);
+    };
+    let Some(wip_limit) = column.wip_limit else {
+        return Ok(None);
+    };
+    if column.task_count < wip_limit {
+        return Ok(None);
+    }
+
+    let message = format!(
+        "Column '{}' is at its WIP limit ({}/{})",
+        column.name, column.task_count, wip_limit
+    );
+    if column.wip_mode == "hard" {
+        return Err(ApiError::conflict("wip_limit_exceeded", message));
+    }
+
+    Ok(Some(WipWarning {
+        code: "wip_limit_exceeded",
+        message,
+        column_id,
+        wip_limit,
+        task_count: column.task_count + 1,
+    }))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ReorderItem {
     id: i64,
     sort_order: i64,
@@ -34,12 +146,15 @@ pub struct ReorderItem {
 
 pub async fn list_columns(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(project_id): Path<i64>,
-) -> Result<Json<Vec<Column>>, StatusCode> {
+) -> Result<Json<Vec<Column>>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
     let columns = sqlx::query_as!(
         Column,
         r#"
-        SELECT id, project_id, name, sort_order, created_at
+        SELECT id, project_id, name, sort_order, wip_limit, kind, created_at
         FROM columns
         WHERE project_id = ?
         ORDER BY sort_order ASC
@@ -47,17 +162,26 @@ pub async fn list_columns(
         project_id
     )
     .fetch_all(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     Ok(Json(columns))
 }
 
 pub async fn create_column(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(project_id): Path<i64>,
     Json(payload): Json<CreateColumnRequest>,
-) -> Result<Json<Column>, StatusCode> {
+) -> Result<Json<Column>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::validation("name", "must not be empty"));
+    }
+    let kind = payload.kind.unwrap_or_else(|| "todo".to_string());
+    validate_kind(&kind)?;
+
     let max_sort_order: Option<i64> = sqlx::query_scalar(
         r#"
         SELECT MAX(sort_order)
@@ -67,109 +191,206 @@ pub async fn create_column(
     )
     .bind(project_id)
     .fetch_one(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .await?;
 
     let new_sort_order = max_sort_order.unwrap_or(0) + 1000;
 
+    let mut tx = state.db.begin().await?;
+
     let result = sqlx::query(
         r#"
-        INSERT INTO columns (project_id, name, sort_order)
-        VALUES (?, ?, ?)
+        INSERT INTO columns (project_id, name, sort_order, wip_limit, kind)
+        VALUES (?, ?, ?, ?, ?)
         "#,
     )
     .bind(project_id)
     .bind(&payload.name)
     .bind(new_sort_order)
-    .execute(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .bind(payload.wip_limit)
+    .bind(&kind)
+    .execute(&mut *tx)
+    .await?;
 
     let result_id = result.last_insert_rowid();
 
     let column = sqlx::query_as!(
         Column,
         r#"
-        SELECT id, project_id, name, sort_order, created_at
+        SELECT id, project_id, name, sort_order, wip_limit, kind, created_at
         FROM columns
         WHERE id = ?
         "#,
         result_id
     )
-    .fetch_one(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "create", "column", column.id)
+            .project(column.project_id)
+            .after(&column),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, column.project_id, "column.created", &column).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(column.project_id), "column.created", &column);
 
     Ok(Json(column))
 }
 
 pub async fn update_column(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
     Json(payload): Json<UpdateColumnRequest>,
-) -> Result<Json<Column>, StatusCode> {
+) -> Result<Json<Column>, ApiError> {
+    let project_id = project_of_column(&state.db, id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    if payload.name.as_deref().is_some_and(|n| n.trim().is_empty()) {
+        return Err(ApiError::validation("name", "must not be empty"));
+    }
+    if let Some(kind) = payload.kind.as_deref() {
+        validate_kind(kind)?;
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let existing = sqlx::query_as!(
+        Column,
+        r#"
+        SELECT id, project_id, name, sort_order, wip_limit, kind, created_at
+        FROM columns
+        WHERE id = ?
+        "#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::not_found("column_not_found", "Column not found"))?;
+
+    let name = payload.name.unwrap_or_else(|| existing.name.clone());
+    let wip_limit = payload.wip_limit.unwrap_or(existing.wip_limit);
+    let kind = payload.kind.unwrap_or_else(|| existing.kind.clone());
+
     sqlx::query(
         r#"
         UPDATE columns
-        SET name = ?
+        SET name = ?, wip_limit = ?, kind = ?
         WHERE id = ?
         "#,
     )
-    .bind(&payload.name)
+    .bind(&name)
+    .bind(wip_limit)
+    .bind(&kind)
     .bind(id)
-    .execute(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .execute(&mut *tx)
+    .await?;
+
+    // Tasks already in the column become finished, or stop being finished, with it.
+    if kind != existing.kind {
+        sqlx::query!(
+            r#"UPDATE tasks
+               SET completed_at = CASE WHEN ? = 'done' THEN COALESCE(completed_at, datetime('now')) END
+               WHERE column_id = ?"#,
+            kind,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
 
     let column = sqlx::query_as!(
         Column,
         r#"
-        SELECT id, project_id, name, sort_order, created_at
+        SELECT id, project_id, name, sort_order, wip_limit, kind, created_at
         FROM columns
         WHERE id = ?
         "#,
         id
     )
-    .fetch_one(&state.db)
-    .await
-    .map_err(|_| StatusCode::NOT_FOUND)?;
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "update", "column", id)
+            .project(column.project_id)
+            .before(&existing)
+            .after(&column),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, column.project_id, "column.updated", &column).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(column.project_id), "column.updated", &column);
 
     Ok(Json(column))
 }
 
 pub async fn delete_column(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
-) -> Result<StatusCode, StatusCode> {
-    let result = sqlx::query(
+) -> Result<StatusCode, ApiError> {
+    let project_id = project_of_column(&state.db, id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    let mut tx = state.db.begin().await?;
+
+    let deleted = sqlx::query_as!(
+        Column,
         r#"
         DELETE FROM columns
         WHERE id = ?
+        RETURNING id, project_id, name, sort_order, wip_limit, kind, created_at
         "#,
+        id
     )
-    .bind(id)
-    .execute(&state.db)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::not_found("column_not_found", "Column not found"))?;
 
-    if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
-    }
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "delete", "column", id)
+            .project(deleted.project_id)
+            .before(&deleted),
+    )
+    .await?;
+
+    let data = serde_json::json!({ "id": id });
+    webhooks::record_event(&mut tx, deleted.project_id, "column.deleted", &data).await?;
+
+    tx.commit().await?;
+
+    collect_garbage(&state.db, &state.blobs).await;
+
+    state.events.publish(Some(deleted.project_id), "column.deleted", &data);
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn reorder_columns(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(project_id): Path<i64>,
     Json(payload): Json<Vec<ReorderItem>>,
-) -> Result<StatusCode, StatusCode> {
-    let mut tx = state.db
-        .begin()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+) -> Result<StatusCode, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    let mut tx = state.db.begin().await?;
 
-    for item in payload {
+    for item in &payload {
         sqlx::query(
             r#"
             UPDATE columns
@@ -181,13 +402,22 @@ pub async fn reorder_columns(
         .bind(item.id)
         .bind(project_id)
         .execute(&mut *tx)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .await?;
     }
 
-    tx.commit()
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "reorder_columns", "project", project_id)
+            .project(project_id)
+            .after(&payload),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, project_id, "columns.reordered", &payload).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project_id), "columns.reordered", &payload);
 
     Ok(StatusCode::OK)
 }
@@ -0,0 +1,217 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite};
+
+use super::projects::AppState;
+use crate::access::{project_of_comment, project_of_task, require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+use crate::webhooks;
+
+/// A comment on a task. `body` is markdown; clients render it.
+#[derive(Debug, Serialize)]
+pub struct Comment {
+    pub id: i64,
+    pub task_id: i64,
+    pub author_id: Option<i64>,
+    pub author: String,
+    pub body: String,
+    pub created_at: String,
+    /// Set once the comment has been edited.
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommentRequest {
+    pub body: String,
+}
+
+async fn find_comment<'e, E>(executor: E, id: i64) -> Result<Comment, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as!(
+        Comment,
+        r#"SELECT id as "id!", task_id, author_id, author, body, created_at, updated_at
+           FROM comments WHERE id = ?"#,
+        id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| ApiError::not_found("comment_not_found", "Comment not found"))
+}
+
+/// Comments are changed by their author. Comments without a user behind them (written
+/// with the admin key or a token minted with it) can only be changed with the admin key.
+fn is_author(user: &CurrentUser, comment: &Comment) -> bool {
+    user.is_admin() || (user.id.is_some() && user.id == comment.author_id)
+}
+
+fn validate_body(body: &str) -> Result<(), ApiError> {
+    if body.trim().is_empty() {
+        return Err(ApiError::validation("body", "must not be empty"));
+    }
+    Ok(())
+}
+
+/// Oldest first, so the thread reads top to bottom.
+pub async fn list_comments(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(task_id): Path<i64>,
+) -> Result<Json<Vec<Comment>>, ApiError> {
+    let project_id = project_of_task(&state.db, task_id).await?;
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let comments = sqlx::query_as!(
+        Comment,
+        r#"SELECT id as "id!", task_id, author_id, author, body, created_at, updated_at
+           FROM comments WHERE task_id = ? ORDER BY id"#,
+        task_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(comments))
+}
+
+pub async fn create_comment(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(task_id): Path<i64>,
+    Json(payload): Json<CommentRequest>,
+) -> Result<(StatusCode, Json<Comment>), ApiError> {
+    let project_id = project_of_task(&state.db, task_id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+    validate_body(&payload.body)?;
+
+    let mut tx = state.db.begin().await?;
+
+    let comment = sqlx::query_as!(
+        Comment,
+        r#"INSERT INTO comments (task_id, author_id, author, body) VALUES (?, ?, ?, ?)
+           RETURNING id as "id!", task_id, author_id, author, body, created_at, updated_at"#,
+        task_id,
+        user.id,
+        actor.0,
+        payload.body
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "create", "comment", comment.id)
+            .project(project_id)
+            .task(task_id)
+            .after(&comment),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, project_id, "comment.created", &comment).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project_id), "comment.created", &comment);
+
+    Ok((StatusCode::CREATED, Json(comment)))
+}
+
+pub async fn update_comment(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i64>,
+    Json(payload): Json<CommentRequest>,
+) -> Result<Json<Comment>, ApiError> {
+    let project_id = project_of_comment(&state.db, id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+    validate_body(&payload.body)?;
+
+    let mut tx = state.db.begin().await?;
+
+    let existing = find_comment(&mut *tx, id).await?;
+    if !is_author(&user, &existing) {
+        return Err(ApiError::forbidden(
+            "not_comment_author",
+            "Only the author can edit a comment",
+        ));
+    }
+
+    let comment = sqlx::query_as!(
+        Comment,
+        r#"UPDATE comments SET body = ?, updated_at = datetime('now') WHERE id = ?
+           RETURNING id as "id!", task_id, author_id, author, body, created_at, updated_at"#,
+        payload.body,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "update", "comment", id)
+            .project(project_id)
+            .task(comment.task_id)
+            .before(&existing)
+            .after(&comment),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, project_id, "comment.updated", &comment).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project_id), "comment.updated", &comment);
+
+    Ok(Json(comment))
+}
+
+/// Authors can delete their comments, and project admins can delete any comment.
+pub async fn delete_comment(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    let project_id = project_of_comment(&state.db, id).await?;
+    let role = require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    let mut tx = state.db.begin().await?;
+
+    let existing = find_comment(&mut *tx, id).await?;
+    if role < Role::Admin && !is_author(&user, &existing) {
+        return Err(ApiError::forbidden(
+            "not_comment_author",
+            "Only the author or a project admin can delete a comment",
+        ));
+    }
+
+    sqlx::query!(r#"DELETE FROM comments WHERE id = ?"#, id)
+        .execute(&mut *tx)
+        .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "delete", "comment", id)
+            .project(project_id)
+            .task(existing.task_id)
+            .before(&existing),
+    )
+    .await?;
+
+    let data = serde_json::json!({ "id": id, "task_id": existing.task_id });
+    webhooks::record_event(&mut tx, project_id, "comment.deleted", &data).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project_id), "comment.deleted", &data);
+
+    Ok(StatusCode::NO_CONTENT)
+}
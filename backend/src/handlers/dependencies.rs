@@ -0,0 +1,316 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite};
+
+use super::projects::AppState;
+use crate::access::{project_of_task, require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+use crate::webhooks;
+
+/// A link read as "`source_id` `kind` `target_id`", e.g. task 4 blocks task 7.
+#[derive(Debug, Serialize)]
+pub struct Dependency {
+    pub id: i64,
+    pub source_id: i64,
+    pub target_id: i64,
+    pub kind: String,
+    pub created_at: String,
+}
+
+/// The other end of a link, as listed on a task.
+#[derive(Debug, Serialize)]
+pub struct LinkedTask {
+    /// The link's id, for removing it.
+    pub dependency_id: i64,
+    pub id: i64,
+    pub title: String,
+    pub column_id: i64,
+    /// Whether the task sits in a `done` column.
+    pub done: bool,
+}
+
+/// A task's links in both directions.
+#[derive(Debug, Default, Serialize)]
+pub struct TaskLinks {
+    pub blocks: Vec<LinkedTask>,
+    pub blocked_by: Vec<LinkedTask>,
+    pub relates_to: Vec<LinkedTask>,
+    pub duplicates: Vec<LinkedTask>,
+    pub duplicated_by: Vec<LinkedTask>,
+}
+
+/// `kind` is from the point of view of the task in the path: `blocks`, `blocked_by`,
+/// `relates_to`, `duplicates` or `duplicated_by`.
+#[derive(Debug, Deserialize)]
+pub struct CreateDependencyRequest {
+    pub task_id: i64,
+    pub kind: String,
+}
+
+/// Returned alongside a task that moved ahead while tasks blocking it are still open
+/// and the project is in `soft` blocker mode.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockedWarning {
+    pub code: &'static str,
+    pub message: String,
+    pub task_id: i64,
+    pub blocker_ids: Vec<i64>,
+}
+
+/// Checks a move of `task_id` from one column to another. Moving to a later column
+/// while a blocker isn't done returns a warning in `soft` mode and a 409
+/// `blocked_by_open_tasks` error in `hard` mode; moving back is always allowed.
+pub(crate) async fn check_blockers<'e, E>(
+    executor: E,
+    task_id: i64,
+    from_column_id: i64,
+    to_column_id: i64,
+) -> Result<Option<BlockedWarning>, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let blockers = sqlx::query!(
+        r#"SELECT b.id as "id!", b.title, p.blocker_mode
+           FROM task_dependencies d
+           INNER JOIN tasks b ON b.id = d.source_id
+           INNER JOIN tasks t ON t.id = d.target_id
+           INNER JOIN projects p ON p.id = t.project_id
+           INNER JOIN columns bc ON bc.id = b.column_id
+           WHERE d.target_id = ? AND d.kind = 'blocks'
+             AND (SELECT sort_order FROM columns WHERE id = ?) > (SELECT sort_order FROM columns WHERE id = ?)
+             AND bc.kind <> 'done'
+           ORDER BY b.id"#,
+        task_id,
+        to_column_id,
+        from_column_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let Some(first) = blockers.first() else {
+        return Ok(None);
+    };
+
+    let titles: Vec<String> = blockers.iter().map(|b| format!("'{}'", b.title)).collect();
+    let message = format!("Task is blocked by open tasks: {}", titles.join(", "));
+    if first.blocker_mode == "hard" {
+        return Err(ApiError::conflict("blocked_by_open_tasks", message));
+    }
+
+    Ok(Some(BlockedWarning {
+        code: "blocked_by_open_tasks",
+        message,
+        task_id,
+        blocker_ids: blockers.iter().map(|b| b.id).collect(),
+    }))
+}
+
+/// Every link the task is part of, grouped by how it relates to the task.
+pub(crate) async fn task_links<'e, E>(executor: E, task_id: i64) -> Result<TaskLinks, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let rows = sqlx::query!(
+        r#"SELECT d.id as "dependency_id!", d.kind, d.source_id = ? as "outgoing!: bool",
+                  t.id as "id!", t.title, t.column_id, c.kind = 'done' as "done!: bool"
+           FROM task_dependencies d
+           INNER JOIN tasks t ON t.id = CASE WHEN d.source_id = ? THEN d.target_id ELSE d.source_id END
+           INNER JOIN columns c ON c.id = t.column_id
+           WHERE d.source_id = ? OR d.target_id = ?
+           ORDER BY d.id"#,
+        task_id,
+        task_id,
+        task_id,
+        task_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let mut links = TaskLinks::default();
+    for row in rows {
+        let list = match (row.kind.as_str(), row.outgoing) {
+            ("blocks", true) => &mut links.blocks,
+            ("blocks", false) => &mut links.blocked_by,
+            ("duplicates", true) => &mut links.duplicates,
+            ("duplicates", false) => &mut links.duplicated_by,
+            _ => &mut links.relates_to,
+        };
+        list.push(LinkedTask {
+            dependency_id: row.dependency_id,
+            id: row.id,
+            title: row.title,
+            column_id: row.column_id,
+            done: row.done,
+        });
+    }
+
+    Ok(links)
+}
+
+pub async fn list_dependencies(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(task_id): Path<i64>,
+) -> Result<Json<TaskLinks>, ApiError> {
+    let project_id = project_of_task(&state.db, task_id).await?;
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    Ok(Json(task_links(&state.db, task_id).await?))
+}
+
+/// Links the task in the path to another task on the same board. `blocks` links may
+/// not form a cycle, since no task in it could ever be finished first.
+pub async fn create_dependency(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(task_id): Path<i64>,
+    Json(payload): Json<CreateDependencyRequest>,
+) -> Result<(StatusCode, Json<Dependency>), ApiError> {
+    let project_id = project_of_task(&state.db, task_id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    let (source_id, target_id, kind) = match payload.kind.as_str() {
+        "blocks" | "relates_to" | "duplicates" => (task_id, payload.task_id, payload.kind.as_str()),
+        "blocked_by" => (payload.task_id, task_id, "blocks"),
+        "duplicated_by" => (payload.task_id, task_id, "duplicates"),
+        _ => {
+            return Err(ApiError::validation(
+                "kind",
+                "must be blocks, blocked_by, relates_to, duplicates or duplicated_by",
+            ))
+        }
+    };
+    if payload.task_id == task_id {
+        return Err(ApiError::validation("task_id", "must be a different task"));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let other_project_id = sqlx::query_scalar!(r#"SELECT project_id FROM tasks WHERE id = ?"#, payload.task_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if other_project_id != Some(project_id) {
+        return Err(ApiError::validation("task_id", "must be a task in the same project"));
+    }
+
+    // `relates_to` has no direction, so a link either way counts.
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(
+               SELECT 1 FROM task_dependencies
+               WHERE kind = ? AND ((source_id = ? AND target_id = ?)
+                                   OR (kind = 'relates_to' AND source_id = ? AND target_id = ?))
+           ) as "exists!: bool""#,
+        kind,
+        source_id,
+        target_id,
+        target_id,
+        source_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if exists {
+        return Err(ApiError::conflict("dependency_exists", "These tasks are already linked this way"));
+    }
+
+    if kind == "blocks" {
+        // A cycle appears if the source is already downstream of the target.
+        let cycle = sqlx::query_scalar!(
+            r#"WITH RECURSIVE downstream(id) AS (
+                   SELECT ?
+                   UNION
+                   SELECT d.target_id FROM task_dependencies d
+                   INNER JOIN downstream ds ON d.source_id = ds.id
+                   WHERE d.kind = 'blocks'
+               )
+               SELECT EXISTS(SELECT 1 FROM downstream WHERE id = ?) as "cycle!: bool""#,
+            target_id,
+            source_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if cycle {
+            return Err(ApiError::conflict(
+                "dependency_cycle",
+                format!("Task {} already depends on task {}, so it can't block it", target_id, source_id),
+            ));
+        }
+    }
+
+    let dependency = sqlx::query_as!(
+        Dependency,
+        r#"INSERT INTO task_dependencies (source_id, target_id, kind) VALUES (?, ?, ?)
+           RETURNING id as "id!", source_id, target_id, kind, created_at"#,
+        source_id,
+        target_id,
+        kind
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "create", "dependency", dependency.id)
+            .project(project_id)
+            .task(task_id)
+            .after(&dependency),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, project_id, "dependency.created", &dependency).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project_id), "dependency.created", &dependency);
+
+    Ok((StatusCode::CREATED, Json(dependency)))
+}
+
+pub async fn delete_dependency(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = state.db.begin().await?;
+
+    let existing = sqlx::query_as!(
+        Dependency,
+        r#"SELECT id as "id!", source_id, target_id, kind, created_at FROM task_dependencies WHERE id = ?"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::not_found("dependency_not_found", "Dependency not found"))?;
+
+    let project_id = project_of_task(&mut *tx, existing.source_id).await?;
+    require_role(&mut *tx, &user, project_id, Role::Editor).await?;
+
+    sqlx::query!(r#"DELETE FROM task_dependencies WHERE id = ?"#, id)
+        .execute(&mut *tx)
+        .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "delete", "dependency", id)
+            .project(project_id)
+            .task(existing.source_id)
+            .before(&existing),
+    )
+    .await?;
+
+    let data = serde_json::json!({ "id": id, "source_id": existing.source_id, "target_id": existing.target_id });
+    webhooks::record_event(&mut tx, project_id, "dependency.deleted", &data).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project_id), "dependency.deleted", &data);
+
+    Ok(StatusCode::NO_CONTENT)
+}
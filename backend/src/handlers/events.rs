@@ -0,0 +1,68 @@
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Extension,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::convert::Infallible;
+use tokio_stream::wrappers::BroadcastStream;
+
+use super::projects::AppState;
+use crate::access::{require_role, Role};
+use crate::error::ApiError;
+use crate::events::BoardEvent;
+use crate::middleware::CurrentUser;
+
+fn to_sse_event(event: &BoardEvent) -> Event {
+    Event::default()
+        .id(event.id.to_string())
+        .event(&event.kind)
+        .json_data(&event.data)
+        .unwrap_or_else(|_| Event::default().id(event.id.to_string()).event(&event.kind))
+}
+
+pub async fn project_events(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let exists: bool = sqlx::query_scalar("SELECT COUNT(*) > 0 FROM projects WHERE id = ?")
+        .bind(project_id)
+        .fetch_one(&state.db)
+        .await?;
+
+    if !exists {
+        return Err(ApiError::not_found("project_not_found", "Project not found"));
+    }
+
+    let last_event_id = headers
+        .get("Last-Event-ID")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .unwrap_or(0);
+
+    // Subscribe before reading the buffer so nothing published in between is lost.
+    let receiver = state.events.subscribe();
+    let replay = state.events.replay_since(project_id, last_event_id);
+    let replayed_up_to = replay.last().map(|e| e.id).unwrap_or(last_event_id);
+
+    let replay_stream = stream::iter(replay.into_iter().map(|e| Ok(to_sse_event(&e))));
+    let live_stream = BroadcastStream::new(receiver).filter_map(move |result| async move {
+        match result {
+            Ok(event) if event.id > replayed_up_to && event.visible_to(project_id) => {
+                Some(Ok(to_sse_event(&event)))
+            }
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("Event stream for project {} lagged: {}", project_id, e);
+                None
+            }
+        }
+    });
+
+    Ok(Sse::new(replay_stream.chain(live_stream)).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,606 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::columns::COLUMN_KINDS;
+use super::projects::{AppState, Project};
+use super::tasks::parse_timestamp;
+use crate::access::{require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+use crate::trello::{self, UnmappedField};
+use crate::webhooks;
+
+pub const EXPORT_FORMAT: &str = "kanban-board";
+pub const EXPORT_VERSION: i64 = 1;
+
+/// A whole board as one document. Ids only link records within the document: they are
+/// numbered from 1 on export and replaced with fresh ones on import, so a document can
+/// be imported into any instance, any number of times.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BoardExport {
+    pub format: String,
+    pub version: i64,
+    #[serde(default)]
+    pub exported_at: Option<String>,
+    pub project: ExportProject,
+    pub columns: Vec<ExportColumn>,
+    #[serde(default)]
+    pub tasks: Vec<ExportTask>,
+    #[serde(default)]
+    pub subtasks: Vec<ExportSubtask>,
+    #[serde(default)]
+    pub tags: Vec<ExportTag>,
+    #[serde(default)]
+    pub task_tags: Vec<ExportTaskTag>,
+    #[serde(default)]
+    pub linked_paths: Vec<ExportLinkedPath>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportProject {
+    pub name: String,
+    pub wip_mode: String,
+    pub blocker_mode: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportColumn {
+    pub id: i64,
+    pub name: String,
+    pub sort_order: i64,
+    pub wip_limit: Option<i64>,
+    pub kind: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportTask {
+    pub id: i64,
+    pub column_id: i64,
+    pub parent_id: Option<i64>,
+    pub title: String,
+    pub description: Option<String>,
+    pub priority: Option<String>,
+    pub sort_order: i64,
+    pub source_tag: Option<String>,
+    /// The assignee's user name; on import it is matched to a user of the same name.
+    pub assignee: Option<String>,
+    pub due_at: Option<String>,
+    pub start_at: Option<String>,
+    pub created_at: Option<String>,
+    pub completed_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportSubtask {
+    pub id: i64,
+    pub task_id: i64,
+    pub title: String,
+    pub done: bool,
+    pub sort_order: i64,
+}
+
+/// Tags are shared between projects, so on import one with the same name is reused.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportTag {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportTaskTag {
+    pub task_id: i64,
+    pub tag_id: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportLinkedPath {
+    pub path: String,
+    pub hostname: Option<String>,
+    pub default_column_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportQuery {
+    /// Overrides the project name in the document, e.g. when importing a second copy.
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportSummary {
+    pub project: Project,
+    pub columns: usize,
+    pub tasks: usize,
+    pub subtasks: usize,
+    pub tags_created: usize,
+    /// Paths already linked to another project on this instance, which were left alone.
+    pub skipped_linked_paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrelloImportSummary {
+    #[serde(flatten)]
+    pub summary: ImportSummary,
+    /// Trello fields that held data but had nowhere to go, e.g. `cards.idMembers`.
+    pub unmapped: Vec<UnmappedField>,
+}
+
+/// Numbers ids from 1 in the order they are given.
+fn renumber(ids: impl IntoIterator<Item = i64>) -> HashMap<i64, i64> {
+    ids.into_iter().zip(1..).collect()
+}
+
+/// Looks up a document id, failing with a 422 on `field` if nothing in the document has it.
+fn resolve(ids: &HashMap<i64, i64>, id: i64, field: &str, what: &str) -> Result<i64, ApiError> {
+    ids.get(&id)
+        .copied()
+        .ok_or_else(|| ApiError::validation(field, &format!("refers to {} {}, which is not in the document", what, id)))
+}
+
+/// Fails with a 422 on `field` if two records in it share an id.
+fn ensure_unique_ids(ids: impl IntoIterator<Item = i64>, field: &str) -> Result<(), ApiError> {
+    let mut seen = HashSet::new();
+    match ids.into_iter().find(|id| !seen.insert(*id)) {
+        Some(id) => Err(ApiError::validation(field, &format!("id {} is used more than once", id))),
+        None => Ok(()),
+    }
+}
+
+/// A task that is its own ancestor through `parent_id` links, if the document has one.
+fn parent_cycle(tasks: &[ExportTask]) -> Option<i64> {
+    let parents: HashMap<i64, i64> = tasks.iter().filter_map(|t| Some((t.id, t.parent_id?))).collect();
+    tasks.iter().map(|t| t.id).find(|&start| {
+        let mut current = start;
+        // A chain with more links than there are parents has to loop.
+        for _ in 0..parents.len() {
+            match parents.get(&current) {
+                Some(&parent) if parent == start => return true,
+                Some(&parent) => current = parent,
+                None => return false,
+            }
+        }
+        false
+    })
+}
+
+/// `GET /api/projects/{id}/export`
+pub async fn export_project(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+) -> Result<Json<BoardExport>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let project = sqlx::query!(
+        r#"SELECT name, wip_mode, blocker_mode FROM projects WHERE id = ?"#,
+        project_id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ApiError::not_found("project_not_found", "Project not found"))?;
+
+    let columns = sqlx::query!(
+        r#"SELECT id as "id!", name, sort_order, wip_limit, kind FROM columns
+           WHERE project_id = ? ORDER BY sort_order, id"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+    let column_ids = renumber(columns.iter().map(|c| c.id));
+
+    let tasks = sqlx::query!(
+        r#"SELECT t.id as "id!", t.column_id, t.parent_id, t.title, t.description, t.priority, t.sort_order,
+                  t.source_tag, u.name as "assignee?", t.due_at, t.start_at, t.created_at, t.completed_at
+           FROM tasks t
+           LEFT JOIN users u ON u.id = t.assignee_id
+           WHERE t.project_id = ?
+           ORDER BY t.id"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+    let task_ids = renumber(tasks.iter().map(|t| t.id));
+
+    let subtasks = sqlx::query!(
+        r#"SELECT s.id as "id!", s.task_id, s.title, s.done, s.sort_order
+           FROM subtasks s
+           INNER JOIN tasks t ON t.id = s.task_id
+           WHERE t.project_id = ?
+           ORDER BY s.id"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let tags = sqlx::query!(
+        r#"SELECT DISTINCT tg.id as "id!", tg.name, tg.color
+           FROM tags tg
+           INNER JOIN task_tags tt ON tt.tag_id = tg.id
+           INNER JOIN tasks t ON t.id = tt.task_id
+           WHERE t.project_id = ?
+           ORDER BY tg.name"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+    let tag_ids = renumber(tags.iter().map(|t| t.id));
+
+    let task_tags = sqlx::query!(
+        r#"SELECT tt.task_id, tt.tag_id
+           FROM task_tags tt
+           INNER JOIN tasks t ON t.id = tt.task_id
+           WHERE t.project_id = ?"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let linked_paths = sqlx::query!(
+        r#"SELECT path, hostname, default_column_id FROM linked_paths WHERE project_id = ? ORDER BY path"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut task_tags: Vec<ExportTaskTag> = task_tags
+        .into_iter()
+        .filter_map(|tt| {
+            Some(ExportTaskTag {
+                task_id: *task_ids.get(&tt.task_id)?,
+                tag_id: *tag_ids.get(&tt.tag_id)?,
+            })
+        })
+        .collect();
+    task_tags.sort_by_key(|tt| (tt.task_id, tt.tag_id));
+
+    Ok(Json(BoardExport {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        exported_at: Some(Utc::now().to_rfc3339()),
+        project: ExportProject {
+            name: project.name,
+            wip_mode: project.wip_mode,
+            blocker_mode: project.blocker_mode,
+        },
+        columns: columns
+            .into_iter()
+            .map(|c| ExportColumn {
+                id: column_ids[&c.id],
+                name: c.name,
+                sort_order: c.sort_order,
+                wip_limit: c.wip_limit,
+                kind: c.kind,
+            })
+            .collect(),
+        tasks: tasks
+            .into_iter()
+            .map(|t| ExportTask {
+                id: task_ids[&t.id],
+                column_id: column_ids[&t.column_id],
+                parent_id: t.parent_id.and_then(|id| task_ids.get(&id).copied()),
+                title: t.title,
+                description: t.description,
+                priority: t.priority,
+                sort_order: t.sort_order,
+                source_tag: Some(t.source_tag),
+                assignee: t.assignee,
+                due_at: t.due_at,
+                start_at: t.start_at,
+                created_at: Some(t.created_at),
+                completed_at: t.completed_at,
+            })
+            .collect(),
+        subtasks: subtasks
+            .into_iter()
+            .zip(1..)
+            .map(|(s, id)| ExportSubtask {
+                id,
+                task_id: task_ids[&s.task_id],
+                title: s.title,
+                done: s.done != 0,
+                sort_order: s.sort_order,
+            })
+            .collect(),
+        tags: tags
+            .into_iter()
+            .map(|t| ExportTag {
+                id: tag_ids[&t.id],
+                name: t.name,
+                color: t.color,
+            })
+            .collect(),
+        task_tags,
+        linked_paths: linked_paths
+            .into_iter()
+            .map(|lp| ExportLinkedPath {
+                path: lp.path,
+                hostname: lp.hostname,
+                default_column_id: lp.default_column_id.and_then(|id| column_ids.get(&id).copied()),
+            })
+            .collect(),
+    }))
+}
+
+/// `POST /api/projects/import?name=` recreates an exported board as a new project.
+pub async fn import_project(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Query(query): Query<ImportQuery>,
+    Json(document): Json<BoardExport>,
+) -> Result<(StatusCode, Json<ImportSummary>), ApiError> {
+    if document.format != EXPORT_FORMAT {
+        return Err(ApiError::validation("format", &format!("must be '{}'", EXPORT_FORMAT)));
+    }
+    if !(1..=EXPORT_VERSION).contains(&document.version) {
+        return Err(ApiError::validation(
+            "version",
+            &format!("version {} is not supported; this server reads up to {}", document.version, EXPORT_VERSION),
+        ));
+    }
+
+    let summary = import_board(&state, &user, &actor, document, query.name).await?;
+
+    Ok((StatusCode::CREATED, Json(summary)))
+}
+
+/// `POST /api/projects/import/trello?name=` takes a Trello board's JSON export; see
+/// [`trello::convert`] for how it maps.
+pub async fn import_trello(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Query(query): Query<ImportQuery>,
+    Json(board): Json<serde_json::Value>,
+) -> Result<(StatusCode, Json<TrelloImportSummary>), ApiError> {
+    let (document, unmapped) = trello::convert(board)
+        .map_err(|e| ApiError::validation("board", &format!("is not a Trello board export: {}", e)))?;
+
+    let summary = import_board(&state, &user, &actor, document, query.name).await?;
+
+    Ok((StatusCode::CREATED, Json(TrelloImportSummary { summary, unmapped })))
+}
+
+/// Creates a project from `document` in one transaction, named `name` if given. Like
+/// creating a project, a personal-token caller becomes its admin.
+pub(crate) async fn import_board(
+    state: &AppState,
+    user: &CurrentUser,
+    actor: &Actor,
+    document: BoardExport,
+    name: Option<String>,
+) -> Result<ImportSummary, ApiError> {
+    if user.project_ids.is_some() {
+        return Err(ApiError::forbidden(
+            "project_not_allowed",
+            "This token is limited to existing projects",
+        ));
+    }
+    let name = name.unwrap_or(document.project.name);
+    if name.trim().is_empty() {
+        return Err(ApiError::validation("name", "must not be empty"));
+    }
+    if document.columns.is_empty() {
+        return Err(ApiError::validation("columns", "a board needs at least one column"));
+    }
+    if let Some(column) = document.columns.iter().find(|c| !COLUMN_KINDS.contains(&c.kind.as_str())) {
+        return Err(ApiError::validation(
+            "columns",
+            &format!("column '{}' has unknown kind '{}'", column.name, column.kind),
+        ));
+    }
+
+    ensure_unique_ids(document.columns.iter().map(|c| c.id), "columns")?;
+    ensure_unique_ids(document.tasks.iter().map(|t| t.id), "tasks")?;
+    ensure_unique_ids(document.tags.iter().map(|t| t.id), "tags")?;
+    if let Some(id) = parent_cycle(&document.tasks) {
+        return Err(ApiError::bad_request(
+            "parent_cycle",
+            format!("Task {} is placed under itself or one of its children", id),
+        )
+        .with_detail("tasks", &format!("task {} is its own ancestor", id)));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let project = sqlx::query_as!(
+        Project,
+        r#"INSERT INTO projects (name, sort_order, wip_mode, blocker_mode)
+           VALUES (?, (SELECT COALESCE(MAX(sort_order), 0) + 1000 FROM projects), ?, ?)
+           RETURNING id, name, sort_order, wip_mode, blocker_mode, created_at"#,
+        name,
+        document.project.wip_mode,
+        document.project.blocker_mode
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let mut column_ids = HashMap::new();
+    for column in &document.columns {
+        let id = sqlx::query_scalar!(
+            r#"INSERT INTO columns (project_id, name, sort_order, wip_limit, kind) VALUES (?, ?, ?, ?, ?)
+               RETURNING id as "id!""#,
+            project.id,
+            column.name,
+            column.sort_order,
+            column.wip_limit,
+            column.kind
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        column_ids.insert(column.id, id);
+    }
+
+    let users: HashMap<String, i64> = sqlx::query!(r#"SELECT id as "id!", name FROM users"#)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|u| (u.name, u.id))
+        .collect();
+
+    // Parents may come after their children in the document, so they are linked once
+    // every task exists. `completed_at` follows the column's kind, as it does for moves.
+    let mut task_ids = HashMap::new();
+    for task in &document.tasks {
+        let column_id = resolve(&column_ids, task.column_id, "tasks", "column")?;
+        let priority = task.priority.as_deref().unwrap_or("medium");
+        let source_tag = task.source_tag.as_deref().unwrap_or("manual");
+        let [due_at, start_at, created_at, completed_at] =
+            [&task.due_at, &task.start_at, &task.created_at, &task.completed_at].map(|value| {
+                value
+                    .as_deref()
+                    .map(parse_timestamp)
+                    .transpose()
+                    .map_err(|e| ApiError::validation("tasks", &format!("task {}: {}", task.id, e)))
+            });
+        let (due_at, start_at, created_at, completed_at) = (due_at?, start_at?, created_at?, completed_at?);
+        let assignee_id = task.assignee.as_ref().and_then(|name| users.get(name).copied());
+        let id = sqlx::query_scalar!(
+            r#"INSERT INTO tasks (project_id, column_id, title, description, priority, sort_order, source_tag,
+                                  assignee_id, due_at, start_at, created_at, completed_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, datetime('now')),
+                       (SELECT CASE WHEN kind = 'done' THEN COALESCE(?, datetime('now')) END FROM columns WHERE id = ?))
+               RETURNING id as "id!""#,
+            project.id,
+            column_id,
+            task.title,
+            task.description,
+            priority,
+            task.sort_order,
+            source_tag,
+            assignee_id,
+            due_at,
+            start_at,
+            created_at,
+            completed_at,
+            column_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        task_ids.insert(task.id, id);
+    }
+    for task in document.tasks.iter().filter(|t| t.parent_id.is_some()) {
+        let parent_id = task
+            .parent_id
+            .map(|parent| resolve(&task_ids, parent, "tasks", "parent task"))
+            .transpose()?;
+        sqlx::query!(r#"UPDATE tasks SET parent_id = ? WHERE id = ?"#, parent_id, task_ids[&task.id])
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for subtask in &document.subtasks {
+        let task_id = resolve(&task_ids, subtask.task_id, "subtasks", "task")?;
+        sqlx::query!(
+            r#"INSERT INTO subtasks (task_id, title, done, sort_order) VALUES (?, ?, ?, ?)"#,
+            task_id,
+            subtask.title,
+            subtask.done,
+            subtask.sort_order
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let mut tag_ids = HashMap::new();
+    let mut tags_created = 0;
+    for tag in &document.tags {
+        let existing = sqlx::query_scalar!(r#"SELECT id as "id!" FROM tags WHERE name = ?"#, tag.name)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                tags_created += 1;
+                sqlx::query_scalar!(
+                    r#"INSERT INTO tags (name, color) VALUES (?, ?) RETURNING id as "id!""#,
+                    tag.name,
+                    tag.color
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+        tag_ids.insert(tag.id, id);
+    }
+    for task_tag in &document.task_tags {
+        let task_id = resolve(&task_ids, task_tag.task_id, "task_tags", "task")?;
+        let tag_id = resolve(&tag_ids, task_tag.tag_id, "task_tags", "tag")?;
+        sqlx::query!(
+            r#"INSERT OR IGNORE INTO task_tags (task_id, tag_id) VALUES (?, ?)"#,
+            task_id,
+            tag_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let mut skipped_linked_paths = Vec::new();
+    for linked in &document.linked_paths {
+        let taken = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM linked_paths WHERE path = ?) as "taken!: bool""#,
+            linked.path
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if taken {
+            skipped_linked_paths.push(linked.path.clone());
+            continue;
+        }
+        let default_column_id = linked
+            .default_column_id
+            .map(|id| resolve(&column_ids, id, "linked_paths", "column"))
+            .transpose()?;
+        sqlx::query!(
+            r#"INSERT INTO linked_paths (project_id, path, hostname, default_column_id) VALUES (?, ?, ?, ?)"#,
+            project.id,
+            linked.path,
+            linked.hostname,
+            default_column_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    if let Some(user_id) = user.id {
+        sqlx::query!(
+            r#"INSERT INTO project_members (project_id, user_id, role) VALUES (?, ?, 'admin')"#,
+            project.id,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let summary = ImportSummary {
+        project,
+        columns: column_ids.len(),
+        tasks: task_ids.len(),
+        subtasks: document.subtasks.len(),
+        tags_created,
+        skipped_linked_paths,
+    };
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(actor, "import", "project", summary.project.id)
+            .project(summary.project.id)
+            .after(&summary),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, summary.project.id, "project.created", &summary.project).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(summary.project.id), "project.created", &summary.project);
+
+    Ok(summary)
+}
@@ -1,11 +1,15 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 
 use super::projects::AppState;
+use crate::access::{require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LinkedPath {
@@ -26,8 +30,11 @@ pub struct CreateLinkedPathRequest {
 
 pub async fn list_linked_paths(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(project_id): Path<i64>,
-) -> Result<Json<Vec<LinkedPath>>, (StatusCode, String)> {
+) -> Result<Json<Vec<LinkedPath>>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
     let paths = sqlx::query_as!(
         LinkedPath,
         r#"SELECT id as "id!", project_id as "project_id!", path, hostname, default_column_id, created_at
@@ -37,17 +44,37 @@ pub async fn list_linked_paths(
         project_id
     )
     .fetch_all(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    .await?;
 
     Ok(Json(paths))
 }
 
+/// Re-linking a path that belongs to another project also needs edit rights there.
 pub async fn create_linked_path(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(project_id): Path<i64>,
     Json(payload): Json<CreateLinkedPathRequest>,
-) -> Result<Json<LinkedPath>, (StatusCode, String)> {
+) -> Result<Json<LinkedPath>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    if payload.path.trim().is_empty() {
+        return Err(ApiError::validation("path", "must not be empty"));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let current_owner = sqlx::query_scalar!(
+        r#"SELECT project_id FROM linked_paths WHERE path = ?"#,
+        payload.path
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(owner) = current_owner.filter(|owner| *owner != project_id) {
+        require_role(&mut *tx, &user, owner, Role::Editor).await?;
+    }
+
     let result = sqlx::query!(
         r#"INSERT INTO linked_paths (project_id, path, hostname, default_column_id)
            VALUES (?, ?, ?, ?)
@@ -60,9 +87,8 @@ pub async fn create_linked_path(
         payload.hostname,
         payload.default_column_id
     )
-    .execute(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    .execute(&mut *tx)
+    .await?;
 
     let id = result.last_insert_rowid();
 
@@ -74,37 +100,91 @@ pub async fn create_linked_path(
         id,
         payload.path
     )
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "link", "linked_path", path.id)
+            .project(path.project_id)
+            .after(&path),
+    )
+    .await?;
+
+    tx.commit().await?;
 
     Ok(Json(path))
 }
 
 pub async fn delete_linked_path(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    sqlx::query!("DELETE FROM linked_paths WHERE id = ?", id)
-        .execute(&state.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+) -> Result<StatusCode, ApiError> {
+    let mut tx = state.db.begin().await?;
+
+    let deleted = sqlx::query_as!(
+        LinkedPath,
+        r#"DELETE FROM linked_paths WHERE id = ?
+           RETURNING id as "id!", project_id as "project_id!", path, hostname, default_column_id, created_at"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    finish_unlink(tx, &user, &actor, deleted).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn delete_linked_path_by_path(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Json(payload): Json<DeleteByPathRequest>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    sqlx::query!("DELETE FROM linked_paths WHERE path = ?", payload.path)
-        .execute(&state.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+) -> Result<StatusCode, ApiError> {
+    let mut tx = state.db.begin().await?;
+
+    let deleted = sqlx::query_as!(
+        LinkedPath,
+        r#"DELETE FROM linked_paths WHERE path = ?
+           RETURNING id as "id!", project_id as "project_id!", path, hostname, default_column_id, created_at"#,
+        payload.path
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    finish_unlink(tx, &user, &actor, deleted).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// Deleting an unknown link is a no-op, so there is only something to record when a row went away.
+/// Returning early without committing rolls the delete back.
+async fn finish_unlink(
+    mut tx: sqlx::Transaction<'_, sqlx::Sqlite>,
+    user: &CurrentUser,
+    actor: &Actor,
+    deleted: Option<LinkedPath>,
+) -> Result<(), ApiError> {
+    if let Some(deleted) = deleted {
+        require_role(&mut *tx, user, deleted.project_id, Role::Editor).await?;
+
+        activity::record(
+            &mut *tx,
+            NewActivity::new(actor, "unlink", "linked_path", deleted.id)
+                .project(deleted.project_id)
+                .before(&deleted),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DeleteByPathRequest {
     pub path: String,
@@ -113,7 +193,6 @@ pub struct DeleteByPathRequest {
 #[derive(Debug, Deserialize)]
 pub struct LookupQuery {
     pub path: String,
-    pub hostname: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -122,24 +201,32 @@ pub struct LookupResponse {
     pub project_name: String,
 }
 
+/// Only links into projects the caller can see are considered.
 pub async fn lookup_linked_path(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     axum::extract::Query(query): axum::extract::Query<LookupQuery>,
-) -> Result<Json<LookupResponse>, (StatusCode, String)> {
+) -> Result<Json<LookupResponse>, ApiError> {
     let path = &query.path;
-    
+    let allowed = user.project_filter();
+
     let result = sqlx::query!(
         r#"SELECT lp.id, lp.project_id, lp.path, lp.hostname, lp.default_column_id, lp.created_at, p.name as project_name
            FROM linked_paths lp
            JOIN projects p ON p.id = lp.project_id
            WHERE ? LIKE lp.path || '%'
+             AND (? IS NULL OR lp.project_id IN (SELECT project_id FROM project_members WHERE user_id = ?))
+             AND (? IS NULL OR lp.project_id IN (SELECT value FROM json_each(?)))
            ORDER BY LENGTH(lp.path) DESC
            LIMIT 1"#,
-        path
+        path,
+        user.id,
+        user.id,
+        allowed,
+        allowed
     )
     .fetch_optional(&state.db)
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    .await?;
 
     match result {
         Some(row) => Ok(Json(LookupResponse {
@@ -153,6 +240,6 @@ pub async fn lookup_linked_path(
             },
             project_name: row.project_name,
         })),
-        None => Err((StatusCode::NOT_FOUND, "No linked path found".to_string())),
+        None => Err(ApiError::not_found("linked_path_not_found", "No linked path found")),
     }
 }
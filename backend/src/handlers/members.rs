@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite};
+
+use super::projects::AppState;
+use crate::access::{require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+
+#[derive(Debug, Serialize)]
+pub struct Member {
+    pub project_id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub display_name: Option<String>,
+    pub role: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetMemberRequest {
+    pub role: String,
+}
+
+async fn find_member<'e, E>(
+    executor: E,
+    project_id: i64,
+    user_id: i64,
+) -> Result<Option<Member>, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let member = sqlx::query_as!(
+        Member,
+        r#"SELECT m.project_id, m.user_id, u.name, u.display_name, m.role, m.created_at
+           FROM project_members m
+           INNER JOIN users u ON u.id = m.user_id
+           WHERE m.project_id = ? AND m.user_id = ?"#,
+        project_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(member)
+}
+
+/// A project must keep at least one admin member, or only the admin key could manage it.
+async fn ensure_other_admin<'e, E>(executor: E, project_id: i64, user_id: i64) -> Result<(), ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let others = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) FROM project_members WHERE project_id = ? AND role = 'admin' AND user_id != ?"#,
+        project_id,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    if others == 0 {
+        return Err(ApiError::conflict(
+            "last_project_admin",
+            "A project must keep at least one admin",
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn list_members(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+) -> Result<Json<Vec<Member>>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let members = sqlx::query_as!(
+        Member,
+        r#"SELECT m.project_id, m.user_id, u.name, u.display_name, m.role, m.created_at
+           FROM project_members m
+           INNER JOIN users u ON u.id = m.user_id
+           WHERE m.project_id = ?
+           ORDER BY u.name"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(members))
+}
+
+/// Adds the user to the project or changes their role.
+pub async fn set_member(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path((project_id, user_id)): Path<(i64, i64)>,
+    Json(payload): Json<SetMemberRequest>,
+) -> Result<Json<Member>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Admin).await?;
+
+    let role = Role::parse(&payload.role)
+        .ok_or_else(|| ApiError::validation("role", "must be viewer, editor or admin"))?;
+
+    let mut tx = state.db.begin().await?;
+
+    let existing = find_member(&mut *tx, project_id, user_id).await?;
+    if existing.as_ref().is_some_and(|m| m.role == "admin") && role != Role::Admin {
+        ensure_other_admin(&mut *tx, project_id, user_id).await?;
+    }
+
+    let role = role.as_str();
+    sqlx::query!(
+        r#"INSERT INTO project_members (project_id, user_id, role) VALUES (?, ?, ?)
+           ON CONFLICT(project_id, user_id) DO UPDATE SET role = excluded.role"#,
+        project_id,
+        user_id,
+        role
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let member = find_member(&mut *tx, project_id, user_id)
+        .await?
+        .ok_or_else(|| ApiError::internal("Member vanished after being saved"))?;
+
+    let mut entry = NewActivity::new(&actor, "add_member", "project_member", user_id)
+        .project(project_id)
+        .after(&member);
+    if let Some(existing) = &existing {
+        entry.action = "update_member";
+        entry = entry.before(existing);
+    }
+    activity::record(&mut *tx, entry).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(member))
+}
+
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path((project_id, user_id)): Path<(i64, i64)>,
+) -> Result<StatusCode, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Admin).await?;
+
+    let mut tx = state.db.begin().await?;
+
+    let existing = find_member(&mut *tx, project_id, user_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("member_not_found", "User is not a member of this project"))?;
+    if existing.role == "admin" {
+        ensure_other_admin(&mut *tx, project_id, user_id).await?;
+    }
+
+    sqlx::query!(
+        r#"DELETE FROM project_members WHERE project_id = ? AND user_id = ?"#,
+        project_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "remove_member", "project_member", user_id)
+            .project(project_id)
+            .before(&existing),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,605 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite};
+use std::collections::{HashMap, HashSet};
+
+use super::projects::AppState;
+use crate::access::{project_of_task, require_role, Role};
+use crate::error::ApiError;
+use crate::middleware::CurrentUser;
+
+const DEFAULT_WEEKS: i64 = 12;
+const MAX_WEEKS: i64 = 104;
+const STORED_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const DEFAULT_DAYS: i64 = 30;
+const MAX_DAYS: i64 = 366;
+
+#[derive(Debug, Deserialize)]
+pub struct MetricsQuery {
+    /// How many weeks to cover, counting the current one (default 12).
+    pub weeks: Option<i64>,
+}
+
+/// Flow metrics over the tasks completed since `since`. Durations are in hours.
+#[derive(Debug, Serialize)]
+pub struct ProjectMetrics {
+    pub project_id: i64,
+    /// Monday of the first week covered, in UTC.
+    pub since: String,
+    pub completed: usize,
+    /// From creation to completion.
+    pub lead_time: DurationStats,
+    /// From first entering an `in_progress` column to completion. Tasks that skipped
+    /// every `in_progress` column have no cycle time.
+    pub cycle_time: DurationStats,
+    /// Completed tasks per week, oldest first, including weeks with none.
+    pub throughput: Vec<WeeklyThroughput>,
+    /// The tasks completed in the period, then the open ones, each with the time spent
+    /// in every column it passed through.
+    pub tasks: Vec<TaskFlow>,
+}
+
+/// Percentiles use the nearest-rank method. Everything but `count` is `None` when no
+/// task qualifies.
+#[derive(Debug, Default, Serialize)]
+pub struct DurationStats {
+    pub count: usize,
+    pub mean_hours: Option<f64>,
+    pub p50_hours: Option<f64>,
+    pub p85_hours: Option<f64>,
+    pub p95_hours: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeeklyThroughput {
+    pub week_start: String,
+    pub completed: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskFlow {
+    pub task_id: i64,
+    pub title: String,
+    pub column_id: i64,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    pub lead_time_hours: Option<f64>,
+    pub cycle_time_hours: Option<f64>,
+    /// In order of first entry. Time in the `done` column a task finished in isn't
+    /// counted; an open task's current column counts up to now.
+    pub time_in_column: Vec<ColumnTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnTime {
+    pub column_id: i64,
+    pub column_name: String,
+    pub hours: f64,
+}
+
+/// A day range, as `YYYY-MM-DD`. Days end at midnight UTC.
+#[derive(Debug, Deserialize)]
+pub struct RangeQuery {
+    /// First day; defaults to 29 days before `to`.
+    pub from: Option<String>,
+    /// Last day; defaults to today.
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BurndownQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Only tasks with this tag.
+    pub tag_id: Option<i64>,
+    /// Only tasks under this parent task, at any depth.
+    pub parent_id: Option<i64>,
+}
+
+/// Tasks per column at the end of each day, for a cumulative flow diagram.
+#[derive(Debug, Serialize)]
+pub struct CumulativeFlow {
+    pub project_id: i64,
+    pub dates: Vec<String>,
+    /// One series per column in board order, with a count for each date.
+    pub series: Vec<FlowSeries>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlowSeries {
+    pub column_id: i64,
+    pub column_name: String,
+    pub kind: String,
+    pub counts: Vec<usize>,
+}
+
+/// Work left at the end of each day. Tasks in `archived` columns are out of scope, and
+/// tags count as they are now, not as they were on each day.
+#[derive(Debug, Serialize)]
+pub struct Burndown {
+    pub project_id: i64,
+    pub tag_id: Option<i64>,
+    pub parent_id: Option<i64>,
+    pub dates: Vec<String>,
+    pub total: Vec<usize>,
+    pub completed: Vec<usize>,
+    pub remaining: Vec<usize>,
+    /// A straight line from the first day's remaining count to zero on the last day.
+    pub ideal: Vec<f64>,
+}
+
+struct Transition {
+    to_column_id: Option<i64>,
+    at: NaiveDateTime,
+}
+
+struct BoardColumn {
+    id: i64,
+    name: String,
+    kind: String,
+}
+
+async fn board_columns<'e, E>(executor: E, project_id: i64) -> Result<Vec<BoardColumn>, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let columns = sqlx::query_as!(
+        BoardColumn,
+        r#"SELECT id as "id!", name, kind FROM columns WHERE project_id = ? ORDER BY sort_order, id"#,
+        project_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(columns)
+}
+
+/// Every column transition of the project's tasks, oldest first, by task id.
+async fn task_histories<'e, E>(executor: E, project_id: i64) -> Result<HashMap<i64, Vec<Transition>>, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let rows = sqlx::query!(
+        r#"SELECT tr.task_id, tr.to_column_id, tr.transitioned_at
+           FROM task_transitions tr
+           INNER JOIN tasks t ON t.id = tr.task_id
+           WHERE t.project_id = ?
+           ORDER BY tr.task_id, tr.transitioned_at, tr.id"#,
+        project_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    let mut histories: HashMap<i64, Vec<Transition>> = HashMap::new();
+    for row in rows {
+        if let Some(at) = parse_stored(&row.transitioned_at) {
+            histories.entry(row.task_id).or_default().push(Transition {
+                to_column_id: row.to_column_id,
+                at,
+            });
+        }
+    }
+
+    Ok(histories)
+}
+
+/// The column a task was in just before `at`, or `None` if it didn't exist yet.
+fn column_at(history: &[Transition], at: NaiveDateTime) -> Option<i64> {
+    history
+        .iter()
+        .take_while(|t| t.at < at)
+        .last()
+        .and_then(|t| t.to_column_id)
+}
+
+/// The days from `from` to `to` inclusive. `to` defaults to today (UTC) and `from` to
+/// 29 days before it.
+fn day_range(from: Option<&str>, to: Option<&str>) -> Result<Vec<NaiveDate>, ApiError> {
+    let parse = |field: &str, value: &str| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| ApiError::invalid_query(field, format!("{} must be a date (YYYY-MM-DD)", field)))
+    };
+    let to = match to {
+        Some(value) => parse("to", value)?,
+        None => Utc::now().date_naive(),
+    };
+    let from = match from {
+        Some(value) => parse("from", value)?,
+        None => to - Duration::days(DEFAULT_DAYS - 1),
+    };
+    if from > to {
+        return Err(ApiError::invalid_query("from", "from must not be after to"));
+    }
+    if (to - from).num_days() >= MAX_DAYS {
+        return Err(ApiError::invalid_query(
+            "from",
+            format!("a range covers at most {} days", MAX_DAYS),
+        ));
+    }
+
+    Ok(from.iter_days().take_while(|day| *day <= to).collect())
+}
+
+fn end_of_day(day: NaiveDate) -> NaiveDateTime {
+    (day + Duration::days(1)).and_time(NaiveTime::MIN)
+}
+
+/// Writes the rows out as a CSV document, quoting fields where needed.
+fn write_csv<R>(rows: impl IntoIterator<Item = R>) -> Result<String, ApiError>
+where
+    R: IntoIterator,
+    R::Item: AsRef<[u8]>,
+{
+    let write_error = |e: csv::Error| ApiError::internal(format!("Failed to write CSV: {}", e));
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.write_record(row).map_err(write_error)?;
+    }
+    let body = writer
+        .into_inner()
+        .map_err(|e| ApiError::internal(format!("Failed to write CSV: {}", e)))?;
+    String::from_utf8(body).map_err(|e| ApiError::internal(e.to_string()))
+}
+
+pub(crate) fn csv_response(filename: String, body: String) -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+}
+
+fn parse_stored(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, STORED_FORMAT).ok()
+}
+
+fn hours(from: NaiveDateTime, to: NaiveDateTime) -> f64 {
+    let hours = (to - from).num_seconds().max(0) as f64 / 3600.0;
+    (hours * 100.0).round() / 100.0
+}
+
+fn duration_stats(mut values: Vec<f64>) -> DurationStats {
+    if values.is_empty() {
+        return DurationStats::default();
+    }
+    values.sort_by(f64::total_cmp);
+
+    let percentile = |p: f64| {
+        let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+        values[rank.clamp(1, values.len()) - 1]
+    };
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+
+    DurationStats {
+        count: values.len(),
+        mean_hours: Some((mean * 100.0).round() / 100.0),
+        p50_hours: Some(percentile(50.0)),
+        p85_hours: Some(percentile(85.0)),
+        p95_hours: Some(percentile(95.0)),
+    }
+}
+
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+}
+
+pub async fn get_project_metrics(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+    Query(query): Query<MetricsQuery>,
+) -> Result<Json<ProjectMetrics>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let weeks = query.weeks.unwrap_or(DEFAULT_WEEKS);
+    if !(1..=MAX_WEEKS).contains(&weeks) {
+        return Err(ApiError::invalid_query(
+            "weeks",
+            format!("weeks must be between 1 and {}", MAX_WEEKS),
+        ));
+    }
+
+    let now = Utc::now().naive_utc();
+    let first_week = week_start(now.date()) - Duration::weeks(weeks - 1);
+    let since = first_week.and_hms_opt(0, 0, 0).unwrap_or(now);
+
+    let columns: HashMap<i64, (String, String)> = board_columns(&state.db, project_id)
+        .await?
+        .into_iter()
+        .map(|c| (c.id, (c.name, c.kind)))
+        .collect();
+
+    let tasks = sqlx::query!(
+        r#"SELECT t.id as "id!", t.title, t.column_id, t.created_at, t.completed_at, c.kind as "column_kind!"
+           FROM tasks t
+           INNER JOIN columns c ON c.id = t.column_id
+           WHERE t.project_id = ?
+           ORDER BY t.completed_at IS NULL, t.completed_at, t.id"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut transitions = task_histories(&state.db, project_id).await?;
+
+    let mut lead_times = Vec::new();
+    let mut cycle_times = Vec::new();
+    let mut throughput: Vec<WeeklyThroughput> = (0..weeks)
+        .map(|week| WeeklyThroughput {
+            week_start: (first_week + Duration::weeks(week)).to_string(),
+            completed: 0,
+        })
+        .collect();
+    let mut flows = Vec::new();
+
+    for task in tasks {
+        let completed_at = task.completed_at.as_deref().and_then(parse_stored);
+        match completed_at {
+            Some(done) if done < since => continue,
+            None if task.column_kind == "archived" => continue,
+            _ => {}
+        }
+        let history = transitions.remove(&task.id).unwrap_or_default();
+
+        let mut time_in_column: Vec<ColumnTime> = Vec::new();
+        for (i, transition) in history.iter().enumerate() {
+            let end = match (history.get(i + 1), completed_at) {
+                (Some(next), _) => next.at,
+                (None, None) => now,
+                (None, Some(_)) => break,
+            };
+            let Some((column_id, (name, _))) = transition
+                .to_column_id
+                .and_then(|id| columns.get_key_value(&id))
+            else {
+                continue;
+            };
+            let spent = hours(transition.at, end);
+            match time_in_column.iter_mut().find(|c| c.column_id == *column_id) {
+                Some(entry) => entry.hours = ((entry.hours + spent) * 100.0).round() / 100.0,
+                None => time_in_column.push(ColumnTime {
+                    column_id: *column_id,
+                    column_name: name.clone(),
+                    hours: spent,
+                }),
+            }
+        }
+
+        let created_at = parse_stored(&task.created_at);
+        let lead_time = completed_at.zip(created_at).map(|(done, created)| hours(created, done));
+        let started_at = history
+            .iter()
+            .find(|t| {
+                t.to_column_id
+                    .and_then(|id| columns.get(&id))
+                    .is_some_and(|(_, kind)| kind == "in_progress")
+            })
+            .map(|t| t.at);
+        let cycle_time = completed_at.zip(started_at).map(|(done, started)| hours(started, done));
+
+        if let Some(done) = completed_at {
+            lead_times.extend(lead_time);
+            cycle_times.extend(cycle_time);
+            let week = (week_start(done.date()) - first_week).num_weeks();
+            if let Some(bucket) = usize::try_from(week).ok().and_then(|w| throughput.get_mut(w)) {
+                bucket.completed += 1;
+            }
+        }
+
+        flows.push(TaskFlow {
+            task_id: task.id,
+            title: task.title,
+            column_id: task.column_id,
+            created_at: task.created_at,
+            completed_at: task.completed_at,
+            lead_time_hours: lead_time,
+            cycle_time_hours: cycle_time,
+            time_in_column,
+        });
+    }
+
+    Ok(Json(ProjectMetrics {
+        project_id,
+        since: since.format(STORED_FORMAT).to_string(),
+        completed: lead_times.len(),
+        lead_time: duration_stats(lead_times),
+        cycle_time: duration_stats(cycle_times),
+        throughput,
+        tasks: flows,
+    }))
+}
+
+async fn cumulative_flow(
+    state: &AppState,
+    user: &CurrentUser,
+    project_id: i64,
+    query: &RangeQuery,
+) -> Result<CumulativeFlow, ApiError> {
+    require_role(&state.db, user, project_id, Role::Viewer).await?;
+    let days = day_range(query.from.as_deref(), query.to.as_deref())?;
+
+    let columns = board_columns(&state.db, project_id).await?;
+    let histories = task_histories(&state.db, project_id).await?;
+
+    let position: HashMap<i64, usize> = columns.iter().enumerate().map(|(i, c)| (c.id, i)).collect();
+    let mut series: Vec<FlowSeries> = columns
+        .into_iter()
+        .map(|c| FlowSeries {
+            column_id: c.id,
+            column_name: c.name,
+            kind: c.kind,
+            counts: vec![0; days.len()],
+        })
+        .collect();
+
+    for (day, &date) in days.iter().enumerate() {
+        let end = end_of_day(date);
+        for history in histories.values() {
+            if let Some(&i) = column_at(history, end).and_then(|id| position.get(&id)) {
+                series[i].counts[day] += 1;
+            }
+        }
+    }
+
+    Ok(CumulativeFlow {
+        project_id,
+        dates: days.iter().map(NaiveDate::to_string).collect(),
+        series,
+    })
+}
+
+/// `GET /api/projects/{id}/cfd?from=&to=`
+pub async fn get_cumulative_flow(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+    Query(query): Query<RangeQuery>,
+) -> Result<Json<CumulativeFlow>, ApiError> {
+    Ok(Json(cumulative_flow(&state, &user, project_id, &query).await?))
+}
+
+/// The same data as a `date` column followed by one column per board column.
+pub async fn get_cumulative_flow_csv(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+    Query(query): Query<RangeQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let flow = cumulative_flow(&state, &user, project_id, &query).await?;
+
+    let mut header_row = vec!["date".to_string()];
+    header_row.extend(flow.series.iter().map(|s| s.column_name.clone()));
+    let rows = flow.dates.iter().enumerate().map(|(day, date)| {
+        let mut row = vec![date.clone()];
+        row.extend(flow.series.iter().map(|s| s.counts[day].to_string()));
+        row
+    });
+    let body = write_csv(std::iter::once(header_row).chain(rows))?;
+
+    Ok(csv_response(format!("cfd-project-{}.csv", project_id), body))
+}
+
+async fn burndown(
+    state: &AppState,
+    user: &CurrentUser,
+    project_id: i64,
+    query: &BurndownQuery,
+) -> Result<Burndown, ApiError> {
+    require_role(&state.db, user, project_id, Role::Viewer).await?;
+    let days = day_range(query.from.as_deref(), query.to.as_deref())?;
+
+    let mut histories = task_histories(&state.db, project_id).await?;
+    if let Some(tag_id) = query.tag_id {
+        let tagged: HashSet<i64> = sqlx::query_scalar!(r#"SELECT task_id FROM task_tags WHERE tag_id = ?"#, tag_id)
+            .fetch_all(&state.db)
+            .await?
+            .into_iter()
+            .collect();
+        histories.retain(|task_id, _| tagged.contains(task_id));
+    }
+    if let Some(parent_id) = query.parent_id {
+        let parent_project = project_of_task(&state.db, parent_id).await.ok();
+        if parent_project != Some(project_id) {
+            return Err(ApiError::invalid_query("parent_id", "parent_id must be a task in this project"));
+        }
+        let descendants: HashSet<i64> = sqlx::query_scalar!(
+            r#"WITH RECURSIVE descendants(id) AS (
+                   SELECT id FROM tasks WHERE parent_id = ?
+                   UNION
+                   SELECT t.id FROM tasks t INNER JOIN descendants d ON t.parent_id = d.id
+               )
+               SELECT id as "id!: i64" FROM descendants"#,
+            parent_id
+        )
+        .fetch_all(&state.db)
+        .await?
+        .into_iter()
+        .collect();
+        histories.retain(|task_id, _| descendants.contains(task_id));
+    }
+
+    let kinds: HashMap<i64, String> = board_columns(&state.db, project_id)
+        .await?
+        .into_iter()
+        .map(|c| (c.id, c.kind))
+        .collect();
+
+    let mut total = vec![0; days.len()];
+    let mut completed = vec![0; days.len()];
+    for (day, &date) in days.iter().enumerate() {
+        let end = end_of_day(date);
+        for history in histories.values() {
+            match column_at(history, end).and_then(|id| kinds.get(&id)).map(String::as_str) {
+                None | Some("archived") => {}
+                Some("done") => {
+                    total[day] += 1;
+                    completed[day] += 1;
+                }
+                Some(_) => total[day] += 1,
+            }
+        }
+    }
+    let remaining: Vec<usize> = total.iter().zip(&completed).map(|(t, c)| t - c).collect();
+
+    let start = remaining.first().copied().unwrap_or_default() as f64;
+    let steps = days.len().saturating_sub(1).max(1) as f64;
+    let ideal = (0..days.len())
+        .map(|day| {
+            let left = start * (1.0 - day as f64 / steps);
+            (left.max(0.0) * 100.0).round() / 100.0
+        })
+        .collect();
+
+    Ok(Burndown {
+        project_id,
+        tag_id: query.tag_id,
+        parent_id: query.parent_id,
+        dates: days.iter().map(NaiveDate::to_string).collect(),
+        total,
+        completed,
+        remaining,
+        ideal,
+    })
+}
+
+/// `GET /api/projects/{id}/burndown?from=&to=&tag_id=&parent_id=`. With both `tag_id`
+/// and `parent_id`, only tasks matching both count.
+pub async fn get_burndown(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+    Query(query): Query<BurndownQuery>,
+) -> Result<Json<Burndown>, ApiError> {
+    Ok(Json(burndown(&state, &user, project_id, &query).await?))
+}
+
+pub async fn get_burndown_csv(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+    Query(query): Query<BurndownQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let chart = burndown(&state, &user, project_id, &query).await?;
+
+    let header_row = ["date", "total", "completed", "remaining", "ideal"].map(String::from);
+    let rows = chart.dates.iter().enumerate().map(|(day, date)| {
+        [
+            date.clone(),
+            chart.total[day].to_string(),
+            chart.completed[day].to_string(),
+            chart.remaining[day].to_string(),
+            chart.ideal[day].to_string(),
+        ]
+    });
+    let body = write_csv(std::iter::once(header_row).chain(rows))?;
+
+    Ok(csv_response(format!("burndown-project-{}.csv", project_id), body))
+}
@@ -1,6 +1,20 @@
+pub mod activity;
+pub mod attachments;
+pub mod calendar;
 pub mod columns;
+pub mod comments;
+pub mod dependencies;
+pub mod events;
+pub mod export;
 pub mod linked_paths;
+pub mod members;
+pub mod metrics;
 pub mod projects;
+pub mod schedule;
+pub mod search;
 pub mod subtasks;
 pub mod tags;
+pub mod task_csv;
 pub mod tasks;
+pub mod users;
+pub mod webhooks;
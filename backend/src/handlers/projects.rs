@@ -1,14 +1,27 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+use super::tasks::Progress;
+
+use crate::access::{require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::events::EventBus;
+use crate::attachments::{collect_garbage, BlobStore};
+use crate::middleware::{Actor, CurrentUser};
+use crate::webhooks;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: SqlitePool,
+    pub events: EventBus,
+    pub blobs: BlobStore,
 }
 
 #[derive(Debug, Serialize)]
@@ -16,6 +29,8 @@ pub struct Project {
     pub id: i64,
     pub name: String,
     pub sort_order: i64,
+    pub wip_mode: String,
+    pub blocker_mode: String,
     pub created_at: String,
 }
 
@@ -25,6 +40,8 @@ pub struct Column {
     pub project_id: i64,
     pub name: String,
     pub sort_order: i64,
+    pub wip_limit: Option<i64>,
+    pub kind: String,
     pub created_at: String,
 }
 
@@ -39,12 +56,28 @@ pub struct Task {
     pub sort_order: i64,
     pub source_tag: Option<String>,
     pub created_at: Option<String>,
+    pub due_at: Option<String>,
+    pub start_at: Option<String>,
+    pub assignee_id: Option<i64>,
+    pub parent_id: Option<i64>,
+    pub completed_at: Option<String>,
+}
+
+/// A card on the board. `progress` is left out for tasks with nothing under them.
+#[derive(Debug, Serialize)]
+pub struct BoardTask {
+    #[serde(flatten)]
+    pub task: Task,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<Progress>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ProjectWithDetails {
     pub id: i64,
     pub name: String,
+    pub wip_mode: String,
+    pub blocker_mode: String,
     pub created_at: String,
     pub columns: Vec<ColumnWithTasks>,
 }
@@ -55,8 +88,10 @@ pub struct ColumnWithTasks {
     pub project_id: i64,
     pub name: String,
     pub sort_order: i64,
+    pub wip_limit: Option<i64>,
+    pub kind: String,
     pub created_at: String,
-    pub tasks: Vec<Task>,
+    pub tasks: Vec<BoardTask>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,171 +101,175 @@ pub struct CreateProjectRequest {
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateProjectRequest {
-    pub name: String,
-}
-
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
+    pub name: Option<String>,
+    /// `soft` (default) warns when a task enters a column at its WIP limit; `hard` rejects it.
+    pub wip_mode: Option<String>,
+    /// `soft` (default) warns when a task moves ahead while its blockers are open; `hard` rejects it.
+    pub blocker_mode: Option<String>,
 }
 
+/// Any caller may create a project; a personal-token caller becomes its admin.
 pub async fn create_project(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Json(payload): Json<CreateProjectRequest>,
-) -> Result<(StatusCode, Json<Project>), (StatusCode, Json<ErrorResponse>)> {
-    let mut tx = state.db.begin().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to start transaction: {}", e),
-            }),
-        )
-    })?;
+) -> Result<(StatusCode, Json<Project>), ApiError> {
+    if user.project_ids.is_some() {
+        return Err(ApiError::forbidden(
+            "project_not_allowed",
+            "This token is limited to existing projects",
+        ));
+    }
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::validation("name", "must not be empty"));
+    }
+
+    let mut tx = state.db.begin().await?;
 
     let project = sqlx::query_as!(
         Project,
-        r#"INSERT INTO projects (name, sort_order) VALUES (?, (SELECT COALESCE(MAX(sort_order), 0) + 1000 FROM projects)) RETURNING id, name, sort_order, created_at"#,
+        r#"INSERT INTO projects (name, sort_order) VALUES (?, (SELECT COALESCE(MAX(sort_order), 0) + 1000 FROM projects)) RETURNING id, name, sort_order, wip_mode, blocker_mode, created_at"#,
         payload.name
     )
     .fetch_one(&mut *tx)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to create project: {}", e),
-            }),
-        )
-    })?;
+    .await?;
 
     let default_columns = [
-        ("Backlog", 1000),
-        ("To Do", 2000),
-        ("In Progress", 3000),
-        ("Testing", 4000),
-        ("Done", 5000),
+        ("Backlog", 1000, "backlog"),
+        ("To Do", 2000, "todo"),
+        ("In Progress", 3000, "in_progress"),
+        ("Testing", 4000, "in_progress"),
+        ("Done", 5000, "done"),
     ];
 
-    for (name, sort_order) in default_columns {
+    for (name, sort_order, kind) in default_columns {
         sqlx::query!(
-            r#"INSERT INTO columns (project_id, name, sort_order) VALUES (?, ?, ?)"#,
+            r#"INSERT INTO columns (project_id, name, sort_order, kind) VALUES (?, ?, ?, ?)"#,
             project.id,
             name,
-            sort_order
+            sort_order,
+            kind
         )
         .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to create default columns: {}", e),
-                }),
-            )
-        })?;
+        .await?;
     }
 
-    tx.commit().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to commit transaction: {}", e),
-            }),
+    if let Some(user_id) = user.id {
+        sqlx::query!(
+            r#"INSERT INTO project_members (project_id, user_id, role) VALUES (?, ?, 'admin')"#,
+            project.id,
+            user_id
         )
-    })?;
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "create", "project", project.id)
+            .project(project.id)
+            .after(&project),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, project.id, "project.created", &project).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project.id), "project.created", &project);
 
     Ok((StatusCode::CREATED, Json(project)))
 }
 
+/// Only the projects the caller is a member of (all of them for the admin key), narrowed
+/// further if their token is limited to some projects.
 pub async fn list_projects(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Project>>, (StatusCode, Json<ErrorResponse>)> {
+    Extension(user): Extension<CurrentUser>,
+) -> Result<Json<Vec<Project>>, ApiError> {
+    let allowed = user.project_filter();
     let projects = sqlx::query_as!(
         Project,
-        r#"SELECT id, name, sort_order, created_at FROM projects ORDER BY sort_order ASC"#
+        r#"SELECT id, name, sort_order, wip_mode, blocker_mode, created_at FROM projects
+           WHERE (? IS NULL OR id IN (SELECT project_id FROM project_members WHERE user_id = ?))
+             AND (? IS NULL OR id IN (SELECT value FROM json_each(?)))
+           ORDER BY sort_order ASC"#,
+        user.id,
+        user.id,
+        allowed,
+        allowed
     )
     .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch projects: {}", e),
-            }),
-        )
-    })?;
+    .await?;
 
     Ok(Json(projects))
 }
 
 pub async fn get_project(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(id): Path<i64>,
-) -> Result<Json<ProjectWithDetails>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<ProjectWithDetails>, ApiError> {
+    require_role(&state.db, &user, id, Role::Viewer).await?;
+
     let project = sqlx::query_as!(
         Project,
-        r#"SELECT id, name, sort_order, created_at FROM projects WHERE id = ?"#,
+        r#"SELECT id, name, sort_order, wip_mode, blocker_mode, created_at FROM projects WHERE id = ?"#,
         id
     )
     .fetch_optional(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch project: {}", e),
-            }),
-        )
-    })?;
-
-    let project = project.ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Project not found".to_string(),
-            }),
-        )
-    })?;
+    .await?;
+
+    let project = project
+        .ok_or_else(|| ApiError::not_found("project_not_found", "Project not found"))?;
 
     let columns = sqlx::query_as!(
         Column,
-        r#"SELECT id, project_id, name, sort_order, created_at FROM columns WHERE project_id = ? ORDER BY sort_order"#,
+        r#"SELECT id, project_id, name, sort_order, wip_limit, kind, created_at FROM columns WHERE project_id = ? ORDER BY sort_order"#,
         id
     )
     .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch columns: {}", e),
-            }),
-        )
-    })?;
+    .await?;
 
     let tasks = sqlx::query_as!(
         Task,
-        r#"SELECT id, project_id, column_id, title, description, priority as "priority?", sort_order, source_tag as "source_tag?", created_at FROM tasks WHERE project_id = ? ORDER BY sort_order"#,
+        r#"SELECT id, project_id, column_id, title, description, priority as "priority?", sort_order, source_tag as "source_tag?", created_at, due_at, start_at, assignee_id, parent_id, completed_at FROM tasks WHERE project_id = ? ORDER BY sort_order"#,
         id
     )
     .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch tasks: {}", e),
-            }),
-        )
-    })?;
+    .await?;
+
+    let subtask_counts = sqlx::query!(
+        r#"SELECT s.task_id, COUNT(*) as "total!: i64", SUM(s.done) as "done!: i64"
+           FROM subtasks s
+           INNER JOIN tasks t ON t.id = s.task_id
+           WHERE t.project_id = ?
+           GROUP BY s.task_id"#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|row| (row.task_id, (row.done, row.total)))
+    .collect();
+
+    let done_column_ids: Vec<i64> = columns.iter().filter(|c| c.kind == "done").map(|c| c.id).collect();
+    let progress = board_progress(&tasks, &subtask_counts, &done_column_ids);
 
     let columns_with_tasks: Vec<ColumnWithTasks> = columns
         .into_iter()
         .map(|col| {
-            let column_tasks: Vec<Task> = tasks
+            let column_tasks: Vec<BoardTask> = tasks
                 .iter()
                 .filter(|t| t.column_id == col.id)
-                .cloned()
+                .map(|t| BoardTask {
+                    task: t.clone(),
+                    progress: progress
+                        .get(&t.id)
+                        .copied()
+                        .filter(|p| p.tasks_total > 0 || p.subtasks_total > 0),
+                })
                 .collect();
 
             ColumnWithTasks {
@@ -238,6 +277,8 @@ pub async fn get_project(
                 project_id: col.project_id,
                 name: col.name,
                 sort_order: col.sort_order,
+                wip_limit: col.wip_limit,
+                kind: col.kind,
                 created_at: col.created_at,
                 tasks: column_tasks,
             }
@@ -247,74 +288,173 @@ pub async fn get_project(
     Ok(Json(ProjectWithDetails {
         id: project.id,
         name: project.name,
+        wip_mode: project.wip_mode,
+        blocker_mode: project.blocker_mode,
         created_at: project.created_at,
         columns: columns_with_tasks,
     }))
 }
 
+/// Rolls child tasks and subtasks up into every task above them, the same way
+/// `get_task` reports progress.
+fn board_progress(
+    tasks: &[Task],
+    subtask_counts: &HashMap<i64, (i64, i64)>,
+    done_column_ids: &[i64],
+) -> HashMap<i64, Progress> {
+    let mut children: HashMap<i64, Vec<&Task>> = HashMap::new();
+    for task in tasks {
+        if let Some(parent_id) = task.parent_id {
+            children.entry(parent_id).or_default().push(task);
+        }
+    }
+
+    fn roll_up(
+        task: &Task,
+        children: &HashMap<i64, Vec<&Task>>,
+        subtask_counts: &HashMap<i64, (i64, i64)>,
+        done_column_ids: &[i64],
+        progress: &mut HashMap<i64, Progress>,
+    ) -> Progress {
+        if let Some(known) = progress.get(&task.id) {
+            return *known;
+        }
+
+        let (subtasks_done, subtasks_total) = subtask_counts.get(&task.id).copied().unwrap_or_default();
+        let mut total = Progress {
+            subtasks_done,
+            subtasks_total,
+            ..Progress::default()
+        };
+        for child in children.get(&task.id).into_iter().flatten() {
+            let below = roll_up(child, children, subtask_counts, done_column_ids, progress);
+            total.tasks_total += 1 + below.tasks_total;
+            total.tasks_done += below.tasks_done + i64::from(done_column_ids.contains(&child.column_id));
+            total.subtasks_done += below.subtasks_done;
+            total.subtasks_total += below.subtasks_total;
+        }
+
+        progress.insert(task.id, total);
+        total
+    }
+
+    let mut progress = HashMap::new();
+    for task in tasks {
+        roll_up(task, &children, subtask_counts, done_column_ids, &mut progress);
+    }
+    progress
+}
+
 pub async fn update_project(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
     Json(payload): Json<UpdateProjectRequest>,
-) -> Result<Json<Project>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Project>, ApiError> {
+    require_role(&state.db, &user, id, Role::Admin).await?;
+
+    if payload.name.as_deref().is_some_and(|n| n.trim().is_empty()) {
+        return Err(ApiError::validation("name", "must not be empty"));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let existing = sqlx::query_as!(
+        Project,
+        r#"SELECT id, name, sort_order, wip_mode, blocker_mode, created_at FROM projects WHERE id = ?"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let existing = existing
+        .ok_or_else(|| ApiError::not_found("project_not_found", "Project not found"))?;
+
+    let name = payload.name.unwrap_or_else(|| existing.name.clone());
+    let wip_mode = payload.wip_mode.unwrap_or_else(|| existing.wip_mode.clone());
+    let blocker_mode = payload.blocker_mode.unwrap_or_else(|| existing.blocker_mode.clone());
+
     let project = sqlx::query_as!(
         Project,
-        r#"UPDATE projects SET name = ? WHERE id = ? RETURNING id, name, sort_order, created_at"#,
-        payload.name,
+        r#"UPDATE projects SET name = ?, wip_mode = ?, blocker_mode = ? WHERE id = ? RETURNING id, name, sort_order, wip_mode, blocker_mode, created_at"#,
+        name,
+        wip_mode,
+        blocker_mode,
         id
     )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to update project: {}", e),
-            }),
-        )
-    })?;
-
-    let project = project.ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Project not found".to_string(),
-            }),
-        )
-    })?;
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "update", "project", id)
+            .project(id)
+            .before(&existing)
+            .after(&project),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, project.id, "project.updated", &project).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project.id), "project.updated", &project);
 
     Ok(Json(project))
 }
 
 pub async fn delete_project(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
-    let result = sqlx::query!(r#"DELETE FROM projects WHERE id = ?"#, id)
-        .execute(&state.db)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to delete project: {}", e),
-                }),
-            )
-        })?;
-
-    if result.rows_affected() == 0 {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Project not found".to_string(),
-            }),
-        ));
-    }
+) -> Result<StatusCode, ApiError> {
+    require_role(&state.db, &user, id, Role::Admin).await?;
+
+    let mut tx = state.db.begin().await?;
+
+    // Queued before the project's webhooks go with it; the pending deliveries outlive
+    // them, while the rest of their log is dropped.
+    let data = serde_json::json!({ "id": id });
+    webhooks::record_event(&mut tx, id, "project.deleted", &data).await?;
+    sqlx::query!(
+        r#"DELETE FROM webhook_deliveries
+           WHERE status != 'pending' AND webhook_id IN (SELECT id FROM webhooks WHERE project_id = ?)"#,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let deleted = sqlx::query_as!(
+        Project,
+        r#"DELETE FROM projects WHERE id = ? RETURNING id, name, sort_order, wip_mode, blocker_mode, created_at"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let deleted = deleted
+        .ok_or_else(|| ApiError::not_found("project_not_found", "Project not found"))?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "delete", "project", id)
+            .project(id)
+            .before(&deleted),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    collect_garbage(&state.db, &state.blobs).await;
+
+    state.events.publish(Some(id), "project.deleted", &data);
 
     Ok(StatusCode::NO_CONTENT)
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ReorderProjectItem {
     pub id: i64,
     pub sort_order: i64,
@@ -322,43 +462,33 @@ pub struct ReorderProjectItem {
 
 pub async fn reorder_projects(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Json(payload): Json<Vec<ReorderProjectItem>>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
-    let mut tx = state.db.begin().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to start transaction: {}", e),
-            }),
-        )
-    })?;
+) -> Result<StatusCode, ApiError> {
+    let mut tx = state.db.begin().await?;
 
     for item in payload {
+        require_role(&mut *tx, &user, item.id, Role::Editor).await?;
+
         sqlx::query!(
             r#"UPDATE projects SET sort_order = ? WHERE id = ?"#,
             item.sort_order,
             item.id
         )
         .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to update project order: {}", e),
-                }),
-            )
-        })?;
-    }
+        .await?;
 
-    tx.commit().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to commit transaction: {}", e),
-            }),
+        activity::record(
+            &mut *tx,
+            NewActivity::new(&actor, "reorder", "project", item.id)
+                .project(item.id)
+                .after(&item),
         )
-    })?;
+        .await?;
+    }
+
+    tx.commit().await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
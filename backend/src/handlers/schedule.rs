@@ -0,0 +1,120 @@
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::projects::AppState;
+use crate::error::ApiError;
+use crate::middleware::CurrentUser;
+
+const DEFAULT_UPCOMING_DAYS: i64 = 7;
+const MAX_UPCOMING_DAYS: i64 = 365;
+
+#[derive(Debug, Deserialize)]
+pub struct OverdueQuery {
+    pub project_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpcomingQuery {
+    pub days: Option<i64>,
+    pub project_id: Option<i64>,
+}
+
+/// A task with a due date, with enough context to show it outside its board.
+#[derive(Debug, Serialize)]
+pub struct ScheduledTask {
+    pub task_id: i64,
+    pub project_id: i64,
+    pub project_name: String,
+    pub column_id: i64,
+    pub column_name: String,
+    pub title: String,
+    pub priority: String,
+    pub start_at: Option<String>,
+    pub due_at: String,
+}
+
+/// Tasks past their due date in projects the caller can see, oldest deadline first.
+/// Tasks in `done` or `archived` columns are finished or dropped, and are left out.
+pub async fn list_overdue_tasks(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Query(query): Query<OverdueQuery>,
+) -> Result<Json<Vec<ScheduledTask>>, ApiError> {
+    let allowed = user.project_filter();
+    let tasks = sqlx::query_as!(
+        ScheduledTask,
+        r#"SELECT t.id as "task_id!", t.project_id as "project_id!", p.name as "project_name!",
+                  t.column_id as "column_id!", c.name as "column_name!", t.title as "title!",
+                  t.priority as "priority!", t.start_at, t.due_at as "due_at!"
+           FROM tasks t
+           INNER JOIN projects p ON p.id = t.project_id
+           INNER JOIN columns c ON c.id = t.column_id
+           WHERE t.due_at IS NOT NULL
+             AND t.due_at < datetime('now')
+             AND c.kind NOT IN ('done', 'archived')
+             AND (? IS NULL OR t.project_id = ?)
+             AND (? IS NULL OR t.project_id IN (SELECT project_id FROM project_members WHERE user_id = ?))
+             AND (? IS NULL OR t.project_id IN (SELECT value FROM json_each(?)))
+           ORDER BY t.due_at ASC, t.id ASC"#,
+        query.project_id,
+        query.project_id,
+        user.id,
+        user.id,
+        allowed,
+        allowed
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(tasks))
+}
+
+/// Tasks due between now and `days` from now (default 7) in projects the caller can see,
+/// soonest first.
+pub async fn list_upcoming_tasks(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Query(query): Query<UpcomingQuery>,
+) -> Result<Json<Vec<ScheduledTask>>, ApiError> {
+    let days = query.days.unwrap_or(DEFAULT_UPCOMING_DAYS);
+    if !(1..=MAX_UPCOMING_DAYS).contains(&days) {
+        return Err(ApiError::invalid_query(
+            "days",
+            format!("days must be between 1 and {}", MAX_UPCOMING_DAYS),
+        ));
+    }
+    let window = format!("+{} days", days);
+
+    let allowed = user.project_filter();
+    let tasks = sqlx::query_as!(
+        ScheduledTask,
+        r#"SELECT t.id as "task_id!", t.project_id as "project_id!", p.name as "project_name!",
+                  t.column_id as "column_id!", c.name as "column_name!", t.title as "title!",
+                  t.priority as "priority!", t.start_at, t.due_at as "due_at!"
+           FROM tasks t
+           INNER JOIN projects p ON p.id = t.project_id
+           INNER JOIN columns c ON c.id = t.column_id
+           WHERE t.due_at IS NOT NULL
+             AND t.due_at >= datetime('now')
+             AND t.due_at < datetime('now', ?)
+             AND c.kind NOT IN ('done', 'archived')
+             AND (? IS NULL OR t.project_id = ?)
+             AND (? IS NULL OR t.project_id IN (SELECT project_id FROM project_members WHERE user_id = ?))
+             AND (? IS NULL OR t.project_id IN (SELECT value FROM json_each(?)))
+           ORDER BY t.due_at ASC, t.id ASC"#,
+        window,
+        query.project_id,
+        query.project_id,
+        user.id,
+        user.id,
+        allowed,
+        allowed
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(tasks))
+}
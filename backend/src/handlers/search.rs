@@ -0,0 +1,93 @@
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use super::projects::AppState;
+use crate::error::ApiError;
+use crate::middleware::CurrentUser;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub project_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// A matching task. Matched terms in `title_highlight` and `snippet` are wrapped in `<mark>`.
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub task_id: i64,
+    pub project_id: i64,
+    pub project_name: String,
+    pub column_id: i64,
+    pub column_name: String,
+    pub title: String,
+    pub priority: String,
+    pub title_highlight: String,
+    pub snippet: String,
+    pub rank: f64,
+}
+
+/// Turns free text into an FTS5 query: every word is quoted so punctuation can't
+/// be read as query syntax, and the last word matches as a prefix for search-as-you-type.
+pub(crate) fn to_fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        return None;
+    }
+
+    Some(format!("{}*", terms.join(" ")))
+}
+
+pub async fn search_tasks(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<SearchResult>>, ApiError> {
+    let fts_query = to_fts_query(&query.q)
+        .ok_or_else(|| ApiError::invalid_query("q", "Search query must not be empty"))?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let allowed = user.project_filter();
+
+    // bm25 weights favour title hits over description hits over subtask hits.
+    let results = sqlx::query_as!(
+        SearchResult,
+        r#"SELECT t.id as "task_id!", t.project_id as "project_id!", p.name as "project_name!",
+                  t.column_id as "column_id!", c.name as "column_name!", t.title as "title!",
+                  t.priority as "priority!",
+                  highlight(tasks_fts, 0, '<mark>', '</mark>') as "title_highlight!: String",
+                  snippet(tasks_fts, -1, '<mark>', '</mark>', '…', 16) as "snippet!: String",
+                  bm25(tasks_fts, 10.0, 3.0, 1.0) as "rank!: f64"
+           FROM tasks_fts
+           INNER JOIN tasks t ON t.id = tasks_fts.rowid
+           INNER JOIN projects p ON p.id = t.project_id
+           INNER JOIN columns c ON c.id = t.column_id
+           WHERE tasks_fts MATCH ? AND (? IS NULL OR t.project_id = ?)
+             AND (? IS NULL OR t.project_id IN (SELECT project_id FROM project_members WHERE user_id = ?))
+             AND (? IS NULL OR t.project_id IN (SELECT value FROM json_each(?)))
+           ORDER BY bm25(tasks_fts, 10.0, 3.0, 1.0)
+           LIMIT ?"#,
+        fts_query,
+        query.project_id,
+        query.project_id,
+        user.id,
+        user.id,
+        allowed,
+        allowed,
+        limit
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(results))
+}
@@ -1,11 +1,16 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 
-use super::projects::{AppState, ErrorResponse};
+use super::projects::AppState;
+use crate::access::{project_of_subtask, project_of_task, require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+use crate::webhooks;
 
 #[derive(Debug, Serialize)]
 pub struct Subtask {
@@ -29,42 +34,37 @@ pub struct UpdateSubtaskRequest {
 
 pub async fn create_subtask(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(task_id): Path<i64>,
     Json(payload): Json<CreateSubtaskRequest>,
-) -> Result<(StatusCode, Json<Subtask>), (StatusCode, Json<ErrorResponse>)> {
+) -> Result<(StatusCode, Json<Subtask>), ApiError> {
+    let project_id = project_of_task(&state.db, task_id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    if payload.title.trim().is_empty() {
+        return Err(ApiError::validation("title", "must not be empty"));
+    }
+
     let max_sort_order = sqlx::query!(
         r#"SELECT MAX(sort_order) as max_sort FROM subtasks WHERE task_id = ?"#,
         task_id
     )
     .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to get max sort order: {}", e),
-            }),
-        )
-    })?;
+    .await?;
 
     let sort_order = max_sort_order.max_sort.unwrap_or(0) + 1000;
 
+    let mut tx = state.db.begin().await?;
+
     let result = sqlx::query!(
         r#"INSERT INTO subtasks (task_id, title, sort_order) VALUES (?, ?, ?) RETURNING id as "id!""#,
         task_id,
         payload.title,
         sort_order
     )
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to create subtask: {}", e),
-            }),
-        )
-    })?;
+    .fetch_one(&mut *tx)
+    .await?;
 
     let subtask = Subtask {
         id: result.id,
@@ -74,36 +74,58 @@ pub async fn create_subtask(
         sort_order,
     };
 
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "create", "subtask", subtask.id)
+            .project(project_id)
+            .task(task_id)
+            .after(&subtask),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, project_id, "subtask.created", &subtask).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(project_id), "subtask.created", &subtask);
+
     Ok((StatusCode::CREATED, Json(subtask)))
 }
 
 pub async fn update_subtask(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
     Json(payload): Json<UpdateSubtaskRequest>,
-) -> Result<Json<Subtask>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Subtask>, ApiError> {
+    let project_id = project_of_subtask(&state.db, id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    if payload.title.as_deref().is_some_and(|t| t.trim().is_empty()) {
+        return Err(ApiError::validation("title", "must not be empty"));
+    }
+
+    let mut tx = state.db.begin().await?;
+
     let current = sqlx::query!(
-        r#"SELECT id as "id!", task_id as "task_id!", title, done as "done!", sort_order as "sort_order!" FROM subtasks WHERE id = ?"#,
+        r#"SELECT s.id as "id!", s.task_id as "task_id!", s.title, s.done as "done!", s.sort_order as "sort_order!", t.project_id as "project_id!"
+           FROM subtasks s
+           INNER JOIN tasks t ON t.id = s.task_id
+           WHERE s.id = ?"#,
         id
     )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch subtask: {}", e),
-            }),
-        )
-    })?
-    .ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Subtask not found".to_string(),
-            }),
-        )
-    })?;
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::not_found("subtask_not_found", "Subtask not found"))?;
+
+    let before = Subtask {
+        id: current.id,
+        task_id: current.task_id,
+        title: current.title.clone(),
+        done: current.done != 0,
+        sort_order: current.sort_order,
+    };
 
     let new_title = payload.title.unwrap_or(current.title);
     let new_done = payload.done.unwrap_or(current.done != 0);
@@ -115,50 +137,83 @@ pub async fn update_subtask(
         new_done_int,
         id
     )
-    .execute(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to update subtask: {}", e),
-            }),
-        )
-    })?;
+    .execute(&mut *tx)
+    .await?;
 
-    Ok(Json(Subtask {
+    let subtask = Subtask {
         id: current.id,
         task_id: current.task_id,
         title: new_title,
         done: new_done,
         sort_order: current.sort_order,
-    }))
+    };
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "update", "subtask", id)
+            .project(current.project_id)
+            .task(current.task_id)
+            .before(&before)
+            .after(&subtask),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, current.project_id, "subtask.updated", &subtask).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(current.project_id), "subtask.updated", &subtask);
+
+    Ok(Json(subtask))
 }
 
 pub async fn delete_subtask(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
-    let result = sqlx::query!(r#"DELETE FROM subtasks WHERE id = ?"#, id)
-        .execute(&state.db)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to delete subtask: {}", e),
-                }),
-            )
-        })?;
-
-    if result.rows_affected() == 0 {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Subtask not found".to_string(),
+) -> Result<StatusCode, ApiError> {
+    let project_id = project_of_subtask(&state.db, id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    let mut tx = state.db.begin().await?;
+
+    let deleted = sqlx::query!(
+        r#"SELECT s.id as "id!", s.task_id as "task_id!", s.title, s.done as "done!", s.sort_order as "sort_order!", t.project_id as "project_id!"
+           FROM subtasks s
+           INNER JOIN tasks t ON t.id = s.task_id
+           WHERE s.id = ?"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::not_found("subtask_not_found", "Subtask not found"))?;
+
+    sqlx::query!(r#"DELETE FROM subtasks WHERE id = ?"#, id)
+        .execute(&mut *tx)
+        .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "delete", "subtask", id)
+            .project(deleted.project_id)
+            .task(deleted.task_id)
+            .before(&Subtask {
+                id: deleted.id,
+                task_id: deleted.task_id,
+                title: deleted.title,
+                done: deleted.done != 0,
+                sort_order: deleted.sort_order,
             }),
-        ));
-    }
+    )
+    .await?;
+
+    let data = serde_json::json!({ "id": id, "task_id": deleted.task_id });
+    webhooks::record_event(&mut tx, deleted.project_id, "subtask.deleted", &data).await?;
+
+    tx.commit().await?;
+
+    state.events.publish(Some(deleted.project_id), "subtask.deleted", &data);
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -1,11 +1,15 @@
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
 use serde::{Deserialize, Serialize};
 
-use super::projects::{AppState, ErrorResponse};
+use super::projects::AppState;
+use crate::access::require_editor_anywhere;
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
 
 #[derive(Debug, Serialize)]
 pub struct Tag {
@@ -22,78 +26,78 @@ pub struct CreateTagRequest {
 
 pub async fn list_tags(
     State(state): State<AppState>,
-) -> Result<Json<Vec<Tag>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Vec<Tag>>, ApiError> {
     let tags = sqlx::query_as!(
         Tag,
         r#"SELECT id as "id!", name, color FROM tags ORDER BY name"#
     )
     .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch tags: {}", e),
-            }),
-        )
-    })?;
+    .await?;
 
     Ok(Json(tags))
 }
 
+/// Tags are shared by every project: anyone who edits a project may add one, but only
+/// the admin key may delete one.
 pub async fn create_tag(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Json(payload): Json<CreateTagRequest>,
-) -> Result<(StatusCode, Json<Tag>), (StatusCode, Json<ErrorResponse>)> {
+) -> Result<(StatusCode, Json<Tag>), ApiError> {
+    require_editor_anywhere(&state.db, &user).await?;
+
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::validation("name", "must not be empty"));
+    }
+
+    let mut tx = state.db.begin().await?;
+
     let tag = sqlx::query_as!(
         Tag,
         r#"INSERT INTO tags (name, color) VALUES (?, ?) RETURNING id as "id!", name, color"#,
         payload.name,
         payload.color
     )
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        let status = if e.to_string().contains("UNIQUE constraint failed") {
-            StatusCode::CONFLICT
-        } else {
-            StatusCode::INTERNAL_SERVER_ERROR
-        };
-        (
-            status,
-            Json(ErrorResponse {
-                error: format!("Failed to create tag: {}", e),
-            }),
-        )
-    })?;
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(&mut *tx, NewActivity::new(&actor, "create", "tag", tag.id).after(&tag))
+        .await?;
+
+    tx.commit().await?;
+
+    state.events.publish(None, "tag.created", &tag);
 
     Ok((StatusCode::CREATED, Json(tag)))
 }
 
 pub async fn delete_tag(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
-    let result = sqlx::query!(r#"DELETE FROM tags WHERE id = ?"#, id)
-        .execute(&state.db)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to delete tag: {}", e),
-                }),
-            )
-        })?;
-
-    if result.rows_affected() == 0 {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Tag not found".to_string(),
-            }),
-        ));
-    }
+) -> Result<StatusCode, ApiError> {
+    user.ensure_admin()?;
+
+    let mut tx = state.db.begin().await?;
+
+    let deleted = sqlx::query_as!(
+        Tag,
+        r#"DELETE FROM tags WHERE id = ? RETURNING id as "id!", name, color"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let deleted = deleted.ok_or_else(|| ApiError::not_found("tag_not_found", "Tag not found"))?;
+
+    activity::record(&mut *tx, NewActivity::new(&actor, "delete", "tag", id).before(&deleted))
+        .await?;
+
+    tx.commit().await?;
+
+    state.events.publish(None, "tag.deleted", &serde_json::json!({ "id": id }));
 
     Ok(StatusCode::NO_CONTENT)
 }
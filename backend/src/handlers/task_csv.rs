@@ -0,0 +1,508 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::columns::{check_wip_limit, guess_kind, Column, WipWarning};
+use super::metrics::csv_response;
+use super::projects::AppState;
+use super::tasks::{parse_timestamp, Task, PRIORITIES};
+use crate::access::{require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+use crate::webhooks;
+
+const EXPORT_HEADERS: [&str; 13] = [
+    "id",
+    "title",
+    "description",
+    "column",
+    "priority",
+    "tags",
+    "subtasks_done",
+    "subtasks_total",
+    "source_tag",
+    "due_at",
+    "start_at",
+    "created_at",
+    "completed_at",
+];
+
+/// The task fields a CSV column can be mapped to.
+pub const IMPORT_FIELDS: [&str; 8] = [
+    "title",
+    "description",
+    "column",
+    "priority",
+    "tags",
+    "source_tag",
+    "due_at",
+    "start_at",
+];
+
+/// Colour for tags an import creates; they can be recoloured later.
+const IMPORTED_TAG_COLOR: &str = "#6b7280";
+
+#[derive(Debug, Deserialize)]
+pub struct CsvImportRequest {
+    pub csv: String,
+    /// Header name → task field. Headers left out are matched to the field of the same
+    /// name, ignoring case; mapping a header to `""` skips it.
+    #[serde(default)]
+    pub mapping: HashMap<String, String>,
+    /// Validate and report what would happen without writing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeaderMapping {
+    pub header: String,
+    /// `None` when the column is skipped.
+    pub field: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CsvImportSummary {
+    pub project_id: i64,
+    pub dry_run: bool,
+    pub mapping: Vec<HeaderMapping>,
+    pub tasks: usize,
+    pub columns_created: Vec<String>,
+    pub tags_created: Vec<String>,
+    /// Columns in a `soft` WIP project that the import pushed past their limit.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<WipWarning>,
+}
+
+/// One validated data row, ready to insert.
+struct CsvRow {
+    title: String,
+    description: Option<String>,
+    column: Option<String>,
+    priority: String,
+    tags: Vec<String>,
+    source_tag: String,
+    due_at: Option<String>,
+    start_at: Option<String>,
+}
+
+/// Every task of the project, one row each, in board order. `tags` holds the tag names
+/// separated by commas.
+pub async fn export_tasks_csv(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let tasks = sqlx::query!(
+        r#"SELECT t.id as "id!", t.title as "title!", t.description, c.name as "column_name!",
+                  t.priority as "priority!", t.source_tag as "source_tag!", t.due_at, t.start_at,
+                  t.created_at as "created_at!", t.completed_at,
+                  (SELECT COUNT(*) FROM subtasks s WHERE s.task_id = t.id AND s.done = 1) as "subtasks_done!: i64",
+                  (SELECT COUNT(*) FROM subtasks s WHERE s.task_id = t.id) as "subtasks_total!: i64"
+           FROM tasks t
+           JOIN columns c ON c.id = t.column_id
+           WHERE t.project_id = ?
+           ORDER BY c.sort_order, t.sort_order, t.id"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in sqlx::query!(
+        r#"SELECT tt.task_id as "task_id!", g.name as "name!"
+           FROM task_tags tt
+           JOIN tags g ON g.id = tt.tag_id
+           JOIN tasks t ON t.id = tt.task_id
+           WHERE t.project_id = ?
+           ORDER BY g.name"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?
+    {
+        tags.entry(row.task_id).or_default().push(row.name);
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let write_error = |e: csv::Error| ApiError::internal(format!("Failed to write CSV: {}", e));
+    writer.write_record(EXPORT_HEADERS).map_err(write_error)?;
+    for task in tasks {
+        let task_tags = tags.get(&task.id).map(|names| names.join(", ")).unwrap_or_default();
+        writer
+            .write_record([
+                task.id.to_string(),
+                task.title,
+                task.description.unwrap_or_default(),
+                task.column_name,
+                task.priority,
+                task_tags,
+                task.subtasks_done.to_string(),
+                task.subtasks_total.to_string(),
+                task.source_tag,
+                task.due_at.unwrap_or_default(),
+                task.start_at.unwrap_or_default(),
+                task.created_at,
+                task.completed_at.unwrap_or_default(),
+            ])
+            .map_err(write_error)?;
+    }
+    let body = writer
+        .into_inner()
+        .map_err(|e| ApiError::internal(format!("Failed to write CSV: {}", e)))?;
+    let body = String::from_utf8(body).map_err(|e| ApiError::internal(e.to_string()))?;
+
+    Ok(csv_response(format!("tasks-project-{}.csv", project_id), body))
+}
+
+/// Adds one task per data row of a CSV file. Columns and tags named in the file that
+/// don't exist yet are created. Every row is checked before anything is written: if any
+/// row is invalid the response lists each problem with its line number and the board is
+/// left untouched.
+pub async fn import_tasks_csv(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(project_id): Path<i64>,
+    Json(payload): Json<CsvImportRequest>,
+) -> Result<(StatusCode, Json<CsvImportSummary>), ApiError> {
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(payload.csv.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| ApiError::validation("csv", &e.to_string()))?
+        .clone();
+    if headers.iter().all(str::is_empty) {
+        return Err(ApiError::validation("csv", "must start with a header row"));
+    }
+    let mapping = map_headers(&headers, &payload.mapping)?;
+    let field_index = |field: &str| mapping.iter().position(|m| m.field.as_deref() == Some(field));
+    let title_index = field_index("title")
+        .ok_or_else(|| ApiError::validation("mapping", "no header maps to 'title'"))?;
+    let index_of: HashMap<&str, usize> = IMPORT_FIELDS
+        .iter()
+        .filter_map(|field| field_index(field).map(|i| (*field, i)))
+        .collect();
+
+    let columns = sqlx::query!(
+        r#"SELECT id as "id!", name, kind FROM columns WHERE project_id = ? ORDER BY sort_order, id"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+    let default_column = columns
+        .iter()
+        .find(|c| c.kind == "todo")
+        .or(columns.first())
+        .map(|c| c.id);
+
+    let mut rows = Vec::new();
+    let mut errors = ApiError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "invalid_csv",
+        "The file has invalid rows; nothing was imported",
+    );
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                errors = errors.with_line_detail(line, "csv", &e.to_string());
+                break;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        if record.len() > headers.len() {
+            errors = errors.with_line_detail(
+                line,
+                "csv",
+                &format!("has {} fields but the header has {}", record.len(), headers.len()),
+            );
+            continue;
+        }
+        let value = |field: &str| {
+            index_of
+                .get(field)
+                .and_then(|&i| record.get(i))
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+
+        let title = record.get(title_index).unwrap_or_default().to_string();
+        if title.is_empty() {
+            errors = errors.with_line_detail(line, "title", "must not be empty");
+        }
+        let priority = value("priority").map(|p| p.to_lowercase()).unwrap_or_else(|| "medium".to_string());
+        if !PRIORITIES.contains(&priority.as_str()) {
+            errors = errors.with_line_detail(
+                line,
+                "priority",
+                &format!("'{}' is not one of {}", priority, PRIORITIES.join(", ")),
+            );
+        }
+        let column = value("column");
+        if column.is_none() && default_column.is_none() {
+            errors = errors.with_line_detail(line, "column", "is required because the project has no columns");
+        }
+        let mut dates = [None, None];
+        for (slot, field) in dates.iter_mut().zip(["due_at", "start_at"]) {
+            match value(field).as_deref().map(parse_timestamp).transpose() {
+                Ok(date) => *slot = date,
+                Err(e) => errors = errors.with_line_detail(line, field, &e),
+            }
+        }
+        let [due_at, start_at] = dates;
+        if let (Some(start), Some(due)) = (&start_at, &due_at) {
+            if start > due {
+                errors = errors.with_line_detail(line, "start_at", "must not be after due_at");
+            }
+        }
+        let tags = value("tags")
+            .map(|tags| {
+                tags.split(',')
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        rows.push(CsvRow {
+            title,
+            description: value("description"),
+            column,
+            priority,
+            tags,
+            source_tag: value("source_tag").unwrap_or_else(|| "csv".to_string()),
+            due_at,
+            start_at,
+        });
+    }
+    if !errors.details.is_empty() {
+        return Err(errors);
+    }
+
+    // Names match existing columns and tags regardless of case; new ones keep the
+    // spelling of their first appearance.
+    let mut column_ids: HashMap<String, i64> = columns.iter().map(|c| (c.name.to_lowercase(), c.id)).collect();
+    let mut columns_created = Vec::new();
+    let mut new_columns = HashSet::new();
+    for name in rows.iter().filter_map(|r| r.column.as_ref()) {
+        let key = name.to_lowercase();
+        if !column_ids.contains_key(&key) && new_columns.insert(key) {
+            columns_created.push(name.clone());
+        }
+    }
+    let mut tag_ids: HashMap<String, i64> = sqlx::query!(r#"SELECT id as "id!", name FROM tags"#)
+        .fetch_all(&state.db)
+        .await?
+        .into_iter()
+        .map(|t| (t.name.to_lowercase(), t.id))
+        .collect();
+    let mut tags_created = Vec::new();
+    let mut new_tags = HashSet::new();
+    for name in rows.iter().flat_map(|r| &r.tags) {
+        let key = name.to_lowercase();
+        if !tag_ids.contains_key(&key) && new_tags.insert(key) {
+            tags_created.push(name.clone());
+        }
+    }
+
+    let mut summary = CsvImportSummary {
+        project_id,
+        dry_run: payload.dry_run,
+        mapping,
+        tasks: rows.len(),
+        columns_created,
+        tags_created,
+        warnings: Vec::new(),
+    };
+    if payload.dry_run {
+        return Ok((StatusCode::OK, Json(summary)));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let max_column_order: Option<i64> =
+        sqlx::query_scalar!(r#"SELECT MAX(sort_order) FROM columns WHERE project_id = ?"#, project_id)
+            .fetch_one(&mut *tx)
+            .await?;
+    let mut column_order = max_column_order.unwrap_or(0);
+    let mut new_columns = Vec::with_capacity(summary.columns_created.len());
+    for name in &summary.columns_created {
+        column_order += 1000;
+        let kind = guess_kind(name);
+        let column = sqlx::query_as!(
+            Column,
+            r#"INSERT INTO columns (project_id, name, sort_order, kind) VALUES (?, ?, ?, ?)
+               RETURNING id as "id!", project_id, name, sort_order, wip_limit, kind, created_at"#,
+            project_id,
+            name,
+            column_order,
+            kind
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        activity::record(
+            &mut *tx,
+            NewActivity::new(&actor, "create", "column", column.id)
+                .project(project_id)
+                .after(&column),
+        )
+        .await?;
+        webhooks::record_event(&mut tx, project_id, "column.created", &column).await?;
+        column_ids.insert(name.to_lowercase(), column.id);
+        new_columns.push(column);
+    }
+    for name in &summary.tags_created {
+        let id = sqlx::query_scalar!(
+            r#"INSERT INTO tags (name, color) VALUES (?, ?) RETURNING id as "id!""#,
+            name,
+            IMPORTED_TAG_COLOR
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tag_ids.insert(name.to_lowercase(), id);
+    }
+
+    let mut next_sort_order: HashMap<i64, i64> = HashMap::new();
+    let mut created = Vec::with_capacity(rows.len());
+    for row in rows {
+        let column_id = match &row.column {
+            Some(name) => column_ids[&name.to_lowercase()],
+            None => default_column.expect("rows without a column were rejected"),
+        };
+        let sort_order = match next_sort_order.get(&column_id) {
+            Some(order) => order + 1000,
+            None => {
+                let max: Option<i64> =
+                    sqlx::query_scalar!(r#"SELECT MAX(sort_order) FROM tasks WHERE column_id = ?"#, column_id)
+                        .fetch_one(&mut *tx)
+                        .await?;
+                max.unwrap_or(0) + 1000
+            }
+        };
+        next_sort_order.insert(column_id, sort_order);
+
+        // Checked row by row, as if each task were created on its own: a full column
+        // fails the whole import in `hard` mode, and is reported once in `soft` mode.
+        if let Some(warning) = check_wip_limit(&mut *tx, column_id).await? {
+            summary.warnings.retain(|w| w.column_id != column_id);
+            summary.warnings.push(warning);
+        }
+
+        let task = sqlx::query_as!(
+            Task,
+            r#"INSERT INTO tasks (project_id, column_id, title, description, priority, sort_order, source_tag, due_at, start_at, completed_at)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?,
+                       CASE WHEN (SELECT kind FROM columns WHERE id = ?) = 'done' THEN datetime('now') END)
+               RETURNING id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!", due_at, start_at, assignee_id, parent_id, completed_at"#,
+            project_id,
+            column_id,
+            row.title,
+            row.description,
+            row.priority,
+            sort_order,
+            row.source_tag,
+            row.due_at,
+            row.start_at,
+            column_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for tag in &row.tags {
+            let tag_id = tag_ids[&tag.to_lowercase()];
+            sqlx::query!(
+                r#"INSERT OR IGNORE INTO task_tags (task_id, tag_id) VALUES (?, ?)"#,
+                task.id,
+                tag_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        activity::record(
+            &mut *tx,
+            NewActivity::new(&actor, "create", "task", task.id)
+                .project(project_id)
+                .task(task.id)
+                .after(&task),
+        )
+        .await?;
+        webhooks::record_event(&mut tx, project_id, "task.created", &task).await?;
+        created.push(task);
+    }
+
+    tx.commit().await?;
+
+    for column in &new_columns {
+        state.events.publish(Some(project_id), "column.created", column);
+    }
+    for task in &created {
+        state.events.publish(Some(project_id), "task.created", task);
+    }
+
+    Ok((StatusCode::CREATED, Json(summary)))
+}
+
+/// Decides which task field each header feeds, checking the caller's mapping against
+/// the file.
+fn map_headers(
+    headers: &csv::StringRecord,
+    mapping: &HashMap<String, String>,
+) -> Result<Vec<HeaderMapping>, ApiError> {
+    if let Some(header) = mapping.keys().find(|h| !headers.iter().any(|name| name == h.as_str())) {
+        return Err(ApiError::validation(
+            "mapping",
+            &format!("'{}' is not a header in the file", header),
+        ));
+    }
+
+    let mut mapped: Vec<HeaderMapping> = Vec::with_capacity(headers.len());
+    for header in headers {
+        let field = match mapping.get(header) {
+            Some(field) if field.is_empty() => None,
+            Some(field) if IMPORT_FIELDS.contains(&field.as_str()) => Some(field.clone()),
+            Some(field) => {
+                return Err(ApiError::validation(
+                    "mapping",
+                    &format!("'{}' is not one of {}", field, IMPORT_FIELDS.join(", ")),
+                ))
+            }
+            None => {
+                let name = header.to_lowercase().replace([' ', '-'], "_");
+                IMPORT_FIELDS.contains(&name.as_str()).then_some(name)
+            }
+        };
+        if let Some(field) = &field {
+            if mapped.iter().any(|m| m.field.as_ref() == Some(field)) {
+                return Err(ApiError::validation(
+                    "mapping",
+                    &format!("more than one header maps to '{}'", field),
+                ));
+            }
+        }
+        mapped.push(HeaderMapping {
+            header: header.to_string(),
+            field,
+        });
+    }
+    Ok(mapped)
+}
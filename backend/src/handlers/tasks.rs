@@ -1,13 +1,24 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    Json,
+    Extension, Json,
 };
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::{Executor, QueryBuilder, Sqlite};
 
+use super::columns::{check_wip_limit, deserialize_some, WipWarning};
+use super::dependencies::{check_blockers, task_links, BlockedWarning, TaskLinks};
 use super::projects::AppState;
-
-#[derive(Debug, Serialize, Clone)]
+use super::search::to_fts_query;
+use crate::access::{project_of_task, require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::attachments::collect_garbage;
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+use crate::webhooks;
+
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
 pub struct Task {
     pub id: i64,
     pub project_id: i64,
@@ -18,6 +29,12 @@ pub struct Task {
     pub sort_order: i64,
     pub source_tag: String,
     pub created_at: String,
+    pub due_at: Option<String>,
+    pub start_at: Option<String>,
+    pub assignee_id: Option<i64>,
+    pub parent_id: Option<i64>,
+    /// When the task entered a `done` column; `None` while it is anywhere else.
+    pub completed_at: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -47,8 +64,72 @@ pub struct TaskWithDetails {
     pub sort_order: i64,
     pub source_tag: String,
     pub created_at: String,
+    pub due_at: Option<String>,
+    pub start_at: Option<String>,
+    pub assignee_id: Option<i64>,
+    pub parent_id: Option<i64>,
+    pub completed_at: Option<String>,
     pub subtasks: Vec<Subtask>,
     pub tags: Vec<Tag>,
+    pub comment_count: i64,
+    pub dependencies: TaskLinks,
+    /// Direct children only; `progress` covers every level below.
+    pub children: Vec<ChildTask>,
+    pub progress: Progress,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChildTask {
+    pub id: i64,
+    pub title: String,
+    pub column_id: i64,
+    pub priority: String,
+    /// Whether the task sits in a `done` column.
+    pub done: bool,
+}
+
+/// Completion of the work under a task: its child tasks at every depth, and the
+/// subtask checkboxes of the task itself and of those children.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Progress {
+    pub tasks_done: i64,
+    pub tasks_total: i64,
+    pub subtasks_done: i64,
+    pub subtasks_total: i64,
+}
+
+/// A rule the change broke but that the project only warns about.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum TaskWarning {
+    WipLimit(WipWarning),
+    Blocked(BlockedWarning),
+}
+
+impl From<WipWarning> for TaskWarning {
+    fn from(warning: WipWarning) -> Self {
+        TaskWarning::WipLimit(warning)
+    }
+}
+
+impl From<BlockedWarning> for TaskWarning {
+    fn from(warning: BlockedWarning) -> Self {
+        TaskWarning::Blocked(warning)
+    }
+}
+
+/// A task as returned from a mutation, plus any WIP-limit or blocker warnings the change triggered.
+#[derive(Debug, Serialize)]
+pub struct TaskResponse {
+    #[serde(flatten)]
+    pub task: Task,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<TaskWarning>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkUpdateResponse {
+    pub warnings: Vec<TaskWarning>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -58,6 +139,12 @@ pub struct CreateTaskRequest {
     pub description: Option<String>,
     pub priority: Option<String>,
     pub source_tag: Option<String>,
+    /// ISO-8601 date or timestamp; a bare date means midnight UTC.
+    pub due_at: Option<String>,
+    pub start_at: Option<String>,
+    pub assignee_id: Option<i64>,
+    /// Places the new task under another task of the same project.
+    pub parent_id: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -68,6 +155,17 @@ pub struct UpdateTaskRequest {
     pub priority: Option<String>,
     pub sort_order: Option<i64>,
     pub tag_ids: Option<Vec<i64>>,
+    /// Absent leaves the date alone; `null` clears it.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub due_at: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub start_at: Option<Option<String>>,
+    /// Absent leaves the assignee alone; `null` unassigns.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub assignee_id: Option<Option<i64>>,
+    /// Absent leaves the task where it is; `null` moves it to the top level.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub parent_id: Option<Option<i64>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -75,12 +173,6 @@ pub struct BulkUpdateItem {
     pub id: i64,
     pub column_id: Option<i64>,
     pub sort_order: Option<i64>,
-    pub tag_ids: Option<Vec<i64>>,
-}
-
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
 }
 
 struct SubtaskRow {
@@ -91,144 +183,524 @@ struct SubtaskRow {
     sort_order: i64,
 }
 
+const MAX_PAGE_SIZE: i64 = 500;
+pub const PRIORITIES: [&str; 4] = ["urgent", "high", "medium", "low"];
+
+#[derive(Debug, Deserialize)]
+pub struct ListTasksQuery {
+    pub column_id: Option<i64>,
+    /// Comma-separated, e.g. `urgent,high`.
+    pub priority: Option<String>,
+    /// Comma-separated; a task must carry every listed tag.
+    pub tag_ids: Option<String>,
+    pub source_tag: Option<String>,
+    pub assignee_id: Option<i64>,
+    /// Only the direct children of this task.
+    pub parent_id: Option<i64>,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub q: Option<String>,
+    /// `sort_order` (default), `created_at`, `priority` or `title`; prefix with `-` to reverse.
+    pub order_by: Option<String>,
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskPage {
+    pub tasks: Vec<Task>,
+    pub next_cursor: Option<String>,
+}
+
+enum TaskOrder {
+    SortOrder,
+    CreatedAt,
+    Priority,
+    Title,
+}
+
+impl TaskOrder {
+    fn parse(field: &str) -> Option<Self> {
+        match field {
+            "sort_order" => Some(Self::SortOrder),
+            "created_at" => Some(Self::CreatedAt),
+            "priority" => Some(Self::Priority),
+            "title" => Some(Self::Title),
+            _ => None,
+        }
+    }
+
+    fn sql_key(&self) -> &'static str {
+        match self {
+            Self::SortOrder => "sort_order",
+            Self::CreatedAt => "created_at",
+            Self::Priority => {
+                "CASE priority WHEN 'urgent' THEN 0 WHEN 'high' THEN 1 WHEN 'medium' THEN 2 ELSE 3 END"
+            }
+            Self::Title => "title COLLATE NOCASE",
+        }
+    }
+
+    fn key_of(&self, task: &Task) -> String {
+        match self {
+            Self::SortOrder => task.sort_order.to_string(),
+            Self::CreatedAt => task.created_at.clone(),
+            Self::Priority => PRIORITIES
+                .iter()
+                .position(|p| *p == task.priority)
+                .unwrap_or(3)
+                .to_string(),
+            Self::Title => task.title.clone(),
+        }
+    }
+
+    fn push_key_bind(
+        &self,
+        builder: &mut QueryBuilder<'_, Sqlite>,
+        key: &str,
+    ) -> Result<(), ApiError> {
+        match self {
+            Self::SortOrder | Self::Priority => {
+                let value = key.parse::<i64>().map_err(|_| {
+                    ApiError::invalid_query("cursor", "Cursor does not match order_by")
+                })?;
+                builder.push_bind(value);
+            }
+            Self::CreatedAt | Self::Title => {
+                builder.push_bind(key.to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// Cursors are `<id>:<sort key>`, hex-encoded so clients treat them as opaque.
+fn encode_cursor(id: i64, key: &str) -> String {
+    format!("{}:{}", id, key)
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn decode_cursor(cursor: &str) -> Result<(i64, String), ()> {
+    let bytes = cursor
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).map_err(|_| ())?;
+            if pair.len() != 2 {
+                return Err(());
+            }
+            u8::from_str_radix(pair, 16).map_err(|_| ())
+        })
+        .collect::<Result<Vec<u8>, ()>>()?;
+    let decoded = String::from_utf8(bytes).map_err(|_| ())?;
+    let (id, key) = decoded.split_once(':').ok_or(())?;
+
+    Ok((id.parse().map_err(|_| ())?, key.to_string()))
+}
+
+/// Accepts RFC 3339 timestamps as well as bare `YYYY-MM-DD` dates and returns the
+/// UTC `YYYY-MM-DD HH:MM:SS` form SQLite's `datetime('now')` produces, so the
+/// result compares correctly against stored `created_at` values.
+pub fn parse_timestamp(value: &str) -> Result<String, String> {
+    const SQLITE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+    let value = value.trim();
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc).format(SQLITE_FORMAT).to_string());
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(dt.format(SQLITE_FORMAT).to_string());
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(format!("{} 00:00:00", date.format("%Y-%m-%d")));
+    }
+
+    Err(format!("'{}' is not an ISO-8601 date or timestamp", value))
+}
+
+/// Normalises an optional date field from a request body, reporting bad values against `field`.
+fn parse_date_field(field: &str, value: Option<&str>) -> Result<Option<String>, ApiError> {
+    value
+        .map(parse_timestamp)
+        .transpose()
+        .map_err(|e| ApiError::validation(field, &e))
+}
+
+fn ensure_schedule(start_at: Option<&str>, due_at: Option<&str>) -> Result<(), ApiError> {
+    match (start_at, due_at) {
+        (Some(start), Some(due)) if start > due => {
+            Err(ApiError::validation("start_at", "must not be after due_at"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Tasks can only live in columns of their own project.
+async fn ensure_column_in_project<'e, E>(
+    executor: E,
+    column_id: i64,
+    project_id: i64,
+) -> Result<(), ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let column_project_id =
+        sqlx::query_scalar!(r#"SELECT project_id FROM columns WHERE id = ?"#, column_id)
+            .fetch_optional(executor)
+            .await?
+            .ok_or_else(|| ApiError::bad_request("column_not_found", "Column not found"))?;
+
+    if column_project_id != project_id {
+        return Err(ApiError::bad_request(
+            "column_project_mismatch",
+            "Column does not belong to this project",
+        ));
+    }
+
+    Ok(())
+}
+
+/// A parent must be a task of the same project, and a task can't be put under itself
+/// or anything below it. `task_id` is `None` for a task that doesn't exist yet.
+async fn ensure_valid_parent<'e, E>(
+    executor: E,
+    task_id: Option<i64>,
+    parent_id: i64,
+    project_id: i64,
+) -> Result<(), ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let row = sqlx::query!(
+        r#"WITH RECURSIVE ancestors(id) AS (
+               SELECT ?
+               UNION
+               SELECT t.parent_id FROM tasks t
+               INNER JOIN ancestors a ON t.id = a.id
+               WHERE t.parent_id IS NOT NULL
+           )
+           SELECT (SELECT project_id FROM tasks WHERE id = ?) as "project_id: i64",
+                  EXISTS(SELECT 1 FROM ancestors WHERE id = ?) as "cycle!: bool""#,
+        parent_id,
+        parent_id,
+        task_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    if row.project_id != Some(project_id) {
+        return Err(ApiError::validation("parent_id", "must be a task in the same project"));
+    }
+    if row.cycle {
+        return Err(ApiError::conflict(
+            "parent_cycle",
+            "A task can't be placed under itself or one of its children",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Rolls up completion over every task below `task_id`.
+async fn task_progress<'e, E>(executor: E, task_id: i64) -> Result<Progress, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let row = sqlx::query!(
+        r#"WITH RECURSIVE descendants(id) AS (
+               SELECT id FROM tasks WHERE parent_id = ?
+               UNION
+               SELECT t.id FROM tasks t INNER JOIN descendants d ON t.parent_id = d.id
+           )
+           SELECT (SELECT COUNT(*) FROM descendants) as "tasks_total!: i64",
+                  (SELECT COUNT(*) FROM descendants d
+                   INNER JOIN tasks t ON t.id = d.id
+                   INNER JOIN columns c ON c.id = t.column_id
+                   WHERE c.kind = 'done') as "tasks_done!: i64",
+                  (SELECT COUNT(*) FROM subtasks
+                   WHERE task_id = ? OR task_id IN (SELECT id FROM descendants)) as "subtasks_total!: i64",
+                  (SELECT COUNT(*) FROM subtasks
+                   WHERE done = 1 AND (task_id = ? OR task_id IN (SELECT id FROM descendants))) as "subtasks_done!: i64""#,
+        task_id,
+        task_id,
+        task_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(Progress {
+        tasks_done: row.tasks_done,
+        tasks_total: row.tasks_total,
+        subtasks_done: row.subtasks_done,
+        subtasks_total: row.subtasks_total,
+    })
+}
+
 pub async fn list_tasks(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(project_id): Path<i64>,
-) -> Result<Json<Vec<Task>>, (StatusCode, Json<ErrorResponse>)> {
-    let tasks = sqlx::query_as!(
-        Task,
-        r#"SELECT id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!"
-           FROM tasks WHERE project_id = ? ORDER BY sort_order"#,
-        project_id
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch tasks: {}", e),
-            }),
-        )
-    })?;
+    Query(query): Query<ListTasksQuery>,
+) -> Result<Json<TaskPage>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
+    let (order, descending) = match query.order_by.as_deref() {
+        None => (TaskOrder::SortOrder, false),
+        Some(value) => {
+            let (field, descending) = match value.strip_prefix('-') {
+                Some(field) => (field, true),
+                None => (value, false),
+            };
+            let order = TaskOrder::parse(field)
+                .ok_or_else(|| {
+                    ApiError::invalid_query("order_by", format!("Unknown order_by field '{}'", field))
+                })?;
+            (order, descending)
+        }
+    };
+
+    let priorities = split_list(query.priority.as_deref());
+    if let Some(invalid) = priorities.iter().find(|p| !PRIORITIES.contains(&p.as_str())) {
+        return Err(ApiError::invalid_query(
+            "priority",
+            format!("Unknown priority '{}'", invalid),
+        ));
+    }
+
+    let tag_ids = split_list(query.tag_ids.as_deref())
+        .iter()
+        .map(|id| id.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| {
+            ApiError::invalid_query("tag_ids", "tag_ids must be a comma-separated list of ids")
+        })?;
+
+    let created_after = query
+        .created_after
+        .as_deref()
+        .map(parse_timestamp)
+        .transpose()
+        .map_err(|e| {
+            ApiError::invalid_query("created_after", format!("Invalid created_after: {}", e))
+        })?;
+    let created_before = query
+        .created_before
+        .as_deref()
+        .map(parse_timestamp)
+        .transpose()
+        .map_err(|e| {
+            ApiError::invalid_query("created_before", format!("Invalid created_before: {}", e))
+        })?;
+
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(decode_cursor)
+        .transpose()
+        .map_err(|_| ApiError::invalid_query("cursor", "Invalid cursor"))?;
+
+    let limit = query.limit.map(|l| l.clamp(1, MAX_PAGE_SIZE));
+
+    let mut builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+        "SELECT id, project_id, column_id, title, description, priority, sort_order, source_tag, created_at, due_at, start_at, assignee_id, parent_id, completed_at FROM tasks WHERE project_id = ",
+    );
+    builder.push_bind(project_id);
 
-    Ok(Json(tasks))
+    if let Some(column_id) = query.column_id {
+        builder.push(" AND column_id = ").push_bind(column_id);
+    }
+
+    if !priorities.is_empty() {
+        builder.push(" AND priority IN (");
+        let mut separated = builder.separated(", ");
+        for priority in &priorities {
+            separated.push_bind(priority.clone());
+        }
+        builder.push(")");
+    }
+
+    for tag_id in &tag_ids {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM task_tags tt WHERE tt.task_id = tasks.id AND tt.tag_id = ")
+            .push_bind(*tag_id)
+            .push(")");
+    }
+
+    if let Some(source_tag) = &query.source_tag {
+        builder.push(" AND source_tag = ").push_bind(source_tag.clone());
+    }
+
+    if let Some(assignee_id) = query.assignee_id {
+        builder.push(" AND assignee_id = ").push_bind(assignee_id);
+    }
+
+    if let Some(parent_id) = query.parent_id {
+        builder.push(" AND parent_id = ").push_bind(parent_id);
+    }
+
+    if let Some(after) = created_after {
+        builder.push(" AND created_at >= ").push_bind(after);
+    }
+
+    if let Some(before) = created_before {
+        builder.push(" AND created_at < ").push_bind(before);
+    }
+
+    if let Some(fts_query) = query.q.as_deref().and_then(to_fts_query) {
+        builder
+            .push(" AND id IN (SELECT rowid FROM tasks_fts WHERE tasks_fts MATCH ")
+            .push_bind(fts_query)
+            .push(")");
+    }
+
+    let key = order.sql_key();
+    let (cmp, direction) = if descending { ("<", "DESC") } else { (">", "ASC") };
+
+    if let Some((after_id, after_key)) = cursor {
+        builder.push(format!(" AND ({} {} ", key, cmp));
+        order.push_key_bind(&mut builder, &after_key)?;
+        builder.push(format!(" OR ({} = ", key));
+        order.push_key_bind(&mut builder, &after_key)?;
+        builder.push(format!(" AND id {} ", cmp)).push_bind(after_id).push("))");
+    }
+
+    builder.push(format!(" ORDER BY {} {}, id {}", key, direction, direction));
+
+    if let Some(limit) = limit {
+        // One extra row tells us whether another page exists.
+        builder.push(" LIMIT ").push_bind(limit + 1);
+    }
+
+    let mut tasks = builder
+        .build_query_as::<Task>()
+        .fetch_all(&state.db)
+        .await?;
+
+    let next_cursor = match limit {
+        Some(limit) if tasks.len() as i64 > limit => {
+            tasks.truncate(limit as usize);
+            tasks.last().map(|last| encode_cursor(last.id, &order.key_of(last)))
+        }
+        _ => None,
+    };
+
+    Ok(Json(TaskPage { tasks, next_cursor }))
 }
 
 pub async fn create_task(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(project_id): Path<i64>,
     Json(payload): Json<CreateTaskRequest>,
-) -> Result<(StatusCode, Json<Task>), (StatusCode, Json<ErrorResponse>)> {
-    let column = sqlx::query!(
-        r#"SELECT id, project_id FROM columns WHERE id = ?"#,
-        payload.column_id
-    )
-    .fetch_optional(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to verify column: {}", e),
-            }),
-        )
-    })?;
-
-    let column = column.ok_or_else(|| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Column not found".to_string(),
-            }),
-        )
-    })?;
-
-    if column.project_id != project_id {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Column does not belong to this project".to_string(),
-            }),
-        ));
+) -> Result<(StatusCode, Json<TaskResponse>), ApiError> {
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    if payload.title.trim().is_empty() {
+        return Err(ApiError::validation("title", "must not be empty"));
     }
 
+    ensure_column_in_project(&state.db, payload.column_id, project_id).await?;
+
     let max_sort_order: Option<i64> = sqlx::query_scalar!(
         r#"SELECT MAX(sort_order) FROM tasks WHERE column_id = ?"#,
         payload.column_id
     )
     .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to get max sort order: {}", e),
-            }),
-        )
-    })?;
+    .await?;
 
     let sort_order = max_sort_order.unwrap_or(0) + 1000;
 
     let priority = payload.priority.unwrap_or_else(|| "medium".to_string());
     let source_tag = payload.source_tag.unwrap_or_else(|| "manual".to_string());
+    let due_at = parse_date_field("due_at", payload.due_at.as_deref())?;
+    let start_at = parse_date_field("start_at", payload.start_at.as_deref())?;
+    ensure_schedule(start_at.as_deref(), due_at.as_deref())?;
+
+    let mut tx = state.db.begin().await?;
+
+    if let Some(parent_id) = payload.parent_id {
+        ensure_valid_parent(&mut *tx, None, parent_id, project_id).await?;
+    }
+
+    let warnings: Vec<TaskWarning> = check_wip_limit(&mut *tx, payload.column_id)
+        .await?
+        .into_iter()
+        .map(TaskWarning::from)
+        .collect();
 
     let task = sqlx::query_as!(
         Task,
-        r#"INSERT INTO tasks (project_id, column_id, title, description, priority, sort_order, source_tag)
-           VALUES (?, ?, ?, ?, ?, ?, ?)
-           RETURNING id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!""#,
+        r#"INSERT INTO tasks (project_id, column_id, title, description, priority, sort_order, source_tag, due_at, start_at, assignee_id, parent_id, completed_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                   CASE WHEN (SELECT kind FROM columns WHERE id = ?) = 'done' THEN datetime('now') END)
+           RETURNING id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!", due_at, start_at, assignee_id, parent_id, completed_at"#,
         project_id,
         payload.column_id,
         payload.title,
         payload.description,
         priority,
         sort_order,
-        source_tag
+        source_tag,
+        due_at,
+        start_at,
+        payload.assignee_id,
+        payload.parent_id,
+        payload.column_id
     )
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to create task: {}", e),
-            }),
-        )
-    })?;
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "create", "task", task.id)
+            .project(task.project_id)
+            .task(task.id)
+            .after(&task),
+    )
+    .await?;
+
+    webhooks::record_event(&mut tx, task.project_id, "task.created", &task).await?;
+
+    tx.commit().await?;
 
-    Ok((StatusCode::CREATED, Json(task)))
+    state.events.publish(Some(task.project_id), "task.created", &task);
+
+    Ok((StatusCode::CREATED, Json(TaskResponse { task, warnings })))
 }
 
 pub async fn get_task(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(id): Path<i64>,
-) -> Result<Json<TaskWithDetails>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<TaskWithDetails>, ApiError> {
+    let project_id = project_of_task(&state.db, id).await?;
+    require_role(&state.db, &user, project_id, Role::Viewer).await?;
+
     let task = sqlx::query_as!(
         Task,
-        r#"SELECT id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!"
+        r#"SELECT id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!", due_at, start_at, assignee_id, parent_id, completed_at
            FROM tasks WHERE id = ?"#,
         id
     )
     .fetch_optional(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch task: {}", e),
-            }),
-        )
-    })?;
-
-    let task = task.ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Task not found".to_string(),
-            }),
-        )
-    })?;
+    .await?;
+
+    let task = task.ok_or_else(|| ApiError::not_found("task_not_found", "Task not found"))?;
 
     let subtask_rows = sqlx::query_as!(
         SubtaskRow,
@@ -236,15 +708,7 @@ pub async fn get_task(
         id
     )
     .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch subtasks: {}", e),
-            }),
-        )
-    })?;
+    .await?;
 
     let subtasks: Vec<Subtask> = subtask_rows
         .into_iter()
@@ -265,15 +729,27 @@ pub async fn get_task(
         id
     )
     .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch tags: {}", e),
-            }),
-        )
-    })?;
+    .await?;
+
+    let comment_count = sqlx::query_scalar!(r#"SELECT COUNT(*) FROM comments WHERE task_id = ?"#, id)
+        .fetch_one(&state.db)
+        .await?;
+
+    let dependencies = task_links(&state.db, id).await?;
+
+    let children = sqlx::query_as!(
+        ChildTask,
+        r#"SELECT t.id as "id!", t.title as "title!", t.column_id as "column_id!", t.priority as "priority!",
+                  c.kind = 'done' as "done!: bool"
+           FROM tasks t
+           INNER JOIN columns c ON c.id = t.column_id
+           WHERE t.parent_id = ? ORDER BY t.sort_order"#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let progress = task_progress(&state.db, id).await?;
 
     Ok(Json(TaskWithDetails {
         id: task.id,
@@ -285,92 +761,105 @@ pub async fn get_task(
         sort_order: task.sort_order,
         source_tag: task.source_tag,
         created_at: task.created_at,
+        due_at: task.due_at,
+        start_at: task.start_at,
+        assignee_id: task.assignee_id,
+        parent_id: task.parent_id,
+        completed_at: task.completed_at,
         subtasks,
         tags,
+        comment_count,
+        dependencies,
+        children,
+        progress,
     }))
 }
 
 pub async fn update_task(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
     Json(payload): Json<UpdateTaskRequest>,
-) -> Result<Json<Task>, (StatusCode, Json<ErrorResponse>)> {
-    let mut tx = state.db.begin().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to start transaction: {}", e),
-            }),
-        )
-    })?;
+) -> Result<Json<TaskResponse>, ApiError> {
+    let project_id = project_of_task(&state.db, id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
+
+    if payload.title.as_deref().is_some_and(|t| t.trim().is_empty()) {
+        return Err(ApiError::validation("title", "must not be empty"));
+    }
+
+    let mut tx = state.db.begin().await?;
 
     let existing = sqlx::query_as!(
         Task,
-        r#"SELECT id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!"
+        r#"SELECT id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!", due_at, start_at, assignee_id, parent_id, completed_at
            FROM tasks WHERE id = ?"#,
         id
     )
     .fetch_optional(&mut *tx)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to fetch task: {}", e),
-            }),
-        )
-    })?;
-
-    let existing = existing.ok_or_else(|| {
-        (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Task not found".to_string(),
-            }),
-        )
-    })?;
+    .await?;
+
+    let existing = existing.ok_or_else(|| ApiError::not_found("task_not_found", "Task not found"))?;
+
+    let mut entry = NewActivity::new(&actor, "update", "task", id)
+        .project(existing.project_id)
+        .task(id)
+        .before(&existing);
+    let mut warnings = Vec::new();
 
     let title = payload.title.unwrap_or(existing.title);
     let description = payload.description.or(existing.description);
     let column_id = payload.column_id.unwrap_or(existing.column_id);
+    if column_id != existing.column_id {
+        ensure_column_in_project(&mut *tx, column_id, existing.project_id).await?;
+        warnings.extend(check_blockers(&mut *tx, id, existing.column_id, column_id).await?.map(TaskWarning::from));
+        warnings.extend(check_wip_limit(&mut *tx, column_id).await?.map(TaskWarning::from));
+        entry.action = "move";
+    }
     let priority = payload.priority.unwrap_or(existing.priority);
     let sort_order = payload.sort_order.unwrap_or(existing.sort_order);
+    let due_at = match payload.due_at {
+        Some(value) => parse_date_field("due_at", value.as_deref())?,
+        None => existing.due_at,
+    };
+    let start_at = match payload.start_at {
+        Some(value) => parse_date_field("start_at", value.as_deref())?,
+        None => existing.start_at,
+    };
+    ensure_schedule(start_at.as_deref(), due_at.as_deref())?;
+    let assignee_id = payload.assignee_id.unwrap_or(existing.assignee_id);
+    let parent_id = payload.parent_id.unwrap_or(existing.parent_id);
+    if let Some(parent) = parent_id.filter(|p| Some(*p) != existing.parent_id) {
+        ensure_valid_parent(&mut *tx, Some(id), parent, existing.project_id).await?;
+    }
 
     let task = sqlx::query_as!(
         Task,
-        r#"UPDATE tasks SET title = ?, description = ?, column_id = ?, priority = ?, sort_order = ?
+        r#"UPDATE tasks SET title = ?, description = ?, column_id = ?, priority = ?, sort_order = ?, due_at = ?, start_at = ?, assignee_id = ?, parent_id = ?,
+               completed_at = CASE WHEN (SELECT kind FROM columns WHERE id = ?) = 'done'
+                                   THEN COALESCE(completed_at, datetime('now')) END
            WHERE id = ?
-           RETURNING id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!""#,
+           RETURNING id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!", due_at, start_at, assignee_id, parent_id, completed_at"#,
         title,
         description,
         column_id,
         priority,
         sort_order,
+        due_at,
+        start_at,
+        assignee_id,
+        parent_id,
+        column_id,
         id
     )
     .fetch_one(&mut *tx)
-    .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to update task: {}", e),
-            }),
-        )
-    })?;
+    .await?;
 
     if let Some(tag_ids) = payload.tag_ids {
         sqlx::query!(r#"DELETE FROM task_tags WHERE task_id = ?"#, id)
             .execute(&mut *tx)
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: format!("Failed to clear existing tags: {}", e),
-                    }),
-                )
-            })?;
+            .await?;
 
         for tag_id in tag_ids {
             sqlx::query!(
@@ -379,122 +868,153 @@ pub async fn update_task(
                 tag_id
             )
             .execute(&mut *tx)
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: format!("Failed to add tag {}: {}", tag_id, e),
-                    }),
-                )
-            })?;
+            .await?;
         }
     }
 
-    tx.commit().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to commit transaction: {}", e),
-            }),
-        )
-    })?;
+    activity::record(&mut *tx, entry.after(&task))
+        .await?;
+
+    let completed = task.completed_at.is_some() && existing.completed_at.is_none();
+    webhooks::record_event(&mut tx, task.project_id, "task.updated", &task).await?;
+    if completed {
+        webhooks::record_event(&mut tx, task.project_id, "task.completed", &task).await?;
+    }
+
+    tx.commit().await?;
+
+    state.events.publish(Some(task.project_id), "task.updated", &task);
+    if completed {
+        state.events.publish(Some(task.project_id), "task.completed", &task);
+    }
 
-    Ok(Json(task))
+    Ok(Json(TaskResponse { task, warnings }))
 }
 
 pub async fn delete_task(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Path(id): Path<i64>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
-    let result = sqlx::query!(r#"DELETE FROM tasks WHERE id = ?"#, id)
-        .execute(&state.db)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to delete task: {}", e),
-                }),
-            )
-        })?;
+) -> Result<StatusCode, ApiError> {
+    let project_id = project_of_task(&state.db, id).await?;
+    require_role(&state.db, &user, project_id, Role::Editor).await?;
 
-    if result.rows_affected() == 0 {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Task not found".to_string(),
-            }),
-        ));
-    }
+    let mut tx = state.db.begin().await?;
+
+    let deleted = sqlx::query_as!(
+        Task,
+        r#"DELETE FROM tasks WHERE id = ?
+           RETURNING id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!", due_at, start_at, assignee_id, parent_id, completed_at"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let deleted = deleted.ok_or_else(|| ApiError::not_found("task_not_found", "Task not found"))?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "delete", "task", id)
+            .project(deleted.project_id)
+            .task(id)
+            .before(&deleted),
+    )
+    .await?;
+
+    let data = serde_json::json!({ "id": id });
+    webhooks::record_event(&mut tx, deleted.project_id, "task.deleted", &data).await?;
+
+    tx.commit().await?;
+
+    collect_garbage(&state.db, &state.blobs).await;
+
+    state.events.publish(Some(deleted.project_id), "task.deleted", &data);
 
     Ok(StatusCode::NO_CONTENT)
 }
 
 pub async fn bulk_update_tasks(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
     Json(payload): Json<Vec<BulkUpdateItem>>,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
-    let mut tx = state.db.begin().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to start transaction: {}", e),
-            }),
-        )
-    })?;
+) -> Result<Json<BulkUpdateResponse>, ApiError> {
+    let mut tx = state.db.begin().await?;
+
+    let mut updated = Vec::new();
+    let mut warnings = Vec::new();
 
     for item in payload {
-        let existing = sqlx::query!(r#"SELECT id, column_id, sort_order FROM tasks WHERE id = ?"#, item.id)
-            .fetch_optional(&mut *tx)
-            .await
-            .map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: format!("Failed to fetch task {}: {}", item.id, e),
-                    }),
-                )
-            })?;
+        let existing = sqlx::query_as!(
+            Task,
+            r#"SELECT id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!", due_at, start_at, assignee_id, parent_id, completed_at
+               FROM tasks WHERE id = ?"#,
+            item.id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
 
         let existing = existing.ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: format!("Task {} not found", item.id),
-                }),
-            )
+            ApiError::not_found("task_not_found", format!("Task {} not found", item.id))
         })?;
+        require_role(&mut *tx, &user, existing.project_id, Role::Editor).await?;
 
         let column_id = item.column_id.unwrap_or(existing.column_id);
+        if column_id != existing.column_id {
+            ensure_column_in_project(&mut *tx, column_id, existing.project_id).await?;
+            warnings.extend(
+                check_blockers(&mut *tx, item.id, existing.column_id, column_id)
+                    .await?
+                    .map(TaskWarning::from),
+            );
+            warnings.extend(check_wip_limit(&mut *tx, column_id).await?.map(TaskWarning::from));
+        }
         let sort_order = item.sort_order.unwrap_or(existing.sort_order);
 
-        sqlx::query!(
-            r#"UPDATE tasks SET column_id = ?, sort_order = ? WHERE id = ?"#,
+        let task = sqlx::query_as!(
+            Task,
+            r#"UPDATE tasks
+               SET column_id = ?, sort_order = ?,
+                   completed_at = CASE WHEN (SELECT kind FROM columns WHERE id = ?) = 'done'
+                                       THEN COALESCE(completed_at, datetime('now')) END
+               WHERE id = ?
+               RETURNING id as "id!", project_id as "project_id!", column_id as "column_id!", title as "title!", description, priority as "priority!", sort_order as "sort_order!", source_tag as "source_tag!", created_at as "created_at!", due_at, start_at, assignee_id, parent_id, completed_at"#,
             column_id,
             sort_order,
+            column_id,
             item.id
         )
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Failed to update task {}: {}", item.id, e),
-                }),
-            )
-        })?;
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let action = if task.column_id != existing.column_id { "move" } else { "update" };
+        activity::record(
+            &mut *tx,
+            NewActivity::new(&actor, action, "task", task.id)
+                .project(task.project_id)
+                .task(task.id)
+                .before(&existing)
+                .after(&task),
+        )
+        .await?;
+
+        let completed = task.completed_at.is_some() && existing.completed_at.is_none();
+        webhooks::record_event(&mut tx, task.project_id, "task.updated", &task).await?;
+        if completed {
+            webhooks::record_event(&mut tx, task.project_id, "task.completed", &task).await?;
+        }
+        updated.push((task, completed));
     }
 
-    tx.commit().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Failed to commit transaction: {}", e),
-            }),
-        )
-    })?;
+    tx.commit().await?;
+
+    for (task, completed) in &updated {
+        state.events.publish(Some(task.project_id), "task.updated", task);
+        if *completed {
+            state.events.publish(Some(task.project_id), "task.completed", task);
+        }
+    }
 
-    Ok(StatusCode::OK)
+    Ok(Json(BulkUpdateResponse { warnings }))
 }
@@ -0,0 +1,367 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use super::projects::AppState;
+use super::tasks::parse_timestamp;
+use crate::access::{require_role, Role, Scope};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{generate_token, hash_token, Actor, CurrentUser};
+
+/// How much of a token is kept in the clear, enough to tell tokens apart in a list.
+const VISIBLE_PREFIX_LEN: usize = 8;
+
+#[derive(Debug, Serialize)]
+pub struct User {
+    pub id: i64,
+    pub name: String,
+    pub display_name: Option<String>,
+    pub created_at: String,
+}
+
+/// The caller. `id` is `null` when authenticated with the shared admin key.
+#[derive(Debug, Serialize)]
+pub struct Me {
+    pub id: Option<i64>,
+    pub name: String,
+    pub is_admin: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    /// `None` for tokens minted with the shared admin key.
+    pub user_id: Option<i64>,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Option<Vec<String>>,
+    pub project_ids: Option<Vec<i64>>,
+    pub expires_at: Option<String>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+/// Returned once, when the token is created; only its hash is kept.
+#[derive(Debug, Serialize)]
+pub struct CreatedToken {
+    #[serde(flatten)]
+    pub info: ApiToken,
+    pub token: String,
+}
+
+struct TokenRow {
+    id: i64,
+    user_id: Option<i64>,
+    name: String,
+    prefix: String,
+    scopes: Option<String>,
+    project_ids: Option<String>,
+    expires_at: Option<String>,
+    created_at: String,
+    last_used_at: Option<String>,
+}
+
+impl From<TokenRow> for ApiToken {
+    fn from(row: TokenRow) -> Self {
+        ApiToken {
+            id: row.id,
+            user_id: row.user_id,
+            name: row.name,
+            prefix: row.prefix,
+            scopes: row
+                .scopes
+                .map(|scopes| scopes.split_whitespace().map(str::to_string).collect()),
+            project_ids: row.project_ids.and_then(|ids| serde_json::from_str(&ids).ok()),
+            expires_at: row.expires_at,
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUserRequest {
+    pub name: String,
+    pub display_name: Option<String>,
+}
+
+/// `scopes`, `project_ids` and `expires_at` narrow the token for automation; leave them
+/// out for a token that can do whatever its user can.
+#[derive(Debug, Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scopes: Option<Vec<String>>,
+    pub project_ids: Option<Vec<i64>>,
+    pub expires_at: Option<String>,
+}
+
+pub async fn list_users(State(state): State<AppState>) -> Result<Json<Vec<User>>, ApiError> {
+    let users = sqlx::query_as!(
+        User,
+        r#"SELECT id as "id!", name, display_name, created_at FROM users ORDER BY name"#
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(users))
+}
+
+pub async fn create_user(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Json(payload): Json<CreateUserRequest>,
+) -> Result<(StatusCode, Json<User>), ApiError> {
+    user.ensure_admin()?;
+
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::validation("name", "must not be empty"));
+    }
+    if name.chars().any(char::is_whitespace) {
+        return Err(ApiError::validation("name", "must not contain spaces"));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let created = sqlx::query_as!(
+        User,
+        r#"INSERT INTO users (name, display_name) VALUES (?, ?)
+           RETURNING id as "id!", name, display_name, created_at"#,
+        name,
+        payload.display_name
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "create", "user", created.id).after(&created),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+pub async fn get_me(Extension(user): Extension<CurrentUser>) -> Json<Me> {
+    Json(Me {
+        is_admin: user.is_admin(),
+        id: user.id,
+        name: user.name,
+    })
+}
+
+/// Active tokens only; revoked ones are kept for the audit trail but not listed.
+pub async fn list_tokens(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(user_id): Path<i64>,
+) -> Result<Json<Vec<ApiToken>>, ApiError> {
+    user.ensure_self_or_admin(user_id)?;
+
+    Ok(Json(active_tokens(&state.db, Some(user_id)).await?))
+}
+
+pub async fn create_token(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(user_id): Path<i64>,
+    Json(payload): Json<CreateTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedToken>), ApiError> {
+    user.ensure_self_or_admin(user_id)?;
+
+    sqlx::query_scalar!(r#"SELECT id FROM users WHERE id = ?"#, user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| ApiError::not_found("user_not_found", "User not found"))?;
+
+    let created = issue_token(&state.db, &user, &actor, Some(user_id), payload).await?;
+
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+/// The caller's own tokens. For the admin key, the tokens minted with it.
+pub async fn list_own_tokens(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<Json<Vec<ApiToken>>, ApiError> {
+    Ok(Json(active_tokens(&state.db, user.id).await?))
+}
+
+/// Mints a token for the caller. With the admin key the token belongs to no user and
+/// acts as the admin key within its scopes and projects.
+pub async fn create_own_token(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Json(payload): Json<CreateTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedToken>), ApiError> {
+    let created = issue_token(&state.db, &user, &actor, user.id, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+async fn active_tokens(db: &SqlitePool, user_id: Option<i64>) -> Result<Vec<ApiToken>, ApiError> {
+    let rows = sqlx::query_as!(
+        TokenRow,
+        r#"SELECT id as "id!", user_id, name, prefix, scopes, project_ids, expires_at, created_at, last_used_at
+           FROM api_tokens
+           WHERE user_id IS ? AND revoked_at IS NULL
+           ORDER BY id"#,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.into_iter().map(ApiToken::from).collect())
+}
+
+async fn issue_token(
+    db: &SqlitePool,
+    user: &CurrentUser,
+    actor: &Actor,
+    owner_id: Option<i64>,
+    payload: CreateTokenRequest,
+) -> Result<CreatedToken, ApiError> {
+    // Otherwise a narrowed token could mint itself a broader one.
+    if user.is_restricted() {
+        return Err(ApiError::forbidden(
+            "restricted_token",
+            "Tokens with scopes or project limits can't create other tokens",
+        ));
+    }
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::validation("name", "must not be empty"));
+    }
+
+    let scopes = match payload.scopes {
+        Some(scopes) if scopes.is_empty() => {
+            return Err(ApiError::validation("scopes", "must list at least one scope"));
+        }
+        Some(scopes) => {
+            if let Some(unknown) = scopes.iter().find(|s| Scope::parse(s).is_none()) {
+                let known: Vec<&str> = Scope::ALL.iter().map(|s| s.as_str()).collect();
+                return Err(ApiError::validation(
+                    "scopes",
+                    &format!("'{}' is not a scope; use one of {}", unknown, known.join(", ")),
+                ));
+            }
+            Some(scopes.join(" "))
+        }
+        None => None,
+    };
+
+    let project_ids = match payload.project_ids {
+        Some(ids) if ids.is_empty() => {
+            return Err(ApiError::validation("project_ids", "must list at least one project"));
+        }
+        Some(ids) => {
+            // Missing projects and ones the caller can't see fail alike, so the ids
+            // can't be used to probe which projects exist.
+            for id in &ids {
+                let exists = sqlx::query_scalar!(r#"SELECT id FROM projects WHERE id = ?"#, id)
+                    .fetch_optional(db)
+                    .await?;
+                if exists.is_none() {
+                    return Err(ApiError::not_found("project_not_found", "Project not found"));
+                }
+                require_role(db, user, *id, Role::Viewer).await?;
+            }
+            Some(serde_json::to_string(&ids).map_err(|e| ApiError::internal(e.to_string()))?)
+        }
+        None => None,
+    };
+
+    let expires_at = payload
+        .expires_at
+        .as_deref()
+        .map(parse_timestamp)
+        .transpose()
+        .map_err(|e| ApiError::validation("expires_at", &e))?;
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    if expires_at.as_ref().is_some_and(|at| *at <= now) {
+        return Err(ApiError::validation("expires_at", "must be in the future"));
+    }
+
+    let token = generate_token();
+    let token_hash = hash_token(&token);
+    let prefix = &token[..VISIBLE_PREFIX_LEN];
+
+    let mut tx = db.begin().await?;
+
+    let row = sqlx::query_as!(
+        TokenRow,
+        r#"INSERT INTO api_tokens (user_id, name, token_hash, prefix, scopes, project_ids, expires_at)
+           VALUES (?, ?, ?, ?, ?, ?, ?)
+           RETURNING id as "id!", user_id, name, prefix, scopes, project_ids, expires_at, created_at, last_used_at"#,
+        owner_id,
+        payload.name,
+        token_hash,
+        prefix,
+        scopes,
+        project_ids,
+        expires_at
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let info = ApiToken::from(row);
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(actor, "create", "api_token", info.id).after(&info),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(CreatedToken { info, token })
+}
+
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = state.db.begin().await?;
+
+    let existing: ApiToken = sqlx::query_as!(
+        TokenRow,
+        r#"SELECT id as "id!", user_id, name, prefix, scopes, project_ids, expires_at, created_at, last_used_at
+           FROM api_tokens
+           WHERE id = ? AND revoked_at IS NULL"#,
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| ApiError::not_found("token_not_found", "Token not found"))?
+    .into();
+
+    match existing.user_id {
+        Some(owner_id) => user.ensure_self_or_admin(owner_id)?,
+        None => user.ensure_admin()?,
+    }
+
+    sqlx::query!(r#"UPDATE api_tokens SET revoked_at = datetime('now') WHERE id = ?"#, id)
+        .execute(&mut *tx)
+        .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "revoke", "api_token", id).before(&existing),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,381 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Sqlite};
+
+use super::projects::AppState;
+use crate::access::{require_role, Role};
+use crate::activity::{self, NewActivity};
+use crate::error::ApiError;
+use crate::middleware::{Actor, CurrentUser};
+use crate::webhooks::{event_matches, EVENT_KINDS};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+/// A subscription that POSTs matching board events of one project to `url`.
+#[derive(Debug, Serialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub project_id: i64,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: String,
+}
+
+/// Returned on creation only; receivers need the secret to check signatures.
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+struct WebhookRow {
+    id: i64,
+    project_id: i64,
+    url: String,
+    events: String,
+    active: i64,
+    created_at: String,
+}
+
+impl From<WebhookRow> for Webhook {
+    fn from(row: WebhookRow) -> Self {
+        Webhook {
+            id: row.id,
+            project_id: row.project_id,
+            url: row.url,
+            events: row.events.split_whitespace().map(str::to_string).collect(),
+            active: row.active != 0,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// `events` defaults to every event; `secret` is generated when left out.
+#[derive(Debug, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub events: Option<Vec<String>>,
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWebhookRequest {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Delivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event: String,
+    pub status: String,
+    pub attempts: i64,
+    pub response_status: Option<i64>,
+    pub last_error: Option<String>,
+    pub payload: Option<serde_json::Value>,
+    pub next_attempt_at: String,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
+
+/// Newest first; pass `next_before` back as `before` to fetch the next page.
+#[derive(Debug, Serialize)]
+pub struct DeliveryPage {
+    pub items: Vec<Delivery>,
+    pub next_before: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryQuery {
+    pub limit: Option<i64>,
+    pub before: Option<i64>,
+    pub status: Option<String>,
+}
+
+struct DeliveryRow {
+    id: i64,
+    webhook_id: i64,
+    event: String,
+    status: String,
+    attempts: i64,
+    response_status: Option<i64>,
+    last_error: Option<String>,
+    payload: String,
+    next_attempt_at: String,
+    created_at: String,
+    delivered_at: Option<String>,
+}
+
+impl From<DeliveryRow> for Delivery {
+    fn from(row: DeliveryRow) -> Self {
+        Delivery {
+            id: row.id,
+            webhook_id: row.webhook_id,
+            event: row.event,
+            status: row.status,
+            attempts: row.attempts,
+            response_status: row.response_status,
+            last_error: row.last_error,
+            payload: serde_json::from_str(&row.payload).ok(),
+            next_attempt_at: row.next_attempt_at,
+            created_at: row.created_at,
+            delivered_at: row.delivered_at,
+        }
+    }
+}
+
+fn validate_url(url: &str) -> Result<(), ApiError> {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+        _ => Err(ApiError::validation("url", "must be an http or https URL")),
+    }
+}
+
+/// Every pattern must select at least one known event, so typos don't go unnoticed.
+fn event_filter(events: Option<Vec<String>>) -> Result<String, ApiError> {
+    let Some(events) = events else {
+        return Ok("*".to_string());
+    };
+    if events.is_empty() {
+        return Err(ApiError::validation("events", "must list at least one event"));
+    }
+    if let Some(unknown) = events
+        .iter()
+        .find(|pattern| !EVENT_KINDS.iter().any(|kind| event_matches(pattern, kind)))
+    {
+        return Err(ApiError::validation(
+            "events",
+            &format!("'{}' matches no event; use one of {} or a pattern like task.*", unknown, EVENT_KINDS.join(", ")),
+        ));
+    }
+
+    Ok(events.join(" "))
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("whsec_{}", hex)
+}
+
+async fn find_webhook<'e, E>(executor: E, id: i64) -> Result<WebhookRow, ApiError>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query_as!(
+        WebhookRow,
+        r#"SELECT id as "id!", project_id, url, events, active, created_at FROM webhooks WHERE id = ?"#,
+        id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| ApiError::not_found("webhook_not_found", "Webhook not found"))
+}
+
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(project_id): Path<i64>,
+) -> Result<Json<Vec<Webhook>>, ApiError> {
+    require_role(&state.db, &user, project_id, Role::Admin).await?;
+
+    let rows = sqlx::query_as!(
+        WebhookRow,
+        r#"SELECT id as "id!", project_id, url, events, active, created_at
+           FROM webhooks WHERE project_id = ? ORDER BY id"#,
+        project_id
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(rows.into_iter().map(Webhook::from).collect()))
+}
+
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(project_id): Path<i64>,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<CreatedWebhook>), ApiError> {
+    require_role(&state.db, &user, project_id, Role::Admin).await?;
+
+    validate_url(&payload.url)?;
+    let events = event_filter(payload.events)?;
+    let secret = match payload.secret {
+        Some(secret) if secret.trim().is_empty() => {
+            return Err(ApiError::validation("secret", "must not be empty"));
+        }
+        Some(secret) => secret,
+        None => generate_secret(),
+    };
+
+    let mut tx = state.db.begin().await?;
+
+    let webhook: Webhook = sqlx::query_as!(
+        WebhookRow,
+        r#"INSERT INTO webhooks (project_id, url, events, secret) VALUES (?, ?, ?, ?)
+           RETURNING id as "id!", project_id, url, events, active, created_at"#,
+        project_id,
+        payload.url,
+        events,
+        secret
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .into();
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "create", "webhook", webhook.id)
+            .project(project_id)
+            .after(&webhook),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(CreatedWebhook { webhook, secret })))
+}
+
+pub async fn update_webhook(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i64>,
+    Json(payload): Json<UpdateWebhookRequest>,
+) -> Result<Json<Webhook>, ApiError> {
+    let mut tx = state.db.begin().await?;
+
+    let existing: Webhook = find_webhook(&mut *tx, id).await?.into();
+    require_role(&mut *tx, &user, existing.project_id, Role::Admin).await?;
+
+    let url = match payload.url {
+        Some(url) => {
+            validate_url(&url)?;
+            url
+        }
+        None => existing.url.clone(),
+    };
+    let events = match payload.events {
+        Some(events) => event_filter(Some(events))?,
+        None => existing.events.join(" "),
+    };
+    let active = payload.active.unwrap_or(existing.active);
+
+    let webhook: Webhook = sqlx::query_as!(
+        WebhookRow,
+        r#"UPDATE webhooks SET url = ?, events = ?, active = ? WHERE id = ?
+           RETURNING id as "id!", project_id, url, events, active, created_at"#,
+        url,
+        events,
+        active,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .into();
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "update", "webhook", id)
+            .project(webhook.project_id)
+            .before(&existing)
+            .after(&webhook),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(webhook))
+}
+
+/// Pending deliveries go with the webhook.
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(actor): Extension<Actor>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    let mut tx = state.db.begin().await?;
+
+    let existing: Webhook = find_webhook(&mut *tx, id).await?.into();
+    require_role(&mut *tx, &user, existing.project_id, Role::Admin).await?;
+
+    sqlx::query!(r#"DELETE FROM webhook_deliveries WHERE webhook_id = ?"#, id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(r#"DELETE FROM webhooks WHERE id = ?"#, id)
+        .execute(&mut *tx)
+        .await?;
+
+    activity::record(
+        &mut *tx,
+        NewActivity::new(&actor, "delete", "webhook", id)
+            .project(existing.project_id)
+            .before(&existing),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The delivery log, optionally narrowed to `pending`, `delivered` or `failed`.
+pub async fn list_deliveries(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(id): Path<i64>,
+    Query(query): Query<DeliveryQuery>,
+) -> Result<Json<DeliveryPage>, ApiError> {
+    let webhook = find_webhook(&state.db, id).await?;
+    require_role(&state.db, &user, webhook.project_id, Role::Admin).await?;
+
+    if let Some(status) = query.status.as_deref() {
+        if !matches!(status, "pending" | "delivered" | "failed") {
+            return Err(ApiError::invalid_query("status", "must be pending, delivered or failed"));
+        }
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let fetch = limit + 1;
+
+    let mut rows = sqlx::query_as!(
+        DeliveryRow,
+        r#"SELECT id as "id!", webhook_id as "webhook_id!", event, status, attempts, response_status, last_error, payload,
+                  next_attempt_at, created_at, delivered_at
+           FROM webhook_deliveries
+           WHERE webhook_id = ? AND (? IS NULL OR status = ?) AND (? IS NULL OR id < ?)
+           ORDER BY id DESC
+           LIMIT ?"#,
+        id,
+        query.status,
+        query.status,
+        query.before,
+        query.before,
+        fetch
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    let next_before = if has_more { rows.last().map(|r| r.id) } else { None };
+
+    Ok(Json(DeliveryPage {
+        items: rows.into_iter().map(Delivery::from).collect(),
+        next_before,
+    }))
+}
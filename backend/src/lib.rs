@@ -1,3 +1,10 @@
+pub mod access;
+pub mod activity;
+pub mod attachments;
 pub mod db;
+pub mod error;
+pub mod events;
 pub mod handlers;
 pub mod middleware;
+pub mod trello;
+pub mod webhooks;
@@ -1,21 +1,32 @@
+mod access;
+mod activity;
+mod attachments;
 mod db;
+mod error;
+mod events;
 mod handlers;
 mod middleware;
+mod trello;
+mod webhooks;
 
 use axum::{
-    extract::State,
+    extract::{DefaultBodyLimit, State},
     routing::{delete, get, post, put},
     Json, Router,
 };
 use serde::Serialize;
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
-use axum::middleware::from_fn;
+use axum::middleware::from_fn_with_state;
 
 use handlers::projects::{
     AppState, create_project, list_projects, get_project, update_project, delete_project, reorder_projects,
 };
 
+/// Trello exports carry the board's whole action history, so they run far past the
+/// default body limit.
+const TRELLO_IMPORT_LIMIT: usize = 64 * 1024 * 1024;
+
 #[derive(Debug, Serialize)]
 struct HealthResponse {
     status: String,
@@ -39,13 +50,28 @@ async fn main() {
             url
         });
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        let code = run_migrate_command(&database_url, args.get(1).map(String::as_str)).await;
+        std::process::exit(code);
+    }
+
     tracing::info!("Initializing database at: {}", database_url);
 
     let pool = db::init_db(&database_url)
         .await
         .expect("Failed to initialize database");
 
-    let state = AppState { db: pool };
+    let blobs = attachments::BlobStore::from_env(&database_url);
+    attachments::collect_garbage(&pool, &blobs).await;
+
+    let state = AppState {
+        db: pool,
+        events: events::EventBus::new(),
+        blobs,
+    };
+
+    webhooks::spawn(state.db.clone(), &state.events, webhooks::DeliverySettings::default());
 
     let protected_routes = Router::new()
         .route("/api/projects", post(create_project))
@@ -54,6 +80,33 @@ async fn main() {
         .route("/api/projects/{id}", put(update_project))
         .route("/api/projects/{id}", delete(delete_project))
         .route("/api/projects/reorder", put(reorder_projects))
+        .route("/api/projects/import", post(handlers::export::import_project))
+        .route(
+            "/api/projects/import/trello",
+            post(handlers::export::import_trello).layer(DefaultBodyLimit::max(TRELLO_IMPORT_LIMIT)),
+        )
+        .route("/api/projects/{id}/export", get(handlers::export::export_project))
+        .route("/api/projects/{id}/events", get(handlers::events::project_events))
+        .route("/api/projects/{id}/activity", get(handlers::activity::list_project_activity))
+        .route("/api/projects/{id}/metrics", get(handlers::metrics::get_project_metrics))
+        .route("/api/projects/{id}/cfd", get(handlers::metrics::get_cumulative_flow))
+        .route("/api/projects/{id}/cfd.csv", get(handlers::metrics::get_cumulative_flow_csv))
+        .route("/api/projects/{id}/burndown", get(handlers::metrics::get_burndown))
+        .route("/api/projects/{id}/burndown.csv", get(handlers::metrics::get_burndown_csv))
+        .route("/api/projects/{id}/members", get(handlers::members::list_members))
+        .route("/api/projects/{id}/members/{user_id}", put(handlers::members::set_member))
+        .route("/api/projects/{id}/members/{user_id}", delete(handlers::members::remove_member))
+        .route("/api/projects/{id}/calendar-feed", get(handlers::calendar::get_project_feed))
+        .route("/api/projects/{id}/calendar-feed", post(handlers::calendar::rotate_project_feed))
+        .route("/api/projects/{id}/calendar-feed", delete(handlers::calendar::delete_project_feed))
+        .route("/api/projects/{id}/webhooks", get(handlers::webhooks::list_webhooks))
+        .route("/api/projects/{id}/webhooks", post(handlers::webhooks::create_webhook))
+        .route("/api/webhooks/{id}", put(handlers::webhooks::update_webhook))
+        .route("/api/webhooks/{id}", delete(handlers::webhooks::delete_webhook))
+        .route("/api/webhooks/{id}/deliveries", get(handlers::webhooks::list_deliveries))
+        .route("/api/calendar-feed", get(handlers::calendar::get_global_feed))
+        .route("/api/calendar-feed", post(handlers::calendar::rotate_global_feed))
+        .route("/api/calendar-feed", delete(handlers::calendar::delete_global_feed))
         .route("/api/projects/{id}/columns", get(handlers::columns::list_columns))
         .route("/api/projects/{id}/columns", post(handlers::columns::create_column))
         .route("/api/projects/{id}/columns/reorder", put(handlers::columns::reorder_columns))
@@ -61,13 +114,34 @@ async fn main() {
         .route("/api/columns/{id}", delete(handlers::columns::delete_column))
         .route("/api/projects/{project_id}/tasks", get(handlers::tasks::list_tasks))
         .route("/api/projects/{project_id}/tasks", post(handlers::tasks::create_task))
+        .route("/api/projects/{project_id}/tasks.csv", get(handlers::task_csv::export_tasks_csv))
+        .route("/api/projects/{project_id}/tasks/import", post(handlers::task_csv::import_tasks_csv))
+        .route("/api/tasks/overdue", get(handlers::schedule::list_overdue_tasks))
+        .route("/api/tasks/upcoming", get(handlers::schedule::list_upcoming_tasks))
         .route("/api/tasks/{id}", get(handlers::tasks::get_task))
         .route("/api/tasks/{id}", put(handlers::tasks::update_task))
         .route("/api/tasks/{id}", delete(handlers::tasks::delete_task))
+        .route("/api/tasks/{id}/history", get(handlers::activity::get_task_history))
         .route("/api/tasks/bulk-update", put(handlers::tasks::bulk_update_tasks))
         .route("/api/tasks/{id}/subtasks", post(handlers::subtasks::create_subtask))
+        .route("/api/tasks/{id}/comments", get(handlers::comments::list_comments))
+        .route("/api/tasks/{id}/comments", post(handlers::comments::create_comment))
+        .route("/api/comments/{id}", put(handlers::comments::update_comment))
+        .route("/api/comments/{id}", delete(handlers::comments::delete_comment))
+        .route("/api/tasks/{id}/dependencies", get(handlers::dependencies::list_dependencies))
+        .route("/api/tasks/{id}/dependencies", post(handlers::dependencies::create_dependency))
+        .route("/api/dependencies/{id}", delete(handlers::dependencies::delete_dependency))
+        .route("/api/tasks/{id}/attachments", get(handlers::attachments::list_attachments))
+        // The handler enforces the configured attachment size limit while streaming.
+        .route(
+            "/api/tasks/{id}/attachments",
+            post(handlers::attachments::upload_attachment).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/api/attachments/{id}", get(handlers::attachments::download_attachment))
+        .route("/api/attachments/{id}", delete(handlers::attachments::delete_attachment))
         .route("/api/subtasks/{id}", put(handlers::subtasks::update_subtask))
         .route("/api/subtasks/{id}", delete(handlers::subtasks::delete_subtask))
+        .route("/api/search", get(handlers::search::search_tasks))
         .route("/api/tags", get(handlers::tags::list_tags))
         .route("/api/tags", post(handlers::tags::create_tag))
         .route("/api/tags/{id}", delete(handlers::tags::delete_tag))
@@ -76,11 +150,22 @@ async fn main() {
         .route("/api/linked-paths/{id}", delete(handlers::linked_paths::delete_linked_path))
         .route("/api/linked-paths/by-path", delete(handlers::linked_paths::delete_linked_path_by_path))
         .route("/api/linked-paths/lookup", get(handlers::linked_paths::lookup_linked_path))
+        .route("/api/users", get(handlers::users::list_users))
+        .route("/api/users", post(handlers::users::create_user))
+        .route("/api/users/me", get(handlers::users::get_me))
+        .route("/api/users/{id}/tokens", get(handlers::users::list_tokens))
+        .route("/api/users/{id}/tokens", post(handlers::users::create_token))
+        .route("/api/tokens", get(handlers::users::list_own_tokens))
+        .route("/api/tokens", post(handlers::users::create_own_token))
+        .route("/api/tokens/{id}", delete(handlers::users::revoke_token))
         .with_state(state.clone())
-        .layer(from_fn(middleware::api_key_auth));
+        .layer(from_fn_with_state(state.clone(), middleware::api_key_auth));
 
     let app = Router::new()
         .route("/health", get(health_check))
+        // Calendar clients can't send X-API-Key; these check the feed token in the URL instead.
+        .route("/api/projects/{id}/calendar.ics", get(handlers::calendar::project_calendar))
+        .route("/api/calendar.ics", get(handlers::calendar::global_calendar))
         .merge(protected_routes)
         .layer(ServiceBuilder::new().layer(CorsLayer::permissive()))
         .with_state(state);
@@ -89,7 +174,7 @@ async fn main() {
     let addr = format!("0.0.0.0:{}", port);
     let listener = tokio::net::TcpListener::bind(&addr)
         .await
-        .expect(&format!("Failed to bind to {}", addr));
+        .unwrap_or_else(|e| panic!("Failed to bind to {}: {}", addr, e));
 
     tracing::info!("Server listening on {}", addr);
 
@@ -98,6 +183,47 @@ async fn main() {
         .expect("Server error");
 }
 
+/// `kanban-board migrate status|up|down`
+async fn run_migrate_command(database_url: &str, action: Option<&str>) -> i32 {
+    let pool = db::connect(database_url)
+        .await
+        .expect("Failed to open database");
+
+    let result = match action {
+        Some("status") => db::migration_status(&pool).await.map(|statuses| {
+            for status in statuses {
+                match status.applied_at {
+                    Some(at) => println!("  [x] {} (applied {})", status.name, at),
+                    None => println!("  [ ] {} (pending)", status.name),
+                }
+            }
+        }),
+        Some("up") => db::run_migrations(&pool).await.map(|applied| {
+            if applied.is_empty() {
+                println!("Database is up to date");
+            } else {
+                println!("Applied {} migration(s)", applied.len());
+            }
+        }),
+        Some("down") => db::revert_last_migration(&pool).await.map(|reverted| match reverted {
+            Some(version) => println!("Reverted migration {}", version),
+            None => println!("No migrations to revert"),
+        }),
+        _ => {
+            eprintln!("Usage: kanban-board migrate <status|up|down>");
+            return 2;
+        }
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Migration failed: {}", e);
+            1
+        }
+    }
+}
+
 async fn health_check(State(_state): State<AppState>) -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "ok".to_string(),
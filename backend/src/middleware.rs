@@ -1,47 +1,234 @@
 use axum::{
-    extract::Request,
-    http::StatusCode,
+    extract::{Request, State},
+    http::Method,
     middleware::Next,
     response::Response,
-    Json,
 };
-use serde::Serialize;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
 
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
+use crate::access::Scope;
+use crate::error::ApiError;
+use crate::handlers::projects::AppState;
+
+/// Personal tokens start with this so they are recognisable in configs and leak scanners.
+const TOKEN_PREFIX: &str = "kb_";
+
+/// Who performed a request, as recorded in the activity log.
+///
+/// Requests made with a personal token are attributed to that token's user, and those
+/// made with a token minted by the admin key to `token:<name>`. With the shared
+/// `KANBAN_API_KEY`, clients identify themselves with an optional `X-Actor` header (the
+/// CLI sends `rk@<hostname>`, the web app sends `web`); otherwise the API key is the actor.
+#[derive(Debug, Clone)]
+pub struct Actor(pub String);
+
+/// The authenticated caller.
+///
+/// `id` is `None` for the shared `KANBAN_API_KEY`, which is kept as a bootstrap admin
+/// credential so existing setups keep working and the first users can be created, and
+/// for tokens minted with it. `scopes` and `project_ids` are set when the token was
+/// narrowed for automation.
+#[derive(Debug, Clone)]
+pub struct CurrentUser {
+    pub id: Option<i64>,
+    pub name: String,
+    pub scopes: Option<Vec<Scope>>,
+    pub project_ids: Option<Vec<i64>>,
 }
 
-pub async fn api_key_auth(
-    req: Request,
-    next: Next,
-) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
-    let api_key = std::env::var("KANBAN_API_KEY").ok();
-
-    if api_key.is_none() {
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            Json(ErrorResponse {
-                error: "KANBAN_API_KEY environment variable not set".to_string(),
-            }),
-        ));
+impl CurrentUser {
+    /// Admin across the whole instance: the admin key, or a token minted with it that
+    /// isn't limited to particular projects.
+    pub fn is_admin(&self) -> bool {
+        self.id.is_none() && self.project_ids.is_none()
     }
 
-    let expected_key = api_key.unwrap();
+    pub fn is_restricted(&self) -> bool {
+        self.scopes.is_some() || self.project_ids.is_some()
+    }
+
+    pub fn may_access_project(&self, project_id: i64) -> bool {
+        self.project_ids.as_ref().is_none_or(|ids| ids.contains(&project_id))
+    }
+
+    /// The project limit as a JSON array, for `json_each` filters in listing queries.
+    pub fn project_filter(&self) -> Option<String> {
+        self.project_ids
+            .as_ref()
+            .map(|ids| serde_json::to_string(ids).unwrap_or_else(|_| "[]".to_string()))
+    }
+
+    /// Admins may act for anyone; everyone else only for themselves.
+    pub fn ensure_self_or_admin(&self, user_id: i64) -> Result<(), ApiError> {
+        if self.is_admin() || self.id == Some(user_id) {
+            Ok(())
+        } else {
+            Err(ApiError::forbidden("forbidden", "You can only manage your own tokens"))
+        }
+    }
+
+    pub fn ensure_admin(&self) -> Result<(), ApiError> {
+        if self.is_admin() {
+            Ok(())
+        } else {
+            Err(ApiError::forbidden("admin_required", "This action requires the admin key"))
+        }
+    }
+}
+
+/// A new random personal token, returned to the caller once and never stored.
+pub(crate) fn generate_token() -> String {
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", TOKEN_PREFIX, hex)
+}
+
+pub(crate) fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+struct TokenOwner {
+    token_id: i64,
+    user_id: Option<i64>,
+    user_name: Option<String>,
+    token_name: String,
+    scopes: Option<String>,
+    project_ids: Option<String>,
+    expired: bool,
+}
+
+pub async fn api_key_auth(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let admin_key = std::env::var("KANBAN_API_KEY").ok();
     let provided_key = req
         .headers()
         .get("X-API-Key")
         .and_then(|h| h.to_str().ok())
         .map(|s| s.to_string());
 
-    if provided_key.is_none() || provided_key.as_ref().unwrap() != &expected_key {
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            Json(ErrorResponse {
-                error: "Invalid or missing API key".to_string(),
-            }),
+    let Some(provided_key) = provided_key else {
+        return Err(unauthenticated(&state, admin_key.is_some()).await);
+    };
+
+    let (user, actor) = if admin_key.as_deref() == Some(provided_key.as_str()) {
+        let actor = req
+            .headers()
+            .get("X-Actor")
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .unwrap_or("api-key")
+            .to_string();
+        let user = CurrentUser {
+            id: None,
+            name: "admin".to_string(),
+            scopes: None,
+            project_ids: None,
+        };
+        (user, actor)
+    } else {
+        let token_hash = hash_token(&provided_key);
+        let owner = sqlx::query_as!(
+            TokenOwner,
+            r#"SELECT t.id as "token_id!", t.user_id, u.name as "user_name?", t.name as token_name,
+                      t.scopes, t.project_ids,
+                      (t.expires_at IS NOT NULL AND t.expires_at <= datetime('now')) as "expired!: bool"
+               FROM api_tokens t
+               LEFT JOIN users u ON u.id = t.user_id
+               WHERE t.token_hash = ? AND t.revoked_at IS NULL"#,
+            token_hash
+        )
+        .fetch_optional(&state.db)
+        .await?;
+
+        let Some(owner) = owner else {
+            return Err(unauthenticated(&state, admin_key.is_some()).await);
+        };
+        if owner.expired {
+            return Err(ApiError::unauthorized("token_expired", "API token has expired"));
+        }
+
+        let scopes: Option<Vec<Scope>> = owner
+            .scopes
+            .as_deref()
+            .map(|scopes| scopes.split_whitespace().filter_map(Scope::parse).collect());
+        if let Some(scopes) = &scopes {
+            check_scope(scopes, req.method(), req.uri().path())?;
+        }
+        let project_ids = owner
+            .project_ids
+            .as_deref()
+            .map(serde_json::from_str::<Vec<i64>>)
+            .transpose()
+            .map_err(|e| ApiError::internal(format!("Malformed token project list: {}", e)))?;
+
+        // Coarse on purpose: one write a minute per token rather than one per request.
+        sqlx::query!(
+            r#"UPDATE api_tokens SET last_used_at = datetime('now')
+               WHERE id = ? AND (last_used_at IS NULL OR last_used_at < datetime('now', '-1 minute'))"#,
+            owner.token_id
+        )
+        .execute(&state.db)
+        .await?;
+
+        let name = match owner.user_name {
+            Some(user_name) => user_name,
+            None => format!("token:{}", owner.token_name),
+        };
+        let user = CurrentUser {
+            id: owner.user_id,
+            name: name.clone(),
+            scopes,
+            project_ids,
+        };
+        (user, name)
+    };
+
+    req.extensions_mut().insert(user);
+    req.extensions_mut().insert(Actor(actor));
+
+    Ok(next.run(req).await)
+}
+
+fn check_scope(scopes: &[Scope], method: &Method, path: &str) -> Result<(), ApiError> {
+    let Some(needed) = Scope::required_for(method, path) else {
+        return Ok(());
+    };
+
+    if !scopes.iter().any(|scope| scope.grants(needed)) {
+        return Err(ApiError::forbidden(
+            "insufficient_scope",
+            format!("This token lacks the {} scope", needed.as_str()),
         ));
     }
 
-    Ok(next.run(req).await)
+    Ok(())
+}
+
+/// With no admin key and no personal tokens there is no way in at all, which is a
+/// setup problem rather than a bad credential.
+async fn unauthenticated(state: &AppState, admin_key_set: bool) -> ApiError {
+    let has_tokens = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM api_tokens WHERE revoked_at IS NULL) as "exists!: bool""#
+    )
+    .fetch_one(&state.db)
+    .await
+    .unwrap_or(true);
+
+    if !admin_key_set && !has_tokens {
+        return ApiError::unauthorized(
+            "api_key_not_configured",
+            "KANBAN_API_KEY environment variable not set",
+        );
+    }
+
+    ApiError::unauthorized("invalid_api_key", "Invalid or missing API key")
 }
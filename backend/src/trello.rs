@@ -0,0 +1,340 @@
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::handlers::columns::guess_kind;
+use crate::handlers::export::{
+    BoardExport, ExportColumn, ExportProject, ExportSubtask, ExportTag, ExportTask, ExportTaskTag, EXPORT_FORMAT,
+    EXPORT_VERSION,
+};
+
+/// Bookkeeping fields that carry nothing worth keeping, so they aren't reported as unmapped.
+const BOARD_METADATA: &[&str] = &[
+    "id", "idOrganization", "idEnterprise", "idMemberCreator", "idBoardSource", "idTags", "closed", "dateClosed",
+    "dateLastActivity", "dateLastView", "datePluginDisable", "url", "shortUrl", "shortLink", "prefs", "labelNames",
+    "limits", "memberships", "pinned", "starred", "subscribed", "powerUps", "premiumFeatures", "enterpriseOwned",
+    "templateGallery", "creationMethod", "ixUpdate", "nodeId", "descData", "isTemplate", "switcherViews",
+];
+const LIST_METADATA: &[&str] = &[
+    "idBoard", "subscribed", "softLimit", "limits", "creationMethod", "idOrganization", "nodeId", "status", "type",
+    "datasource", "color",
+];
+const CARD_METADATA: &[&str] = &[
+    "idBoard", "idShort", "idChecklists", "labels", "url", "shortUrl", "shortLink", "badges", "subscribed",
+    "manualCoverAttachment", "idAttachmentCover", "cover", "descData", "limits", "nodeId", "pinned", "isTemplate",
+    "cardRole", "email", "creationMethod", "dateViewedByCreator", "idMembersVoted", "checkItemStates",
+    "dueReminder", "mirrorSourceId", "mirrorSourceNodeId",
+];
+const LABEL_METADATA: &[&str] = &["idBoard", "uses", "nodeId", "idOrganization"];
+const CHECKLIST_METADATA: &[&str] = &["id", "idBoard", "limits", "creationMethod", "nodeId"];
+const CHECK_ITEM_METADATA: &[&str] = &["id", "idChecklist", "nameData", "creationMethod", "limits", "nodeId"];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloBoard {
+    name: String,
+    #[serde(default)]
+    lists: Vec<TrelloList>,
+    #[serde(default)]
+    cards: Vec<TrelloCard>,
+    #[serde(default)]
+    labels: Vec<TrelloLabel>,
+    #[serde(default)]
+    checklists: Vec<TrelloChecklist>,
+    #[serde(flatten)]
+    rest: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrelloList {
+    id: String,
+    name: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    pos: f64,
+    #[serde(flatten)]
+    rest: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloCard {
+    id: String,
+    name: String,
+    #[serde(default)]
+    desc: String,
+    #[serde(default)]
+    closed: bool,
+    id_list: String,
+    #[serde(default)]
+    pos: f64,
+    due: Option<String>,
+    start: Option<String>,
+    date_last_activity: Option<String>,
+    #[serde(default)]
+    id_labels: Vec<String>,
+    #[serde(flatten)]
+    rest: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrelloLabel {
+    id: String,
+    #[serde(default)]
+    name: String,
+    color: Option<String>,
+    #[serde(flatten)]
+    rest: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloChecklist {
+    id_card: String,
+    name: String,
+    #[serde(default)]
+    pos: f64,
+    #[serde(default)]
+    check_items: Vec<TrelloCheckItem>,
+    #[serde(flatten)]
+    rest: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrelloCheckItem {
+    name: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    pos: f64,
+    #[serde(flatten)]
+    rest: Map<String, Value>,
+}
+
+/// A Trello field that has data but no place on our boards, and how many records had it.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct UnmappedField {
+    pub field: String,
+    pub count: usize,
+}
+
+/// Counts the fields of Trello records that hold data and aren't mapped or bookkeeping.
+#[derive(Default)]
+struct Unmapped(BTreeMap<String, usize>);
+
+impl Unmapped {
+    fn note(&mut self, prefix: &str, rest: &Map<String, Value>, metadata: &[&str]) {
+        for (key, value) in rest {
+            if metadata.contains(&key.as_str()) || is_empty(value) {
+                continue;
+            }
+            let field = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            *self.0.entry(field).or_default() += 1;
+        }
+    }
+
+    fn into_fields(self) -> Vec<UnmappedField> {
+        self.0
+            .into_iter()
+            .map(|(field, count)| UnmappedField { field, count })
+            .collect()
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::String(s) => s.is_empty(),
+        Value::Array(a) => a.is_empty(),
+        Value::Object(o) => o.is_empty(),
+        _ => false,
+    }
+}
+
+/// Trello's named label colours, with `_dark` and `_light` shades folded into the base.
+fn label_color(color: Option<&str>) -> &'static str {
+    let base = color.map(|c| c.trim_end_matches("_dark").trim_end_matches("_light"));
+    match base {
+        Some("green") => "#61bd4f",
+        Some("yellow") => "#f2d600",
+        Some("orange") => "#ff9f1a",
+        Some("red") => "#eb5a46",
+        Some("purple") => "#c377e0",
+        Some("blue") => "#0079bf",
+        Some("sky") => "#00c2e0",
+        Some("lime") => "#51e898",
+        Some("pink") => "#ff78cb",
+        Some("black") => "#344563",
+        _ => "#b3bac5",
+    }
+}
+
+/// Trello ids start with the creation time in seconds, as eight hex digits.
+fn created_at(trello_id: &str) -> Option<String> {
+    let seconds = i64::from_str_radix(trello_id.get(..8)?, 16).ok()?;
+    DateTime::from_timestamp(seconds, 0).map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+/// Turns a Trello board export (Menu → Print, export and share → Export as JSON) into a
+/// board document for [`crate::handlers::export::import_board`], plus the Trello fields
+/// that had nowhere to go.
+///
+/// Open lists become columns in order; archived cards and the cards of archived lists go
+/// to an extra `archived` column. Checklist items become subtasks, prefixed with the
+/// checklist's name when a card has several.
+pub fn convert(board: Value) -> Result<(BoardExport, Vec<UnmappedField>), serde_json::Error> {
+    let mut board: TrelloBoard = serde_json::from_value(board)?;
+    let mut unmapped = Unmapped::default();
+    unmapped.note("", &board.rest, BOARD_METADATA);
+
+    board.lists.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+    let mut columns: Vec<ExportColumn> = Vec::new();
+    let mut column_of_list = HashMap::new();
+    for list in board.lists.iter().filter(|l| !l.closed) {
+        unmapped.note("lists", &list.rest, LIST_METADATA);
+        let id = columns.len() as i64 + 1;
+        column_of_list.insert(list.id.as_str(), id);
+        columns.push(ExportColumn {
+            id,
+            name: list.name.clone(),
+            sort_order: id * 1000,
+            wip_limit: None,
+            kind: guess_kind(&list.name).to_string(),
+        });
+    }
+    // As for boards from before column kinds, the last column is done if none is.
+    if !columns.iter().any(|c| c.kind == "done") {
+        if let Some(last) = columns.last_mut() {
+            last.kind = "done".to_string();
+        }
+    }
+
+    let archived_id = columns.len() as i64 + 1;
+    let has_archived = board
+        .cards
+        .iter()
+        .any(|c| c.closed || !column_of_list.contains_key(c.id_list.as_str()));
+    if has_archived {
+        columns.push(ExportColumn {
+            id: archived_id,
+            name: "Archived".to_string(),
+            sort_order: archived_id * 1000,
+            wip_limit: None,
+            kind: "archived".to_string(),
+        });
+    }
+
+    let mut tags = Vec::new();
+    let mut tag_of_label = HashMap::new();
+    for label in &board.labels {
+        unmapped.note("labels", &label.rest, LABEL_METADATA);
+        let id = tags.len() as i64 + 1;
+        tag_of_label.insert(label.id.as_str(), id);
+        let name = match (label.name.trim(), label.color.as_deref()) {
+            ("", Some(color)) => color.to_string(),
+            ("", None) => "unnamed".to_string(),
+            (name, _) => name.to_string(),
+        };
+        tags.push(ExportTag {
+            id,
+            name,
+            color: label_color(label.color.as_deref()).to_string(),
+        });
+    }
+
+    board.cards.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+    let mut tasks: Vec<ExportTask> = Vec::new();
+    let mut task_tags = Vec::new();
+    let mut task_of_card = HashMap::new();
+    let mut cards_in_column: HashMap<i64, i64> = HashMap::new();
+    for card in &board.cards {
+        unmapped.note("cards", &card.rest, CARD_METADATA);
+        let column_id = match column_of_list.get(card.id_list.as_str()) {
+            Some(&id) if !card.closed => id,
+            _ => archived_id,
+        };
+        let position = cards_in_column.entry(column_id).or_default();
+        *position += 1;
+
+        let id = tasks.len() as i64 + 1;
+        task_of_card.insert(card.id.as_str(), id);
+        tasks.push(ExportTask {
+            id,
+            column_id,
+            parent_id: None,
+            title: card.name.clone(),
+            description: Some(card.desc.clone()).filter(|d| !d.trim().is_empty()),
+            priority: None,
+            sort_order: *position * 1000,
+            source_tag: Some("trello".to_string()),
+            assignee: None,
+            due_at: card.due.clone(),
+            start_at: card.start.clone(),
+            created_at: created_at(&card.id),
+            completed_at: card.date_last_activity.clone(),
+        });
+        task_tags.extend(
+            card.id_labels
+                .iter()
+                .filter_map(|label| tag_of_label.get(label.as_str()))
+                .map(|&tag_id| ExportTaskTag { task_id: id, tag_id }),
+        );
+    }
+
+    let task_order = |checklist: &TrelloChecklist| task_of_card.get(checklist.id_card.as_str()).copied();
+    board
+        .checklists
+        .sort_by(|a, b| task_order(a).cmp(&task_order(b)).then(a.pos.total_cmp(&b.pos)));
+    let mut checklists_per_card: HashMap<&str, usize> = HashMap::new();
+    for checklist in &board.checklists {
+        *checklists_per_card.entry(checklist.id_card.as_str()).or_default() += 1;
+    }
+    let mut subtasks: Vec<ExportSubtask> = Vec::new();
+    let mut items_per_task: HashMap<i64, i64> = HashMap::new();
+    for checklist in &board.checklists {
+        unmapped.note("checklists", &checklist.rest, CHECKLIST_METADATA);
+        let Some(&task_id) = task_of_card.get(checklist.id_card.as_str()) else {
+            continue;
+        };
+        let prefix = checklists_per_card[checklist.id_card.as_str()] > 1;
+        let mut items: Vec<&TrelloCheckItem> = checklist.check_items.iter().collect();
+        items.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        for item in items {
+            unmapped.note("checklists.checkItems", &item.rest, CHECK_ITEM_METADATA);
+            let position = items_per_task.entry(task_id).or_default();
+            *position += 1;
+            subtasks.push(ExportSubtask {
+                id: subtasks.len() as i64 + 1,
+                task_id,
+                title: if prefix {
+                    format!("{}: {}", checklist.name, item.name)
+                } else {
+                    item.name.clone()
+                },
+                done: item.state == "complete",
+                sort_order: *position * 1000,
+            });
+        }
+    }
+
+    let document = BoardExport {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        exported_at: None,
+        project: ExportProject {
+            name: board.name,
+            wip_mode: "soft".to_string(),
+            blocker_mode: "soft".to_string(),
+        },
+        columns,
+        tasks,
+        subtasks,
+        tags,
+        task_tags,
+        linked_paths: Vec::new(),
+    };
+
+    Ok((document, unmapped.into_fields()))
+}
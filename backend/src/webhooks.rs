@@ -0,0 +1,263 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use serde::Serialize;
+use sqlx::{SqliteConnection, SqlitePool};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast::error::RecvError, Notify};
+
+use crate::events::EventBus;
+
+/// `sha256=<hex>` HMAC of the request body, keyed with the webhook's secret.
+pub const SIGNATURE_HEADER: &str = "X-Kanban-Signature-256";
+pub const EVENT_HEADER: &str = "X-Kanban-Event";
+/// The delivery id, the same across retries so receivers can drop duplicates.
+pub const DELIVERY_HEADER: &str = "X-Kanban-Delivery";
+
+/// Every event a webhook can subscribe to.
+pub const EVENT_KINDS: &[&str] = &[
+    "project.created",
+    "project.updated",
+    "project.deleted",
+    "column.created",
+    "column.updated",
+    "column.deleted",
+    "columns.reordered",
+    "task.created",
+    "task.updated",
+    "task.completed",
+    "task.deleted",
+    "subtask.created",
+    "subtask.updated",
+    "subtask.deleted",
+    "comment.created",
+    "comment.updated",
+    "comment.deleted",
+    "attachment.created",
+    "attachment.deleted",
+    "dependency.created",
+    "dependency.deleted",
+];
+
+/// How the delivery worker paces itself.
+#[derive(Debug, Clone)]
+pub struct DeliverySettings {
+    /// Wait before the first retry; doubles after each failed attempt up to `retry_max`.
+    pub retry_base: Duration,
+    pub retry_max: Duration,
+    /// Attempts before a delivery is given up on and marked `failed`.
+    pub max_attempts: i64,
+    /// How often the outbox is checked for due retries when nothing new comes in.
+    pub poll_interval: Duration,
+    pub request_timeout: Duration,
+}
+
+impl Default for DeliverySettings {
+    fn default() -> Self {
+        Self {
+            retry_base: Duration::from_secs(30),
+            retry_max: Duration::from_secs(60 * 60),
+            max_attempts: 8,
+            poll_interval: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl DeliverySettings {
+    fn backoff(&self, attempts: i64) -> Duration {
+        let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+        self.retry_base.saturating_mul(2u32.pow(exponent)).min(self.retry_max)
+    }
+}
+
+/// Whether a space-separated filter such as `task.completed column.*` selects `kind`.
+pub fn event_matches(filter: &str, kind: &str) -> bool {
+    filter.split_whitespace().any(|pattern| {
+        pattern == "*"
+            || pattern == kind
+            || pattern
+                .strip_suffix(".*")
+                .is_some_and(|prefix| kind.split_once('.').is_some_and(|(family, _)| family == prefix))
+    })
+}
+
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    let hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Same shape as SQLite's `strftime('%Y-%m-%d %H:%M:%f')`, so the two compare as text.
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+}
+
+/// Queues a delivery of the event for every active webhook of its project that wants it.
+/// Called inside the transaction that makes the change, so a committed change always has
+/// its deliveries queued. Returns how many were queued.
+pub async fn record_event<T: Serialize>(
+    conn: &mut SqliteConnection,
+    project_id: i64,
+    kind: &str,
+    data: &T,
+) -> Result<usize, sqlx::Error> {
+    let hooks = sqlx::query!(
+        r#"SELECT id as "id!", url, secret, events FROM webhooks WHERE project_id = ? AND active = 1"#,
+        project_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let hooks: Vec<_> = hooks.into_iter().filter(|hook| event_matches(&hook.events, kind)).collect();
+    if hooks.is_empty() {
+        return Ok(0);
+    }
+
+    let payload = serde_json::json!({
+        "event": kind,
+        "project_id": project_id,
+        "occurred_at": Utc::now().to_rfc3339(),
+        "data": data,
+    })
+    .to_string();
+
+    for hook in &hooks {
+        sqlx::query!(
+            r#"INSERT INTO webhook_deliveries (webhook_id, url, secret, event, payload) VALUES (?, ?, ?, ?, ?)"#,
+            hook.id,
+            hook.url,
+            hook.secret,
+            kind,
+            payload
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(hooks.len())
+}
+
+/// Sends every pending delivery whose time has come and records the outcome. Returns
+/// how many were attempted.
+pub async fn deliver_due(
+    db: &SqlitePool,
+    client: &reqwest::Client,
+    settings: &DeliverySettings,
+) -> Result<usize, sqlx::Error> {
+    let now = timestamp(Utc::now());
+    let due = sqlx::query!(
+        r#"SELECT d.id as "id!", d.event, d.payload, d.attempts,
+                  COALESCE(w.url, d.url) as "url!: String", COALESCE(w.secret, d.secret) as "secret!: String"
+           FROM webhook_deliveries d
+           LEFT JOIN webhooks w ON w.id = d.webhook_id
+           WHERE d.status = 'pending' AND d.next_attempt_at <= ? AND (w.active = 1 OR d.webhook_id IS NULL)
+           ORDER BY d.id
+           LIMIT 50"#,
+        now
+    )
+    .fetch_all(db)
+    .await?;
+
+    for delivery in &due {
+        let attempts = delivery.attempts + 1;
+        let outcome = client
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &delivery.event)
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .header(SIGNATURE_HEADER, sign(&delivery.secret, delivery.payload.as_bytes()))
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+
+        let (response_status, error) = match outcome {
+            Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+            Ok(response) => (
+                Some(response.status().as_u16()),
+                Some(format!("Receiver answered {}", response.status())),
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let response_status = response_status.map(i64::from);
+
+        match error {
+            None => {
+                sqlx::query!(
+                    r#"UPDATE webhook_deliveries
+                       SET status = 'delivered', attempts = ?, response_status = ?, last_error = NULL,
+                           delivered_at = datetime('now')
+                       WHERE id = ?"#,
+                    attempts,
+                    response_status,
+                    delivery.id
+                )
+                .execute(db)
+                .await?;
+            }
+            Some(error) => {
+                let status = if attempts >= settings.max_attempts { "failed" } else { "pending" };
+                let next_attempt_at = timestamp(Utc::now() + settings.backoff(attempts));
+                sqlx::query!(
+                    r#"UPDATE webhook_deliveries
+                       SET status = ?, attempts = ?, response_status = ?, last_error = ?, next_attempt_at = ?
+                       WHERE id = ?"#,
+                    status,
+                    attempts,
+                    response_status,
+                    error,
+                    next_attempt_at,
+                    delivery.id
+                )
+                .execute(db)
+                .await?;
+            }
+        }
+    }
+
+    // Deliveries whose webhook is gone have no log left to show up in once they finish.
+    sqlx::query!(r#"DELETE FROM webhook_deliveries WHERE webhook_id IS NULL AND status != 'pending'"#)
+        .execute(db)
+        .await?;
+
+    Ok(due.len())
+}
+
+/// Starts the webhook worker, which sends what is due in the outbox. Board events only
+/// wake it early: the deliveries themselves are written with each change, so none are
+/// lost if the worker falls behind, and ones still pending at shutdown are sent after a
+/// restart.
+pub fn spawn(db: SqlitePool, events: &EventBus, settings: DeliverySettings) {
+    let wake = Arc::new(Notify::new());
+    let mut receiver = events.subscribe();
+
+    tokio::spawn({
+        let wake = wake.clone();
+        async move {
+            while !matches!(receiver.recv().await, Err(RecvError::Closed)) {
+                wake.notify_one();
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        let client = match reqwest::Client::builder().timeout(settings.request_timeout).build() {
+            Ok(client) => client,
+            Err(e) => {
+                tracing::error!("Webhook deliveries disabled; failed to build HTTP client: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            if let Err(e) = deliver_due(&db, &client, &settings).await {
+                tracing::warn!("Webhook delivery pass failed: {}", e);
+            }
+            tokio::select! {
+                _ = wake.notified() => {}
+                _ = tokio::time::sleep(settings.poll_interval) => {}
+            }
+        }
+    });
+}
@@ -5,13 +5,18 @@ use axum::{
 };
 use serde_json::{json, Value};
 use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+use tempfile::TempDir;
 use tower::util::ServiceExt;
 
+use kanban_board::attachments::BlobStore;
+use kanban_board::events::EventBus;
 use kanban_board::handlers::projects::{AppState, create_project, list_projects, get_project, update_project, delete_project};
+use kanban_board::handlers::{activity, attachments, calendar, columns, comments, dependencies, events, export, linked_paths, members, metrics, schedule, search, subtasks, tags, task_csv, tasks, users, webhooks};
 use kanban_board::middleware::api_key_auth;
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post, put, delete},
-    middleware::from_fn,
+    middleware::from_fn_with_state,
 };
 
 async fn setup_test_db() -> SqlitePool {
@@ -21,17 +26,29 @@ async fn setup_test_db() -> SqlitePool {
         .await
         .expect("Failed to create in-memory database");
 
-    let schema = include_str!("../schema.sql");
-    
-    for statement in schema.split(';').map(|s| s.trim()).filter(|s| !s.is_empty() && !s.starts_with("PRAGMA")) {
-        sqlx::raw_sql(statement).execute(&pool).await.expect("Failed to execute schema");
-    }
+    kanban_board::db::run_migrations(&pool)
+        .await
+        .expect("Failed to run migrations");
 
     pool
 }
 
+/// A blob store in a fresh temporary directory, with a 1 MB upload limit. The directory
+/// is removed when the returned `TempDir` is dropped, so keep it for the whole test.
+fn test_blob_store() -> (TempDir, BlobStore) {
+    let dir = TempDir::new().expect("Failed to create a temporary directory");
+    let blobs = BlobStore::new(dir.path(), 1024 * 1024);
+    (dir, blobs)
+}
+
 fn create_test_router(pool: SqlitePool) -> Router {
-    let state = AppState { db: pool };
+    let (blob_dir, blobs) = test_blob_store();
+    // The router holds on to the directory, so it lives exactly as long as the test's router.
+    create_test_router_with(pool, EventBus::new(), blobs).layer(axum::Extension(std::sync::Arc::new(blob_dir)))
+}
+
+fn create_test_router_with(pool: SqlitePool, events: EventBus, blobs: BlobStore) -> Router {
+    let state = AppState { db: pool, events, blobs };
 
     let protected_routes = Router::new()
         .route("/api/projects", post(create_project))
@@ -39,11 +56,71 @@ fn create_test_router(pool: SqlitePool) -> Router {
         .route("/api/projects/{id}", get(get_project))
         .route("/api/projects/{id}", put(update_project))
         .route("/api/projects/{id}", delete(delete_project))
+        .route("/api/projects/import", post(export::import_project))
+        .route("/api/projects/import/trello", post(export::import_trello).layer(DefaultBodyLimit::disable()))
+        .route("/api/projects/{id}/export", get(export::export_project))
+        .route("/api/projects/{id}/events", get(events::project_events))
+        .route("/api/projects/{id}/activity", get(activity::list_project_activity))
+        .route("/api/projects/{id}/metrics", get(metrics::get_project_metrics))
+        .route("/api/projects/{id}/cfd", get(metrics::get_cumulative_flow))
+        .route("/api/projects/{id}/cfd.csv", get(metrics::get_cumulative_flow_csv))
+        .route("/api/projects/{id}/burndown", get(metrics::get_burndown))
+        .route("/api/projects/{id}/burndown.csv", get(metrics::get_burndown_csv))
+        .route("/api/projects/{id}/members", get(members::list_members))
+        .route("/api/projects/{id}/members/{user_id}", put(members::set_member))
+        .route("/api/projects/{id}/members/{user_id}", delete(members::remove_member))
+        .route("/api/projects/{id}/calendar-feed", get(calendar::get_project_feed))
+        .route("/api/projects/{id}/calendar-feed", post(calendar::rotate_project_feed))
+        .route("/api/calendar-feed", post(calendar::rotate_global_feed))
+        .route("/api/projects/{id}/webhooks", get(webhooks::list_webhooks))
+        .route("/api/projects/{id}/webhooks", post(webhooks::create_webhook))
+        .route("/api/webhooks/{id}", put(webhooks::update_webhook))
+        .route("/api/webhooks/{id}/deliveries", get(webhooks::list_deliveries))
+        .route("/api/projects/{id}/columns", get(columns::list_columns))
+        .route("/api/projects/{id}/columns", post(columns::create_column))
+        .route("/api/columns/{id}", put(columns::update_column))
+        .route("/api/projects/{project_id}/tasks", get(tasks::list_tasks))
+        .route("/api/projects/{project_id}/tasks", post(tasks::create_task))
+        .route("/api/projects/{project_id}/tasks.csv", get(task_csv::export_tasks_csv))
+        .route("/api/projects/{project_id}/tasks/import", post(task_csv::import_tasks_csv))
+        .route("/api/tasks/overdue", get(schedule::list_overdue_tasks))
+        .route("/api/tasks/upcoming", get(schedule::list_upcoming_tasks))
+        .route("/api/tasks/{id}", put(tasks::update_task))
+        .route("/api/tasks/{id}", delete(tasks::delete_task))
+        .route("/api/tasks/{id}/history", get(activity::get_task_history))
+        .route("/api/tasks/bulk-update", put(tasks::bulk_update_tasks))
+        .route("/api/tasks/{id}/subtasks", post(subtasks::create_subtask))
+        .route("/api/subtasks/{id}", put(subtasks::update_subtask))
+        .route("/api/tasks/{id}", get(tasks::get_task))
+        .route("/api/tasks/{id}/comments", get(comments::list_comments))
+        .route("/api/tasks/{id}/comments", post(comments::create_comment))
+        .route("/api/tasks/{id}/dependencies", get(dependencies::list_dependencies))
+        .route("/api/tasks/{id}/dependencies", post(dependencies::create_dependency))
+        .route("/api/dependencies/{id}", delete(dependencies::delete_dependency))
+        .route("/api/tasks/{id}/attachments", get(attachments::list_attachments))
+        .route("/api/tasks/{id}/attachments", post(attachments::upload_attachment).layer(DefaultBodyLimit::disable()))
+        .route("/api/attachments/{id}", get(attachments::download_attachment))
+        .route("/api/attachments/{id}", delete(attachments::delete_attachment))
+        .route("/api/comments/{id}", put(comments::update_comment))
+        .route("/api/comments/{id}", delete(comments::delete_comment))
+        .route("/api/search", get(search::search_tasks))
+        .route("/api/tags", post(tags::create_tag))
+        .route("/api/tags/{id}", delete(tags::delete_tag))
+        .route("/api/projects/{project_id}/linked-paths", post(linked_paths::create_linked_path))
+        .route("/api/users", post(users::create_user))
+        .route("/api/users/me", get(users::get_me))
+        .route("/api/users/{id}/tokens", get(users::list_tokens))
+        .route("/api/users/{id}/tokens", post(users::create_token))
+        .route("/api/tokens", get(users::list_own_tokens))
+        .route("/api/tokens", post(users::create_own_token))
+        .route("/api/tokens/{id}", delete(users::revoke_token))
         .with_state(state.clone())
-        .layer(from_fn(api_key_auth));
+        .layer(from_fn_with_state(state.clone(), api_key_auth));
 
     Router::new()
         .route("/health", get(health_check))
+        .route("/api/projects/{id}/calendar.ics", get(calendar::project_calendar))
+        .route("/api/calendar.ics", get(calendar::global_calendar))
         .merge(protected_routes)
         .with_state(state)
 }
@@ -52,6 +129,91 @@ async fn health_check() -> axum::Json<Value> {
     axum::Json(json!({ "status": "ok" }))
 }
 
+async fn send_json(app: &Router, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    send_json_as(app, "test-secret-key", method, uri, body).await
+}
+
+async fn send_json_as(
+    app: &Router,
+    api_key: &str,
+    method: &str,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("X-API-Key", api_key)
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = match body {
+        Some(json) => Body::from(serde_json::to_string(&json).unwrap()),
+        None => Body::empty(),
+    };
+
+    let response = app.clone().oneshot(builder.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+
+    (status, json)
+}
+
+/// Uploads `contents` as a multipart `file` part, the way browsers and `rk attach` do.
+async fn upload_file(app: &Router, task_id: &Value, filename: &str, contents: &[u8]) -> (StatusCode, Value) {
+    let boundary = "kanban-test-boundary";
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: text/plain\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(contents);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    let request = Request::builder()
+        .method("POST")
+        .uri(format!("/api/tasks/{}/attachments", task_id))
+        .header("X-API-Key", "test-secret-key")
+        .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={boundary}"))
+        .body(Body::from(body))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+/// Creates a user with the admin key and returns their id and a personal token.
+async fn create_user_with_token(app: &Router, name: &str) -> (i64, String) {
+    let (_, user) = send_json(app, "POST", "/api/users", Some(json!({ "name": name }))).await;
+    let user_id = user["id"].as_i64().unwrap();
+    let (_, token) = send_json(app, "POST", &format!("/api/users/{}/tokens", user_id), Some(json!({ "name": "test" }))).await;
+
+    (user_id, token["token"].as_str().unwrap().to_string())
+}
+
+/// Gives `user_id` a role on the project, using the admin key.
+async fn add_member(app: &Router, project_id: i64, user_id: i64, role: &str) {
+    let uri = format!("/api/projects/{}/members/{}", project_id, user_id);
+    let (status, _) = send_json(app, "PUT", &uri, Some(json!({ "role": role }))).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+async fn create_project_with_columns(app: &Router, name: &str) -> (i64, Vec<i64>) {
+    let (_, project) = send_json(app, "POST", "/api/projects", Some(json!({ "name": name }))).await;
+    let project_id = project["id"].as_i64().unwrap();
+    let (_, details) = send_json(app, "GET", &format!("/api/projects/{}", project_id), None).await;
+    let column_ids = details["columns"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["id"].as_i64().unwrap())
+        .collect();
+
+    (project_id, column_ids)
+}
+
 #[tokio::test]
 async fn test_health_endpoint() {
     let pool = setup_test_db().await;
@@ -259,7 +421,7 @@ async fn test_get_project() {
         .oneshot(
             Request::builder()
                 .method("GET")
-                .uri(&format!("/api/projects/{}", project_id))
+                .uri(format!("/api/projects/{}", project_id))
                 .header("X-API-Key", "test-secret-key")
                 .body(Body::empty())
                 .unwrap(),
@@ -331,7 +493,7 @@ async fn test_update_project() {
         .oneshot(
             Request::builder()
                 .method("PUT")
-                .uri(&format!("/api/projects/{}", project_id))
+                .uri(format!("/api/projects/{}", project_id))
                 .header("X-API-Key", "test-secret-key")
                 .header(header::CONTENT_TYPE, "application/json")
                 .body(Body::from(serde_json::to_string(&request_body).unwrap()))
@@ -404,7 +566,7 @@ async fn test_delete_project() {
         .oneshot(
             Request::builder()
                 .method("DELETE")
-                .uri(&format!("/api/projects/{}", project_id))
+                .uri(format!("/api/projects/{}", project_id))
                 .header("X-API-Key", "test-secret-key")
                 .body(Body::empty())
                 .unwrap(),
@@ -489,3 +651,1710 @@ async fn test_create_project_with_default_columns() {
     assert_eq!(columns[3].name, "Testing");
     assert_eq!(columns[4].name, "Done");
 }
+
+#[tokio::test]
+async fn test_project_events_replay_after_last_event_id() {
+    use futures_util::StreamExt;
+
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool);
+
+    let (project_id, columns) = create_project_with_columns(&app, "Live Board").await;
+    let (status, task) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{}/tasks", project_id),
+        Some(json!({ "title": "Ship SSE", "column_id": columns[0] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send_json(&app, "DELETE", &format!("/api/tasks/{}", task["id"]), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // Event 1 is project.created; resuming after it should yield the task events only.
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/projects/{}/events", project_id))
+                .header("X-API-Key", "test-secret-key")
+                .header("Last-Event-ID", "1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/event-stream");
+
+    let mut stream = response.into_body().into_data_stream();
+    let mut received = String::new();
+    while !received.contains("event: task.deleted") {
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(2), stream.next())
+            .await
+            .expect("timed out waiting for events")
+            .unwrap()
+            .unwrap();
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+
+    assert!(!received.contains("project.created"));
+    assert!(received.contains("id: 2\nevent: task.created"));
+    assert!(received.contains("Ship SSE"));
+    assert!(received.contains("id: 3\nevent: task.deleted"));
+}
+
+#[tokio::test]
+async fn test_project_events_unknown_project() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool);
+
+    let (status, body) = send_json(&app, "GET", "/api/projects/999/events", None).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "Project not found");
+}
+
+#[tokio::test]
+async fn test_task_history_records_moves_with_actor() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool);
+
+    let (project_id, columns) = create_project_with_columns(&app, "Audited").await;
+    let (_, task) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{}/tasks", project_id),
+        Some(json!({ "title": "Trace me", "column_id": columns[0] })),
+    )
+    .await;
+    let task_id = task["id"].as_i64().unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/tasks/{}", task_id))
+                .header("X-API-Key", "test-secret-key")
+                .header("X-Actor", "rk@laptop")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(json!({ "column_id": columns[4] }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let (status, history) = send_json(&app, "GET", &format!("/api/tasks/{}/history", task_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let items = history["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["action"], "move");
+    assert_eq!(items[0]["actor"], "rk@laptop");
+    assert_eq!(items[0]["before"]["column_id"], columns[0]);
+    assert_eq!(items[0]["after"]["column_id"], columns[4]);
+    assert_eq!(items[1]["action"], "create");
+    assert_eq!(items[1]["actor"], "api-key");
+    assert!(items[1]["before"].is_null());
+    assert!(history["next_before"].is_null());
+
+    let (status, _) = send_json(&app, "DELETE", &format!("/api/tasks/{}", task_id), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, history) = send_json(&app, "GET", &format!("/api/tasks/{}/history", task_id), None).await;
+    assert_eq!(history["items"][0]["action"], "delete");
+    assert_eq!(history["items"][0]["before"]["title"], "Trace me");
+}
+
+#[tokio::test]
+async fn test_project_activity_pagination() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool);
+
+    let (project_id, _) = create_project_with_columns(&app, "Paged").await;
+    send_json(&app, "PUT", &format!("/api/projects/{}", project_id), Some(json!({ "name": "Paged v2" }))).await;
+    send_json(&app, "PUT", &format!("/api/projects/{}", project_id), Some(json!({ "name": "Paged v3" }))).await;
+
+    let (status, page) = send_json(&app, "GET", &format!("/api/projects/{}/activity?limit=2", project_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let items = page["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["after"]["name"], "Paged v3");
+    assert_eq!(items[1]["before"]["name"], "Paged");
+
+    let next_before = page["next_before"].as_i64().unwrap();
+    let (_, page) = send_json(
+        &app,
+        "GET",
+        &format!("/api/projects/{}/activity?limit=2&before={}", project_id, next_before),
+        None,
+    )
+    .await;
+    let items = page["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["action"], "create");
+    assert_eq!(items[0]["entity_type"], "project");
+    assert!(page["next_before"].is_null());
+}
+
+#[tokio::test]
+async fn test_search_ranks_and_scopes_results() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool);
+
+    let (alpha, alpha_columns) = create_project_with_columns(&app, "Alpha").await;
+    let (beta, beta_columns) = create_project_with_columns(&app, "Beta").await;
+
+    let add_task = |project_id: i64, column_id: i64, title: &'static str, description: &'static str| {
+        let app = app.clone();
+        async move {
+            let (_, task) = send_json(
+                &app,
+                "POST",
+                &format!("/api/projects/{}/tasks", project_id),
+                Some(json!({ "title": title, "column_id": column_id, "description": description })),
+            )
+            .await;
+            task["id"].as_i64().unwrap()
+        }
+    };
+
+    let in_title = add_task(alpha, alpha_columns[0], "Login page crashes", "Seen on Safari").await;
+    let in_description = add_task(beta, beta_columns[0], "Session handling", "Crashes after login timeout").await;
+    let in_subtask = add_task(alpha, alpha_columns[1], "Release checklist", "").await;
+    send_json(
+        &app,
+        "POST",
+        &format!("/api/tasks/{}/subtasks", in_subtask),
+        Some(json!({ "title": "Verify login redirect" })),
+    )
+    .await;
+
+    let (status, results) = send_json(&app, "GET", "/api/search?q=login", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let ids: Vec<i64> = results.as_array().unwrap().iter().map(|r| r["task_id"].as_i64().unwrap()).collect();
+    assert_eq!(ids, vec![in_title, in_description, in_subtask]);
+    assert_eq!(results[0]["title_highlight"], "<mark>Login</mark> page crashes");
+    assert_eq!(results[0]["project_name"], "Alpha");
+    assert_eq!(results[0]["column_name"], "Backlog");
+    assert!(results[1]["snippet"].as_str().unwrap().contains("<mark>login</mark>"));
+
+    let (_, scoped) = send_json(&app, "GET", &format!("/api/search?q=log&project_id={}", beta), None).await;
+    let scoped = scoped.as_array().unwrap();
+    assert_eq!(scoped.len(), 1);
+    assert_eq!(scoped[0]["task_id"], in_description);
+
+    send_json(&app, "PUT", &format!("/api/tasks/{}", in_title), Some(json!({ "title": "Signup page crashes" }))).await;
+    let (_, results) = send_json(&app, "GET", "/api/search?q=signup", None).await;
+    assert_eq!(results[0]["task_id"], in_title);
+
+    // Quotes and operators in user input are treated as plain text, not FTS syntax.
+    let (status, results) = send_json(&app, "GET", "/api/search?q=%22crash-%22(", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(results.as_array().unwrap().len(), 2);
+
+    let (status, _) = send_json(&app, "GET", "/api/search?q=%20%20", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_list_tasks_filters_and_cursor_pagination() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool);
+
+    let (project_id, columns) = create_project_with_columns(&app, "Filtered").await;
+    let (_, bug) = send_json(&app, "POST", "/api/tags", Some(json!({ "name": "bug", "color": "#f00" }))).await;
+
+    let specs = [
+        ("Crash on save", 2, "urgent", true),
+        ("Slow search", 2, "high", true),
+        ("Dark mode", 0, "low", false),
+        ("Export to CSV", 2, "medium", false),
+        ("Broken link", 2, "high", true),
+    ];
+    for (title, column, priority, is_bug) in specs {
+        let (_, task) = send_json(
+            &app,
+            "POST",
+            &format!("/api/projects/{}/tasks", project_id),
+            Some(json!({ "title": title, "column_id": columns[column], "priority": priority })),
+        )
+        .await;
+        if is_bug {
+            send_json(&app, "PUT", &format!("/api/tasks/{}", task["id"]), Some(json!({ "tag_ids": [bug["id"]] }))).await;
+        }
+    }
+
+    let titles = |page: &Value| -> Vec<String> {
+        page["tasks"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect()
+    };
+
+    let (status, page) = send_json(
+        &app,
+        "GET",
+        &format!("/api/projects/{}/tasks?priority=high,urgent&tag_ids={}&column_id={}", project_id, bug["id"], columns[2]),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&page), vec!["Crash on save", "Slow search", "Broken link"]);
+    assert!(page["next_cursor"].is_null());
+
+    let (_, page) = send_json(&app, "GET", &format!("/api/projects/{}/tasks?q=search", project_id), None).await;
+    assert_eq!(titles(&page), vec!["Slow search"]);
+
+    let mut seen = Vec::new();
+    let mut uri = format!("/api/projects/{}/tasks?order_by=priority&limit=2", project_id);
+    loop {
+        let (status, page) = send_json(&app, "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        seen.extend(titles(&page));
+        match page["next_cursor"].as_str() {
+            Some(cursor) => uri = format!("/api/projects/{}/tasks?order_by=priority&limit=2&cursor={}", project_id, cursor),
+            None => break,
+        }
+    }
+    assert_eq!(seen, vec!["Crash on save", "Slow search", "Broken link", "Export to CSV", "Dark mode"]);
+
+    let (_, page) = send_json(&app, "GET", &format!("/api/projects/{}/tasks?order_by=-title&limit=3", project_id), None).await;
+    assert_eq!(titles(&page), vec!["Slow search", "Export to CSV", "Dark mode"]);
+
+    let (status, _) = send_json(&app, "GET", &format!("/api/projects/{}/tasks?order_by=color", project_id), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send_json(&app, "GET", &format!("/api/projects/{}/tasks?created_after=yesterday", project_id), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (_, page) = send_json(&app, "GET", &format!("/api/projects/{}/tasks?created_after=2000-01-01", project_id), None).await;
+    assert_eq!(page["tasks"].as_array().unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn test_errors_carry_stable_codes() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Errors").await;
+    let (_, other_columns) = create_project_with_columns(&app, "Elsewhere").await;
+
+    let (status, body) = send_json(&app, "POST", "/api/projects", Some(json!({ "name": "Errors" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "project_name_taken");
+    assert_eq!(body["details"][0]["field"], "name");
+
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        &tasks_uri,
+        Some(json!({ "title": "Bad priority", "column_id": columns[0], "priority": "someday" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(body["details"][0]["field"], "priority");
+
+    let (status, body) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "  ", "column_id": columns[0] }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "title");
+
+    let (_, task) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Stay home", "column_id": columns[0] }))).await;
+    let task_uri = format!("/api/tasks/{}", task["id"]);
+    let (status, body) = send_json(&app, "PUT", &task_uri, Some(json!({ "column_id": other_columns[0] }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "column_project_mismatch");
+
+    let (status, body) = send_json(&app, "DELETE", "/api/tasks/999999", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "task_not_found");
+    assert_eq!(body["error"], "Task not found");
+
+    let (status, body) = send_json(&app, "GET", &format!("{}?order_by=colour", tasks_uri), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "invalid_query");
+    assert_eq!(body["details"][0]["field"], "order_by");
+
+    let (status, body) = send_json(&app, "POST", "/api/tags", Some(json!({ "name": "dup", "color": "#fff" }))).await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let (status, body) = send_json(&app, "POST", "/api/tags", Some(json!({ "name": "dup", "color": "#000" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "tag_name_taken");
+}
+
+#[tokio::test]
+async fn test_wip_limit_soft_warning_and_hard_block() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "WIP").await;
+    let (backlog, in_progress) = (columns[0], columns[2]);
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+
+    let (status, column) = send_json(&app, "PUT", &format!("/api/columns/{}", in_progress), Some(json!({ "wip_limit": 1 }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(column["wip_limit"], 1);
+    assert_eq!(column["name"], "In Progress", "omitted fields are left alone");
+
+    let (_, first) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "First", "column_id": in_progress }))).await;
+    assert!(first.get("warnings").is_none());
+
+    let (status, second) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Second", "column_id": in_progress }))).await;
+    assert_eq!(status, StatusCode::CREATED, "soft mode still accepts the task");
+    assert_eq!(second["warnings"][0]["code"], "wip_limit_exceeded");
+    assert_eq!(second["warnings"][0]["task_count"], 2);
+
+    let (status, project) = send_json(&app, "PUT", &format!("/api/projects/{}", project_id), Some(json!({ "wip_mode": "hard" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(project["name"], "WIP");
+
+    let (status, body) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Third", "column_id": in_progress }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "wip_limit_exceeded");
+
+    let (_, queued) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Queued", "column_id": backlog }))).await;
+    let (status, body) = send_json(
+        &app,
+        "PUT",
+        "/api/tasks/bulk-update",
+        Some(json!([{ "id": queued["id"], "column_id": in_progress }])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "wip_limit_exceeded");
+
+    // Reordering inside a full column is not "entering" it.
+    let (status, _) = send_json(&app, "PUT", &format!("/api/tasks/{}", first["id"]), Some(json!({ "sort_order": 5 }))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send_json(&app, "PUT", &format!("/api/columns/{}", in_progress), Some(json!({ "wip_limit": 0 }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "wip_limit");
+
+    let (_, column) = send_json(&app, "PUT", &format!("/api/columns/{}", in_progress), Some(json!({ "wip_limit": null }))).await;
+    assert!(column["wip_limit"].is_null());
+    let (status, _) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Unlimited", "column_id": in_progress }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_due_dates_overdue_and_upcoming() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Deadlines").await;
+    let done = *columns.last().unwrap();
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let in_days = |days: i64| (chrono::Utc::now() + chrono::Duration::days(days)).to_rfc3339();
+
+    let (status, late) = send_json(
+        &app,
+        "POST",
+        &tasks_uri,
+        Some(json!({ "title": "Late", "column_id": columns[0], "due_at": "2020-01-15", "start_at": "2020-01-01T09:30:00+02:00" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(late["due_at"], "2020-01-15 00:00:00");
+    assert_eq!(late["start_at"], "2020-01-01 07:30:00", "timestamps are stored in UTC");
+
+    send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Shipped late", "column_id": done, "due_at": "2020-01-10" }))).await;
+    let (_, soon) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Soon", "column_id": columns[1], "due_at": in_days(2) }))).await;
+    send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Later", "column_id": columns[1], "due_at": in_days(30) }))).await;
+    send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Someday", "column_id": columns[1] }))).await;
+
+    let (status, overdue) = send_json(&app, "GET", "/api/tasks/overdue", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let titles: Vec<&str> = overdue.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, vec!["Late"], "tasks in the last column are finished");
+    assert_eq!(overdue[0]["project_name"], "Deadlines");
+
+    let (_, upcoming) = send_json(&app, "GET", "/api/tasks/upcoming", None).await;
+    let titles: Vec<&str> = upcoming.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, vec!["Soon"]);
+
+    let (_, upcoming) = send_json(&app, "GET", "/api/tasks/upcoming?days=60", None).await;
+    assert_eq!(upcoming.as_array().unwrap().len(), 2);
+
+    let (status, body) = send_json(&app, "GET", "/api/tasks/upcoming?days=0", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["details"][0]["field"], "days");
+
+    let soon_uri = format!("/api/tasks/{}", soon["id"]);
+    let (status, body) = send_json(&app, "PUT", &soon_uri, Some(json!({ "due_at": "next tuesday" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "due_at");
+
+    let (status, body) = send_json(&app, "PUT", &soon_uri, Some(json!({ "start_at": in_days(10) }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "start_at");
+
+    let (status, cleared) = send_json(&app, "PUT", &soon_uri, Some(json!({ "due_at": null }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(cleared["due_at"].is_null());
+    assert_eq!(cleared["title"], "Soon");
+}
+
+/// Fetches a calendar feed without an API key, the way a calendar app would.
+async fn fetch_calendar(app: &Router, uri: &str) -> (StatusCode, String) {
+    let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_calendar_feed_tokens_and_rendering() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Launch").await;
+    let (other_id, _) = create_project_with_columns(&app, "Other").await;
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let feed_uri = format!("/api/projects/{}/calendar-feed", project_id);
+
+    let (_, task) = send_json(
+        &app,
+        "POST",
+        &tasks_uri,
+        Some(json!({ "title": "Ship it, finally; really", "column_id": columns[1], "priority": "urgent", "due_at": "2030-03-01T17:00:00Z" })),
+    )
+    .await;
+    send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Undated", "column_id": columns[0] }))).await;
+
+    let (status, body) = send_json(&app, "GET", &feed_uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "calendar_feed_not_found");
+
+    let (status, feed) = send_json(&app, "POST", &feed_uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    let path = feed["path"].as_str().unwrap().to_string();
+
+    let (status, ics) = fetch_calendar(&app, &path).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.contains(&format!("UID:task-{}@kanban-board\r\n", task["id"])));
+    assert!(ics.contains("SUMMARY:Ship it\\, finally\\; really\r\n"));
+    assert!(ics.contains("DTSTART:20300301T170000Z\r\n"));
+    assert!(ics.contains("PRIORITY:1\r\n"));
+    assert!(!ics.contains("Undated"), "only tasks with due dates are listed");
+
+    let (_, todos) = fetch_calendar(&app, &format!("{}&kind=todo", path)).await;
+    assert!(todos.contains("BEGIN:VTODO\r\nUID:"));
+    assert!(todos.contains("DUE:20300301T170000Z\r\n"));
+    assert!(todos.contains("STATUS:IN-PROCESS\r\n"));
+
+    let (status, body) = fetch_calendar(&app, &format!("/api/projects/{}/calendar.ics", project_id)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(body.contains("invalid_feed_token"));
+
+    let token = feed["token"].as_str().unwrap();
+    let (status, _) = fetch_calendar(&app, &format!("/api/projects/{}/calendar.ics?token={}", other_id, token)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "a project token only opens its own feed");
+
+    let (_, rotated) = send_json(&app, "POST", &feed_uri, None).await;
+    assert_ne!(rotated["token"], feed["token"]);
+    let (status, _) = fetch_calendar(&app, &path).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "rotation revokes the old token");
+    let (status, _) = fetch_calendar(&app, rotated["path"].as_str().unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, global) = send_json(&app, "POST", "/api/calendar-feed", None).await;
+    assert!(global["project_id"].is_null());
+    let (status, ics) = fetch_calendar(&app, global["path"].as_str().unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(ics.contains("CATEGORIES:Launch,"));
+}
+
+#[tokio::test]
+async fn test_personal_tokens_attribute_and_revoke() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Team").await;
+
+    let (status, alice) = send_json(&app, "POST", "/api/users", Some(json!({ "name": "alice" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, bob) = send_json(&app, "POST", "/api/users", Some(json!({ "name": "bob" }))).await;
+    let (status, body) = send_json(&app, "POST", "/api/users", Some(json!({ "name": "alice" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "user_name_taken");
+
+    let tokens_uri = format!("/api/users/{}/tokens", alice["id"]);
+    let (status, created) = send_json(&app, "POST", &tokens_uri, Some(json!({ "name": "laptop" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let token = created["token"].as_str().unwrap().to_string();
+    assert!(token.starts_with(created["prefix"].as_str().unwrap()));
+
+    let (_, listed) = send_json(&app, "GET", &tokens_uri, None).await;
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert!(listed[0].get("token").is_none(), "the secret is only shown once");
+
+    let (status, me) = send_json_as(&app, &token, "GET", "/api/users/me", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["name"], "alice");
+    assert_eq!(me["is_admin"], false);
+
+    let (status, body) = send_json_as(&app, &token, "POST", "/api/users", Some(json!({ "name": "mallory" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "admin_required");
+    let bob_tokens = format!("/api/users/{}/tokens", bob["id"]);
+    let (status, _) = send_json_as(&app, &token, "POST", &bob_tokens, Some(json!({ "name": "sneaky" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    add_member(&app, project_id, alice["id"].as_i64().unwrap(), "editor").await;
+    let (status, task) = send_json_as(
+        &app,
+        &token,
+        "POST",
+        &format!("/api/projects/{}/tasks", project_id),
+        Some(json!({ "title": "Mine", "column_id": columns[0], "assignee_id": bob["id"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(task["assignee_id"], bob["id"]);
+
+    let (_, history) = send_json(&app, "GET", &format!("/api/tasks/{}/history", task["id"]), None).await;
+    assert_eq!(history["items"][0]["actor"], "alice", "personal tokens are attributed to their user");
+
+    let (_, page) = send_json(&app, "GET", &format!("/api/projects/{}/tasks?assignee_id={}", project_id, bob["id"]), None).await;
+    assert_eq!(page["tasks"].as_array().unwrap().len(), 1);
+
+    let task_uri = format!("/api/tasks/{}", task["id"]);
+    let (status, body) = send_json(&app, "PUT", &task_uri, Some(json!({ "assignee_id": 9999 }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "invalid_reference");
+    let (_, unassigned) = send_json(&app, "PUT", &task_uri, Some(json!({ "assignee_id": null }))).await;
+    assert!(unassigned["assignee_id"].is_null());
+
+    let (status, _) = send_json_as(&app, &token, "DELETE", &format!("/api/tokens/{}", created["id"]), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, body) = send_json_as(&app, &token, "GET", "/api/users/me", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "invalid_api_key");
+}
+
+#[tokio::test]
+async fn test_role_non_member_cannot_see_project() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Private").await;
+    send_json(&app, "POST", &format!("/api/projects/{}/tasks", project_id), Some(json!({ "title": "Secret plan", "column_id": columns[0] }))).await;
+    let (_, token) = create_user_with_token(&app, "outsider").await;
+
+    let (status, projects) = send_json_as(&app, &token, "GET", "/api/projects", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(projects.as_array().unwrap().is_empty());
+
+    let (status, body) = send_json_as(&app, &token, "GET", &format!("/api/projects/{}", project_id), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "non-members can't tell the project exists");
+    assert_eq!(body["code"], "project_not_found");
+
+    let (status, hidden) = send_json_as(&app, &token, "POST", "/api/tokens", Some(json!({ "name": "probe", "project_ids": [project_id] }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, missing) = send_json_as(&app, &token, "POST", "/api/tokens", Some(json!({ "name": "probe", "project_ids": [project_id + 1000] }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(hidden, missing, "token limits can't probe which projects exist");
+
+    let (_, results) = send_json_as(&app, &token, "GET", "/api/search?q=secret", None).await;
+    assert!(results.as_array().unwrap().is_empty());
+    let (_, results) = send_json(&app, "GET", "/api/search?q=secret", None).await;
+    assert_eq!(results.as_array().unwrap().len(), 1, "the admin key sees everything");
+}
+
+#[tokio::test]
+async fn test_role_viewer_is_read_only() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Client board").await;
+    create_project_with_columns(&app, "Internal").await;
+    let (_, task) = send_json(&app, "POST", &format!("/api/projects/{}/tasks", project_id), Some(json!({ "title": "Review", "column_id": columns[0] }))).await;
+    let (contractor_id, token) = create_user_with_token(&app, "contractor").await;
+    add_member(&app, project_id, contractor_id, "viewer").await;
+
+    let (_, projects) = send_json_as(&app, &token, "GET", "/api/projects", None).await;
+    let names: Vec<&str> = projects.as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["Client board"]);
+
+    let (status, board) = send_json_as(&app, &token, "GET", &format!("/api/projects/{}", project_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(board["columns"][0]["tasks"][0]["title"], "Review");
+    let (status, _) = send_json_as(&app, &token, "GET", &format!("/api/projects/{}/tasks", project_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let task_uri = format!("/api/tasks/{}", task["id"]);
+    let forbidden = [
+        ("POST", format!("/api/projects/{}/tasks", project_id), Some(json!({ "title": "Nope", "column_id": columns[0] }))),
+        ("PUT", task_uri.clone(), Some(json!({ "column_id": columns[1] }))),
+        ("DELETE", task_uri.clone(), None),
+        ("POST", format!("{}/subtasks", task_uri), Some(json!({ "title": "Nope" }))),
+        ("PUT", format!("/api/columns/{}", columns[0]), Some(json!({ "name": "Renamed" }))),
+        ("PUT", format!("/api/projects/{}", project_id), Some(json!({ "name": "Renamed" }))),
+        ("POST", "/api/tags".to_string(), Some(json!({ "name": "nope", "color": "#000000" }))),
+    ];
+    for (method, uri, body) in forbidden {
+        let (status, response) = send_json_as(&app, &token, method, &uri, body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{} {}", method, uri);
+        assert_eq!(response["code"], "insufficient_role");
+    }
+
+    let (_, unchanged) = send_json(&app, "GET", &format!("/api/projects/{}/tasks", project_id), None).await;
+    assert_eq!(unchanged["tasks"][0]["column_id"], columns[0]);
+}
+
+#[tokio::test]
+async fn test_role_editor_changes_tasks_but_not_project() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Shared").await;
+    let (editor_id, token) = create_user_with_token(&app, "editor").await;
+    add_member(&app, project_id, editor_id, "editor").await;
+
+    let (status, task) = send_json_as(&app, &token, "POST", &format!("/api/projects/{}/tasks", project_id), Some(json!({ "title": "Build", "column_id": columns[0] }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, moved) = send_json_as(&app, &token, "PUT", &format!("/api/tasks/{}", task["id"]), Some(json!({ "column_id": columns[2] }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(moved["column_id"], columns[2]);
+    let (status, _) = send_json_as(&app, &token, "PUT", &format!("/api/columns/{}", columns[1]), Some(json!({ "wip_limit": 3 }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send_json_as(&app, &token, "POST", "/api/tags", Some(json!({ "name": "infra", "color": "#00ff00" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, body) = send_json_as(&app, &token, "PUT", &format!("/api/projects/{}", project_id), Some(json!({ "wip_mode": "hard" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "insufficient_role");
+    let (status, _) = send_json_as(&app, &token, "DELETE", &format!("/api/projects/{}", project_id), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send_json_as(&app, &token, "PUT", &format!("/api/projects/{}/members/{}", project_id, editor_id), Some(json!({ "role": "admin" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "editors can't promote themselves");
+}
+
+#[tokio::test]
+async fn test_role_admin_manages_project_and_members() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (owner_id, owner) = create_user_with_token(&app, "owner").await;
+    let (guest_id, guest) = create_user_with_token(&app, "guest").await;
+
+    let (status, project) = send_json_as(&app, &owner, "POST", "/api/projects", Some(json!({ "name": "Owned" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let project_id = project["id"].as_i64().unwrap();
+    let members_uri = format!("/api/projects/{}/members", project_id);
+
+    let (_, members) = send_json_as(&app, &owner, "GET", &members_uri, None).await;
+    assert_eq!(members[0]["user_id"], owner_id);
+    assert_eq!(members[0]["role"], "admin", "creators administer their projects");
+
+    let guest_uri = format!("{}/{}", members_uri, guest_id);
+    let (status, member) = send_json_as(&app, &owner, "PUT", &guest_uri, Some(json!({ "role": "viewer" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(member["name"], "guest");
+    let (status, _) = send_json_as(&app, &guest, "GET", &format!("/api/projects/{}", project_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send_json_as(&app, &owner, "PUT", &guest_uri, Some(json!({ "role": "owner" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "role");
+
+    let owner_uri = format!("{}/{}", members_uri, owner_id);
+    let (status, body) = send_json_as(&app, &owner, "DELETE", &owner_uri, None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "last_project_admin");
+
+    let (status, _) = send_json_as(&app, &owner, "PUT", &format!("/api/projects/{}", project_id), Some(json!({ "wip_mode": "hard" }))).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send_json_as(&app, &owner, "DELETE", &guest_uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send_json_as(&app, &guest, "GET", &format!("/api/projects/{}", project_id), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send_json_as(&app, &owner, "DELETE", "/api/tags/1", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN, "shared tags are only deleted with the admin key");
+    assert_eq!(body["code"], "admin_required");
+
+    let (status, _) = send_json_as(&app, &owner, "DELETE", &format!("/api/projects/{}", project_id), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn test_scoped_token_limited_to_scope_and_projects() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool.clone());
+    let (project_id, columns) = create_project_with_columns(&app, "Automation").await;
+    let (other_id, _) = create_project_with_columns(&app, "Elsewhere").await;
+
+    let (status, body) = send_json(&app, "POST", "/api/tokens", Some(json!({ "name": "ci", "scopes": ["tasks:delete"] }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "scopes");
+    let (status, body) = send_json(&app, "POST", "/api/tokens", Some(json!({ "name": "ci", "expires_at": "2000-01-01" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "expires_at");
+
+    let (status, created) = send_json(
+        &app,
+        "POST",
+        "/api/tokens",
+        Some(json!({ "name": "ci", "scopes": ["tasks:write"], "project_ids": [project_id], "expires_at": "2999-01-01" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(created["user_id"].is_null());
+    assert_eq!(created["scopes"], json!(["tasks:write"]));
+    let token = created["token"].as_str().unwrap().to_string();
+
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let (status, task) = send_json_as(&app, &token, "POST", &tasks_uri, Some(json!({ "title": "Nightly build failed", "column_id": columns[0] }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, history) = send_json(&app, "GET", &format!("/api/tasks/{}/history", task["id"]), None).await;
+    assert_eq!(history["items"][0]["actor"], "token:ci");
+
+    let (status, _) = send_json_as(&app, &token, "GET", &tasks_uri, None).await;
+    assert_eq!(status, StatusCode::OK, "writing tasks implies reading them");
+    let (_, projects) = send_json_as(&app, &token, "GET", "/api/projects", None).await;
+    assert_eq!(projects.as_array().unwrap().len(), 1);
+
+    let (status, body) = send_json_as(&app, &token, "DELETE", &format!("/api/projects/{}", project_id), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "insufficient_scope");
+    let (status, body) = send_json_as(&app, &token, "GET", &format!("/api/projects/{}", other_id), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "project_not_found");
+    let (status, body) = send_json_as(&app, &token, "POST", "/api/projects", Some(json!({ "name": "Sprawl" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "insufficient_scope");
+
+    sqlx::query("UPDATE api_tokens SET expires_at = datetime('now', '-1 minute') WHERE id = ?")
+        .bind(created["id"].as_i64().unwrap())
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (status, body) = send_json_as(&app, &token, "GET", &tasks_uri, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "token_expired");
+}
+
+#[tokio::test]
+async fn test_webhooks_sign_retry_and_log_deliveries() {
+    use kanban_board::webhooks::{sign, DeliverySettings, SIGNATURE_HEADER};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let pool = setup_test_db().await;
+    let bus = EventBus::new();
+    let settings = DeliverySettings {
+        retry_base: Duration::from_millis(20),
+        poll_interval: Duration::from_millis(20),
+        ..DeliverySettings::default()
+    };
+    kanban_board::webhooks::spawn(pool.clone(), &bus, settings);
+    let (_blob_dir, blobs) = test_blob_store();
+    let app = create_test_router_with(pool, bus, blobs);
+
+    // Stand-in receiver that is down for the first request and records the rest.
+    let (received_tx, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let receiver = Router::new().route(
+        "/hook",
+        post(move |headers: axum::http::HeaderMap, body: String| {
+            let received_tx = received_tx.clone();
+            let calls = calls.clone();
+            async move {
+                if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return StatusCode::SERVICE_UNAVAILABLE;
+                }
+                received_tx.send((headers, body)).unwrap();
+                StatusCode::OK
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let receiver_url = format!("http://{}/hook", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, receiver).await.unwrap() });
+
+    let (project_id, columns) = create_project_with_columns(&app, "Hooked").await;
+    let hooks_uri = format!("/api/projects/{}/webhooks", project_id);
+
+    let (status, body) = send_json(&app, "POST", &hooks_uri, Some(json!({ "url": "ftp://example.com" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "url");
+    let (status, body) = send_json(&app, "POST", &hooks_uri, Some(json!({ "url": receiver_url, "events": ["task.finished"] }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "events");
+
+    let (status, hook) = send_json(&app, "POST", &hooks_uri, Some(json!({ "url": receiver_url, "events": ["task.completed"] }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let secret = hook["secret"].as_str().unwrap().to_string();
+    let (_, listed) = send_json(&app, "GET", &hooks_uri, None).await;
+    assert!(listed[0].get("secret").is_none(), "the secret is only shown once");
+
+    let (_, task) = send_json(&app, "POST", &format!("/api/projects/{}/tasks", project_id), Some(json!({ "title": "Ship it", "column_id": columns[0] }))).await;
+    let done_column = *columns.last().unwrap();
+    send_json(&app, "PUT", &format!("/api/tasks/{}", task["id"]), Some(json!({ "column_id": done_column }))).await;
+
+    let (headers, body) = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("webhook was not delivered")
+        .unwrap();
+    assert_eq!(headers["x-kanban-event"], "task.completed");
+    assert_eq!(headers[SIGNATURE_HEADER].to_str().unwrap(), sign(&secret, body.as_bytes()));
+    let payload: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(payload["data"]["title"], "Ship it");
+    assert_eq!(payload["data"]["column_id"], done_column);
+
+    let deliveries_uri = format!("/api/webhooks/{}/deliveries", hook["id"]);
+    let mut log = Value::Null;
+    for _ in 0..100 {
+        (_, log) = send_json(&app, "GET", &deliveries_uri, None).await;
+        if log["items"][0]["status"] == "delivered" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let items = log["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1, "only task.completed was subscribed to");
+    assert_eq!(items[0]["status"], "delivered");
+    assert_eq!(items[0]["attempts"], 2, "the first attempt failed and was retried");
+    assert_eq!(items[0]["response_status"], 200);
+}
+
+#[tokio::test]
+async fn test_webhooks_deliver_project_deleted() {
+    use kanban_board::webhooks::DeliverySettings;
+    use std::time::Duration;
+
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let pool = setup_test_db().await;
+    let bus = EventBus::new();
+    let settings = DeliverySettings {
+        poll_interval: Duration::from_millis(20),
+        ..DeliverySettings::default()
+    };
+    kanban_board::webhooks::spawn(pool.clone(), &bus, settings);
+    let (_blob_dir, blobs) = test_blob_store();
+    let app = create_test_router_with(pool.clone(), bus, blobs);
+
+    let (received_tx, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let receiver = Router::new().route(
+        "/hook",
+        post(move |headers: axum::http::HeaderMap, body: String| {
+            let received_tx = received_tx.clone();
+            async move {
+                received_tx.send((headers, body)).unwrap();
+                StatusCode::OK
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let receiver_url = format!("http://{}/hook", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, receiver).await.unwrap() });
+
+    let (project_id, _) = create_project_with_columns(&app, "Short-lived").await;
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{}/webhooks", project_id),
+        Some(json!({ "url": receiver_url, "events": ["project.deleted"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, _) = send_json(&app, "DELETE", &format!("/api/projects/{}", project_id), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (headers, body) = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("project.deleted was not delivered")
+        .unwrap();
+    assert_eq!(headers["x-kanban-event"], "project.deleted");
+    let payload: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(payload["data"]["id"], project_id);
+
+    // Once sent, the delivery has no webhook left to be logged under.
+    for _ in 0..100 {
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhook_deliveries").fetch_one(&pool).await.unwrap();
+        if left == 0 {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("the finished delivery was not cleared");
+}
+
+#[tokio::test]
+async fn test_webhook_deliveries_survive_event_bursts() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let pool = setup_test_db().await;
+    let bus = EventBus::new();
+    // Subscribed but never read, like a worker that has fallen far behind.
+    let _stalled = bus.subscribe();
+    let (_blob_dir, blobs) = test_blob_store();
+    let app = create_test_router_with(pool.clone(), bus, blobs);
+    let (project_id, _) = create_project_with_columns(&app, "Busy").await;
+    let (status, hook) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{}/webhooks", project_id),
+        Some(json!({ "url": "http://127.0.0.1:9/hook", "events": ["task.created"] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // One import publishes more events than the broadcast channel holds.
+    let rows: String = (1..=300).map(|i| format!("Task {}\n", i)).collect();
+    let (status, _) = send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{}/tasks/import", project_id),
+        Some(json!({ "csv": format!("Title\n{}", rows), "mapping": { "Title": "title" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let queued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhook_deliveries WHERE webhook_id = ? AND event = 'task.created'")
+        .bind(hook["id"].as_i64().unwrap())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(queued, 300);
+}
+
+#[tokio::test]
+async fn test_comments_thread_authors_and_counts() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Discussed").await;
+    let (_, task) = send_json(&app, "POST", &format!("/api/projects/{}/tasks", project_id), Some(json!({ "title": "Pick a logo", "column_id": columns[0] }))).await;
+    let (alice_id, alice) = create_user_with_token(&app, "alice").await;
+    let (bob_id, bob) = create_user_with_token(&app, "bob").await;
+    add_member(&app, project_id, alice_id, "editor").await;
+    add_member(&app, project_id, bob_id, "editor").await;
+
+    let comments_uri = format!("/api/tasks/{}/comments", task["id"]);
+    let (status, body) = send_json_as(&app, &alice, "POST", &comments_uri, Some(json!({ "body": "  " }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "body");
+
+    let (status, comment) = send_json_as(&app, &alice, "POST", &comments_uri, Some(json!({ "body": "I like **option B**" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(comment["author"], "alice");
+    assert_eq!(comment["author_id"], alice_id);
+    assert!(comment["updated_at"].is_null());
+
+    let comment_uri = format!("/api/comments/{}", comment["id"]);
+    let (status, body) = send_json_as(&app, &bob, "PUT", &comment_uri, Some(json!({ "body": "I like option A" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "not_comment_author");
+    let (status, _) = send_json_as(&app, &bob, "DELETE", &comment_uri, None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, edited) = send_json_as(&app, &alice, "PUT", &comment_uri, Some(json!({ "body": "I like **option B**, see mockup" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(edited["updated_at"].is_string());
+
+    let (_, reply) = send_json(&app, "POST", &comments_uri, Some(json!({ "body": "Agreed" }))).await;
+    assert!(reply["author_id"].is_null());
+
+    let (_, thread) = send_json_as(&app, &bob, "GET", &comments_uri, None).await;
+    let bodies: Vec<&str> = thread.as_array().unwrap().iter().map(|c| c["body"].as_str().unwrap()).collect();
+    assert_eq!(bodies, ["I like **option B**, see mockup", "Agreed"]);
+    let (_, details) = send_json(&app, "GET", &format!("/api/tasks/{}", task["id"]), None).await;
+    assert_eq!(details["comment_count"], 2);
+    assert!(details["description"].is_null(), "comments don't touch the description");
+
+    let (status, _) = send_json(&app, "DELETE", &comment_uri, None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT, "admins can delete any comment");
+    let (_, history) = send_json(&app, "GET", &format!("/api/tasks/{}/history", task["id"]), None).await;
+    assert_eq!(history["items"][0]["entity_type"], "comment");
+    assert_eq!(history["items"][0]["action"], "delete");
+}
+
+#[tokio::test]
+async fn test_attachments_upload_dedupe_download_and_collect() {
+    use sha2::{Digest, Sha256};
+
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let (_blob_dir, blobs) = test_blob_store();
+    let app = create_test_router_with(setup_test_db().await, EventBus::new(), blobs.clone());
+    let (project_id, columns) = create_project_with_columns(&app, "Bugs").await;
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let (_, crash) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Crash on save", "column_id": columns[0] }))).await;
+    let (_, dupe) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Crash on save (dupe)", "column_id": columns[0] }))).await;
+
+    let log = b"panic at save.rs:42";
+    let (status, first) = upload_file(&app, &crash["id"], "C:\\Users\\me\\crash.log", log).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(first["filename"], "crash.log", "only the last path component is kept");
+    assert_eq!(first["size"], log.len());
+    let sha256: String = Sha256::digest(log).iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(first["sha256"], sha256.as_str());
+    let (status, second) = upload_file(&app, &dupe["id"], "crash.log", log).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(second["sha256"], first["sha256"], "identical uploads share one blob");
+    assert!(blobs.path_for(&sha256).exists());
+
+    let request = Request::builder()
+        .uri(format!("/api/attachments/{}", first["id"]))
+        .header("X-API-Key", "test-secret-key")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/plain");
+    assert!(response.headers()[header::CONTENT_DISPOSITION].to_str().unwrap().contains("filename=\"crash.log\""));
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(&bytes[..], log);
+
+    let too_big = vec![b'x'; 1024 * 1024 + 1];
+    let (status, body) = upload_file(&app, &crash["id"], "huge.bin", &too_big).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(body["code"], "attachment_too_large");
+    let (_, listed) = send_json(&app, "GET", &format!("/api/tasks/{}/attachments", crash["id"]), None).await;
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+
+    let (status, _) = send_json(&app, "DELETE", &format!("/api/tasks/{}", crash["id"]), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(blobs.path_for(&sha256).exists(), "still attached to the other task");
+    let (status, _) = send_json(&app, "DELETE", &format!("/api/projects/{}", project_id), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert!(!blobs.path_for(&sha256).exists(), "collected once its last task is gone");
+}
+
+#[tokio::test]
+async fn test_dependencies_cycles_and_blocked_moves() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Release").await;
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let (_, schema) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Design schema", "column_id": columns[0] }))).await;
+    let (_, api) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Build API", "column_id": columns[0] }))).await;
+    let (_, ui) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Build UI", "column_id": columns[0] }))).await;
+
+    let deps_uri = |task: &Value| format!("/api/tasks/{}/dependencies", task["id"]);
+    let (status, link) = send_json(&app, "POST", &deps_uri(&api), Some(json!({ "task_id": schema["id"], "kind": "blocked_by" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(link["source_id"], schema["id"], "blocked_by is stored as the other task blocking this one");
+    assert_eq!(link["kind"], "blocks");
+    let (status, _) = send_json(&app, "POST", &deps_uri(&api), Some(json!({ "task_id": ui["id"], "kind": "blocks" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send_json(&app, "POST", &deps_uri(&ui), Some(json!({ "task_id": schema["id"], "kind": "relates_to" }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, body) = send_json(&app, "POST", &deps_uri(&ui), Some(json!({ "task_id": schema["id"], "kind": "blocks" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "dependency_cycle");
+    let (status, body) = send_json(&app, "POST", &deps_uri(&schema), Some(json!({ "task_id": ui["id"], "kind": "relates_to" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT, "relates_to has no direction");
+    assert_eq!(body["code"], "dependency_exists");
+    let (status, _) = send_json(&app, "POST", &deps_uri(&ui), Some(json!({ "task_id": ui["id"], "kind": "duplicates" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (_, details) = send_json(&app, "GET", &format!("/api/tasks/{}", api["id"]), None).await;
+    assert_eq!(details["dependencies"]["blocked_by"][0]["title"], "Design schema");
+    assert_eq!(details["dependencies"]["blocked_by"][0]["done"], false);
+    assert_eq!(details["dependencies"]["blocks"][0]["title"], "Build UI");
+
+    let (status, moved) = send_json(&app, "PUT", &format!("/api/tasks/{}", api["id"]), Some(json!({ "column_id": columns[2] }))).await;
+    assert_eq!(status, StatusCode::OK, "soft mode lets the move through");
+    assert_eq!(moved["warnings"][0]["code"], "blocked_by_open_tasks");
+    assert_eq!(moved["warnings"][0]["blocker_ids"], json!([schema["id"]]));
+    let (_, moved) = send_json(&app, "PUT", &format!("/api/tasks/{}", api["id"]), Some(json!({ "column_id": columns[0] }))).await;
+    assert!(moved.get("warnings").is_none(), "moving back is never blocked");
+
+    send_json(&app, "PUT", &format!("/api/projects/{}", project_id), Some(json!({ "blocker_mode": "hard" }))).await;
+    let (status, body) = send_json(&app, "PUT", "/api/tasks/bulk-update", Some(json!([{ "id": api["id"], "column_id": columns[1] }]))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "blocked_by_open_tasks");
+
+    let done = columns.last().unwrap();
+    send_json(&app, "PUT", &format!("/api/tasks/{}", schema["id"]), Some(json!({ "column_id": done }))).await;
+    let (status, moved) = send_json(&app, "PUT", &format!("/api/tasks/{}", api["id"]), Some(json!({ "column_id": columns[1] }))).await;
+    assert_eq!(status, StatusCode::OK, "done blockers no longer block");
+    assert!(moved.get("warnings").is_none());
+
+    let (status, _) = send_json(&app, "DELETE", &format!("/api/dependencies/{}", link["id"]), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, links) = send_json(&app, "GET", &deps_uri(&api), None).await;
+    assert!(links["blocked_by"].as_array().unwrap().is_empty());
+    assert_eq!(links["blocks"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_task_hierarchy_rolls_up_progress() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Roadmap").await;
+    let (other_project_id, other_columns) = create_project_with_columns(&app, "Elsewhere").await;
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let done = *columns.last().unwrap();
+
+    let (_, epic) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Billing", "column_id": columns[0] }))).await;
+    let (status, invoices) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Invoices", "column_id": columns[1], "parent_id": epic["id"] }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(invoices["parent_id"], epic["id"]);
+    let (_, pdf) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "PDF export", "column_id": done, "parent_id": invoices["id"] }))).await;
+    let (_, _refunds) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Refunds", "column_id": done, "parent_id": epic["id"] }))).await;
+    let (_, subtask) = send_json(&app, "POST", &format!("/api/tasks/{}/subtasks", invoices["id"]), Some(json!({ "title": "Numbering" }))).await;
+    send_json(&app, "POST", &format!("/api/tasks/{}/subtasks", invoices["id"]), Some(json!({ "title": "Tax lines" }))).await;
+    send_json(&app, "PUT", &format!("/api/subtasks/{}", subtask["id"]), Some(json!({ "done": true }))).await;
+
+    let (status, body) = send_json(&app, "PUT", &format!("/api/tasks/{}", epic["id"]), Some(json!({ "parent_id": pdf["id"] }))).await;
+    assert_eq!(status, StatusCode::CONFLICT, "an epic can't go under its own grandchild");
+    assert_eq!(body["code"], "parent_cycle");
+    let (_, foreign) = send_json(&app, "POST", &format!("/api/projects/{}/tasks", other_project_id), Some(json!({ "title": "Foreign", "column_id": other_columns[0] }))).await;
+    let (status, body) = send_json(&app, "PUT", &format!("/api/tasks/{}", foreign["id"]), Some(json!({ "parent_id": epic["id"] }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "parent_id");
+
+    let (_, details) = send_json(&app, "GET", &format!("/api/tasks/{}", epic["id"]), None).await;
+    let children: Vec<&str> = details["children"].as_array().unwrap().iter().map(|c| c["title"].as_str().unwrap()).collect();
+    assert_eq!(children, ["Invoices", "Refunds"], "only direct children are listed");
+    assert_eq!(details["progress"], json!({ "tasks_done": 2, "tasks_total": 3, "subtasks_done": 1, "subtasks_total": 2 }));
+
+    let (_, board) = send_json(&app, "GET", &format!("/api/projects/{}", project_id), None).await;
+    let cards: Vec<&Value> = board["columns"].as_array().unwrap().iter().flat_map(|c| c["tasks"].as_array().unwrap()).collect();
+    let card = |title: &str| cards.iter().find(|t| t["title"] == title).copied().unwrap();
+    assert_eq!(card("Billing")["progress"], details["progress"]);
+    assert_eq!(card("Invoices")["progress"], json!({ "tasks_done": 1, "tasks_total": 1, "subtasks_done": 1, "subtasks_total": 2 }));
+    assert!(card("Refunds").get("progress").is_none());
+
+    let (_, listed) = send_json(&app, "GET", &format!("{}?parent_id={}", tasks_uri, invoices["id"]), None).await;
+    assert_eq!(listed["tasks"].as_array().unwrap().len(), 1);
+
+    let (status, _) = send_json(&app, "DELETE", &format!("/api/tasks/{}", epic["id"]), None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, orphan) = send_json(&app, "GET", &format!("/api/tasks/{}", invoices["id"]), None).await;
+    assert!(orphan["parent_id"].is_null(), "children of a deleted parent move to the top level");
+}
+
+#[tokio::test]
+async fn test_done_follows_column_kind_not_position() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, _) = create_project_with_columns(&app, "Shipping").await;
+    let (_, board) = send_json(&app, "GET", &format!("/api/projects/{}", project_id), None).await;
+    let kinds: Vec<&str> = board["columns"].as_array().unwrap().iter().map(|c| c["kind"].as_str().unwrap()).collect();
+    assert_eq!(kinds, ["backlog", "todo", "in_progress", "in_progress", "done"]);
+    let done = board["columns"][4]["id"].clone();
+
+    let columns_uri = format!("/api/projects/{}/columns", project_id);
+    let (status, body) = send_json(&app, "POST", &columns_uri, Some(json!({ "name": "Someday", "kind": "maybe" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "kind");
+    let (_, wont_do) = send_json(&app, "POST", &columns_uri, Some(json!({ "name": "Won't do", "kind": "archived" }))).await;
+    let (_, released) = send_json(&app, "POST", &columns_uri, Some(json!({ "name": "Released" }))).await;
+    assert_eq!(released["kind"], "todo");
+
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let (_, task) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Dark mode", "column_id": board["columns"][2]["id"], "due_at": "2020-01-01" }))).await;
+    assert!(task["completed_at"].is_null());
+    let (_, overdue) = send_json(&app, "GET", "/api/tasks/overdue", None).await;
+    assert_eq!(overdue.as_array().unwrap().len(), 1);
+
+    let task_uri = format!("/api/tasks/{}", task["id"]);
+    let (_, moved) = send_json(&app, "PUT", &task_uri, Some(json!({ "column_id": done }))).await;
+    let completed_at = moved["completed_at"].as_str().expect("entering a done column completes the task").to_string();
+    let (_, overdue) = send_json(&app, "GET", "/api/tasks/overdue", None).await;
+    assert!(overdue.as_array().unwrap().is_empty(), "done tasks aren't overdue");
+
+    let (_, released) = send_json(&app, "PUT", &format!("/api/columns/{}", released["id"]), Some(json!({ "kind": "done" }))).await;
+    assert_eq!(released["kind"], "done");
+    let (_, moved) = send_json(&app, "PUT", &task_uri, Some(json!({ "column_id": released["id"] }))).await;
+    assert_eq!(moved["completed_at"], completed_at.as_str(), "moving between done columns keeps the completion time");
+
+    let (_, moved) = send_json(&app, "PUT", &task_uri, Some(json!({ "column_id": wont_do["id"] }))).await;
+    assert!(moved["completed_at"].is_null(), "the last column is not done unless its kind says so");
+    let (_, overdue) = send_json(&app, "GET", "/api/tasks/overdue", None).await;
+    assert!(overdue.as_array().unwrap().is_empty(), "archived tasks aren't overdue either");
+
+    let (_, wont_do) = send_json(&app, "PUT", &format!("/api/columns/{}", wont_do["id"]), Some(json!({ "kind": "done" }))).await;
+    assert_eq!(wont_do["kind"], "done");
+    let (_, details) = send_json(&app, "GET", &task_uri, None).await;
+    assert!(details["completed_at"].is_string(), "tasks complete with their column");
+}
+
+#[tokio::test]
+async fn test_metrics_follow_column_transitions() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool.clone());
+    let (project_id, columns) = create_project_with_columns(&app, "Flow").await;
+    let (todo, in_progress, done) = (columns[1], columns[2], columns[4]);
+
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let (_, shipped) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Shipped", "column_id": todo }))).await;
+    let (_, waiting) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Waiting", "column_id": todo }))).await;
+    let (status, _) = send_json(&app, "PUT", "/api/tasks/bulk-update", Some(json!([{ "id": shipped["id"], "column_id": in_progress }]))).await;
+    assert_eq!(status, StatusCode::OK);
+    send_json(&app, "PUT", &format!("/api/tasks/{}", shipped["id"]), Some(json!({ "column_id": done }))).await;
+    send_json(&app, "PUT", &format!("/api/tasks/{}", shipped["id"]), Some(json!({ "title": "Shipped!" }))).await;
+
+    let transitions: Vec<(Option<i64>, Option<i64>)> = sqlx::query_as(
+        "SELECT from_column_id, to_column_id FROM task_transitions WHERE task_id = ? ORDER BY id",
+    )
+    .bind(shipped["id"].as_i64())
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(transitions, [(None, Some(todo)), (Some(todo), Some(in_progress)), (Some(in_progress), Some(done))]);
+
+    // Spread the history out: a day in To Do, a day and a half in progress.
+    for (offset, to_column) in [("-3 days", todo), ("-2 days", in_progress), ("-12 hours", done)] {
+        sqlx::query("UPDATE task_transitions SET transitioned_at = datetime('now', ?) WHERE task_id = ? AND to_column_id = ?")
+            .bind(offset)
+            .bind(shipped["id"].as_i64())
+            .bind(to_column)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    sqlx::query("UPDATE tasks SET created_at = datetime('now', '-3 days'), completed_at = datetime('now', '-12 hours') WHERE id = ?")
+        .bind(shipped["id"].as_i64())
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let metrics_uri = format!("/api/projects/{}/metrics", project_id);
+    let (status, metrics) = send_json(&app, "GET", &metrics_uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(metrics["completed"], 1);
+    assert_eq!(metrics["lead_time"]["p50_hours"], 60.0);
+    assert_eq!(metrics["cycle_time"]["p95_hours"], 36.0);
+    let throughput = metrics["throughput"].as_array().unwrap();
+    assert_eq!(throughput.len(), 12);
+    assert_eq!(throughput.iter().map(|w| w["completed"].as_u64().unwrap()).sum::<u64>(), 1);
+
+    let flows = metrics["tasks"].as_array().unwrap();
+    assert_eq!(flows.len(), 2);
+    assert_eq!(flows[0]["task_id"], shipped["id"]);
+    assert_eq!(flows[0]["time_in_column"], json!([
+        { "column_id": todo, "column_name": "To Do", "hours": 24.0 },
+        { "column_id": in_progress, "column_name": "In Progress", "hours": 36.0 },
+    ]));
+    assert_eq!(flows[1]["task_id"], waiting["id"]);
+    assert!(flows[1]["lead_time_hours"].is_null());
+    assert_eq!(flows[1]["time_in_column"][0]["column_id"], todo);
+
+    let (status, body) = send_json(&app, "GET", &format!("{}?weeks=0", metrics_uri), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["details"][0]["field"], "weeks");
+}
+
+#[tokio::test]
+async fn test_cumulative_flow_and_burndown() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool.clone());
+    let (project_id, columns) = create_project_with_columns(&app, "Release").await;
+    let (todo, in_progress, done) = (columns[1], columns[2], columns[4]);
+    let (_, release) = send_json(&app, "POST", "/api/tags", Some(json!({ "name": "v2", "color": "#0af" }))).await;
+
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let mut ids = Vec::new();
+    for title in ["Changelog", "Migration guide", "Unrelated"] {
+        let (_, task) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": title, "column_id": todo }))).await;
+        ids.push(task["id"].clone());
+    }
+    for id in &ids[..2] {
+        send_json(&app, "PUT", &format!("/api/tasks/{}", id), Some(json!({ "tag_ids": [release["id"]] }))).await;
+    }
+    send_json(&app, "PUT", &format!("/api/tasks/{}", ids[0]), Some(json!({ "column_id": in_progress }))).await;
+    send_json(&app, "PUT", &format!("/api/tasks/{}", ids[0]), Some(json!({ "column_id": done }))).await;
+
+    // Created three days ago, started two days ago, finished today.
+    sqlx::query("UPDATE task_transitions SET transitioned_at = datetime('now', '-3 days') WHERE from_column_id IS NULL")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE task_transitions SET transitioned_at = datetime('now', '-2 days') WHERE to_column_id = ?")
+        .bind(in_progress)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let today = chrono::Utc::now().date_naive();
+    let (status, cfd) = send_json(&app, "GET", &format!("/api/projects/{}/cfd", project_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(cfd["dates"].as_array().unwrap().len(), 30);
+    assert_eq!(cfd["dates"][29], today.to_string());
+    let names: Vec<&str> = cfd["series"].as_array().unwrap().iter().map(|s| s["column_name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["Backlog", "To Do", "In Progress", "Testing", "Done"]);
+    let counts = |column: usize| cfd["series"][column]["counts"].as_array().unwrap()[25..].to_vec();
+    assert_eq!(counts(1), [json!(0), json!(3), json!(2), json!(2), json!(2)]);
+    assert_eq!(counts(2), [json!(0), json!(0), json!(1), json!(1), json!(0)]);
+    assert_eq!(counts(4), [json!(0), json!(0), json!(0), json!(0), json!(1)]);
+
+    let from = today - chrono::Duration::days(3);
+    let burndown_uri = format!("/api/projects/{}/burndown?from={}&tag_id={}", project_id, from, release["id"]);
+    let (status, burndown) = send_json(&app, "GET", &burndown_uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(burndown["total"], json!([2, 2, 2, 2]));
+    assert_eq!(burndown["remaining"], json!([2, 2, 2, 1]));
+    assert_eq!(burndown["completed"], json!([0, 0, 0, 1]));
+    assert_eq!(burndown["ideal"], json!([2.0, 1.33, 0.67, 0.0]));
+
+    let request = Request::builder()
+        .uri(format!("/api/projects/{}/cfd.csv?from={}", project_id, from))
+        .header("X-API-Key", "test-secret-key")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv; charset=utf-8");
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let csv = String::from_utf8(bytes.to_vec()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "date,Backlog,To Do,In Progress,Testing,Done");
+    assert_eq!(lines[4], format!("{},0,2,0,0,1", today));
+
+    let (status, body) = send_json(&app, "GET", &format!("/api/projects/{}/cfd?from={}&to={}", project_id, today, from), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["details"][0]["field"], "from");
+}
+
+/// An export without the fields expected to differ between two copies of a board.
+fn comparable_export(mut document: Value) -> Value {
+    let object = document.as_object_mut().unwrap();
+    object.remove("exported_at");
+    object["project"].as_object_mut().unwrap().remove("name");
+    document
+}
+
+#[tokio::test]
+async fn test_export_import_round_trip() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Laptop board").await;
+    send_json(&app, "PUT", &format!("/api/projects/{}", project_id), Some(json!({ "wip_mode": "hard" }))).await;
+    send_json(&app, "PUT", &format!("/api/columns/{}", columns[2]), Some(json!({ "wip_limit": 3 }))).await;
+    let (_, bug) = send_json(&app, "POST", "/api/tags", Some(json!({ "name": "bug", "color": "#f00" }))).await;
+
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    let (_, epic) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Billing", "column_id": columns[2], "priority": "high" }))).await;
+    let (_, child) = send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Invoices", "column_id": columns[4], "parent_id": epic["id"], "due_at": "2026-03-01" }))).await;
+    send_json(&app, "POST", &tasks_uri, Some(json!({ "title": "Refunds", "column_id": columns[0], "description": "Partial too" }))).await;
+    send_json(&app, "PUT", &format!("/api/tasks/{}", child["id"]), Some(json!({ "tag_ids": [bug["id"]] }))).await;
+    for title in ["Draft", "Send"] {
+        send_json(&app, "POST", &format!("/api/tasks/{}/subtasks", child["id"]), Some(json!({ "title": title }))).await;
+    }
+    send_json(
+        &app,
+        "POST",
+        &format!("/api/projects/{}/linked-paths", project_id),
+        Some(json!({ "path": "/home/me/billing", "default_column_id": columns[1] })),
+    )
+    .await;
+
+    let (status, exported) = send_json(&app, "GET", &format!("/api/projects/{}/export", project_id), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(exported["format"], "kanban-board");
+    assert_eq!(exported["version"], 1);
+    assert_eq!(exported["tasks"][1]["parent_id"], 1, "ids are numbered within the document");
+    assert_eq!(exported["linked_paths"][0]["default_column_id"], 2);
+
+    // Into a fresh instance, as when moving a board to a staging server.
+    let staging = create_test_router(setup_test_db().await);
+    let (status, summary) = send_json(&staging, "POST", "/api/projects/import", Some(exported.clone())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(summary["tasks"], 3);
+    assert_eq!(summary["tags_created"], 1);
+    let (_, copy) = send_json(&staging, "GET", &format!("/api/projects/{}/export", summary["project"]["id"]), None).await;
+    assert_eq!(copy["project"]["name"], "Laptop board");
+    assert_eq!(comparable_export(copy), comparable_export(exported.clone()));
+
+    // A second copy next to the original needs its own name, reuses the tag and leaves
+    // the already linked path alone.
+    let (status, body) = send_json(&app, "POST", "/api/projects/import", Some(exported.clone())).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "project_name_taken");
+    let (status, summary) = send_json(&app, "POST", "/api/projects/import?name=Second%20copy", Some(exported.clone())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(summary["project"]["name"], "Second copy");
+    assert_eq!(summary["tags_created"], 0);
+    assert_eq!(summary["skipped_linked_paths"], json!(["/home/me/billing"]));
+    let (_, copy) = send_json(&app, "GET", &format!("/api/projects/{}/export", summary["project"]["id"]), None).await;
+    assert_eq!(copy["tasks"], exported["tasks"]);
+    assert_eq!(copy["linked_paths"], json!([]));
+
+    let mut broken = exported.clone();
+    broken["tasks"][0]["column_id"] = json!(99);
+    let (status, body) = send_json(&app, "POST", "/api/projects/import?name=Broken", Some(broken)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "tasks");
+    let (_, projects) = send_json(&app, "GET", "/api/projects", None).await;
+    assert_eq!(projects.as_array().unwrap().len(), 2, "a failed import leaves nothing behind");
+}
+
+#[tokio::test]
+async fn test_import_rejects_duplicate_ids_and_parent_cycles() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Loops").await;
+    let tasks_uri = format!("/api/projects/{}/tasks", project_id);
+    for title in ["Epic", "Story", "Chore"] {
+        send_json(&app, "POST", &tasks_uri, Some(json!({ "title": title, "column_id": columns[0] }))).await;
+    }
+    let (_, exported) = send_json(&app, "GET", &format!("/api/projects/{}/export", project_id), None).await;
+
+    let mut cyclic = exported.clone();
+    cyclic["tasks"][0]["parent_id"] = json!(2);
+    cyclic["tasks"][1]["parent_id"] = json!(1);
+    let (status, body) = send_json(&app, "POST", "/api/projects/import?name=Cyclic", Some(cyclic)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "parent_cycle");
+
+    let mut own_parent = exported.clone();
+    own_parent["tasks"][2]["parent_id"] = json!(3);
+    let (status, _) = send_json(&app, "POST", "/api/projects/import?name=Own%20parent", Some(own_parent)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let mut duplicated = exported.clone();
+    duplicated["tasks"][1]["id"] = json!(1);
+    let (status, body) = send_json(&app, "POST", "/api/projects/import?name=Duplicated", Some(duplicated)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "tasks");
+    let mut duplicated = exported.clone();
+    duplicated["columns"][1]["id"] = json!(1);
+    let (status, body) = send_json(&app, "POST", "/api/projects/import?name=Duplicated", Some(duplicated)).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "columns");
+
+    let (_, projects) = send_json(&app, "GET", "/api/projects", None).await;
+    assert_eq!(projects.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_trello_import_maps_board_and_reports_leftovers() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let board: Value = serde_json::from_str(include_str!("fixtures/trello_board.json")).unwrap();
+
+    let (status, summary) = send_json(&app, "POST", "/api/projects/import/trello", Some(board.clone())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(summary["project"]["name"], "Website Relaunch");
+    assert_eq!(summary["tasks"], 6);
+    assert_eq!(summary["tags_created"], 3);
+    assert_eq!(summary["unmapped"], json!([
+        { "field": "actions", "count": 1 },
+        { "field": "cards.attachments", "count": 1 },
+        { "field": "cards.dueComplete", "count": 1 },
+        { "field": "cards.idMembers", "count": 1 },
+        { "field": "desc", "count": 1 },
+        { "field": "members", "count": 1 },
+    ]));
+
+    let (_, board_export) = send_json(&app, "GET", &format!("/api/projects/{}/export", summary["project"]["id"]), None).await;
+    let columns: Vec<(&str, &str)> = board_export["columns"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| (c["name"].as_str().unwrap(), c["kind"].as_str().unwrap()))
+        .collect();
+    assert_eq!(columns, [
+        ("Ideas", "backlog"),
+        ("To Do", "todo"),
+        ("Doing", "in_progress"),
+        ("Live", "done"),
+        ("Archived", "archived"),
+    ]);
+
+    let tasks = board_export["tasks"].as_array().unwrap();
+    let task = |title: &str| tasks.iter().find(|t| t["title"] == title).unwrap().clone();
+    let font = task("Pick a font");
+    assert_eq!(font["column_id"], 2);
+    assert_eq!(font["sort_order"], 2000, "cards keep their order within a list");
+    assert_eq!(font["description"], "Something friendly but readable");
+    assert_eq!(font["due_at"], "2026-03-01 17:00:00");
+    assert_eq!(font["created_at"], "2024-01-12 21:44:35", "creation time comes from the card id");
+    assert_eq!(font["source_tag"], "trello");
+    assert!(task("Homepage hero")["description"].is_null());
+    assert_eq!(task("Launch")["completed_at"], "2026-02-20 10:00:00");
+    assert_eq!(task("Old banner")["column_id"], 5);
+    assert_eq!(task("Legacy sitemap")["column_id"], 5);
+
+    let subtasks: Vec<(i64, &str, bool)> = board_export["subtasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| (s["task_id"].as_i64().unwrap(), s["title"].as_str().unwrap(), s["done"].as_bool().unwrap()))
+        .collect();
+    let (hero, font) = (task("Homepage hero")["id"].as_i64().unwrap(), font["id"].as_i64().unwrap());
+    assert_eq!(subtasks, [
+        (hero, "Copy: Headline", true),
+        (hero, "Images: Hero photo", false),
+        (font, "Shortlist three", true),
+        (font, "Buy license", false),
+    ]);
+
+    let tags: Vec<(&str, &str)> = board_export["tags"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| (t["name"].as_str().unwrap(), t["color"].as_str().unwrap()))
+        .collect();
+    assert_eq!(tags, [("Bug", "#eb5a46"), ("Design", "#61bd4f"), ("yellow", "#f2d600")]);
+    assert_eq!(board_export["task_tags"].as_array().unwrap().len(), 3);
+
+    let (status, body) = send_json(&app, "POST", "/api/projects/import/trello", Some(json!({ "name": 5 }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "board");
+}
+
+#[tokio::test]
+async fn test_csv_import_respects_wip_limits_and_announces_new_columns() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let app = create_test_router(setup_test_db().await);
+    let (project_id, columns) = create_project_with_columns(&app, "Crowded").await;
+    let import_uri = format!("/api/projects/{}/tasks/import", project_id);
+    send_json(&app, "PUT", &format!("/api/columns/{}", columns[2]), Some(json!({ "wip_limit": 1 }))).await;
+    let csv = "Title,Column\nOne,In Progress\nTwo,In Progress\nThree,In Progress\nFour,Review\n";
+
+    let (status, summary) = send_json(&app, "POST", &import_uri, Some(json!({ "csv": csv }))).await;
+    assert_eq!(status, StatusCode::CREATED, "soft mode still takes the rows");
+    let warnings = summary["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1, "one warning per column");
+    assert_eq!(warnings[0]["column_id"], columns[2]);
+    assert_eq!(warnings[0]["task_count"], 3);
+
+    let (_, activity) = send_json(&app, "GET", &format!("/api/projects/{}/activity", project_id), None).await;
+    let created_columns: Vec<&Value> = activity["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|a| a["entity_type"] == "column" && a["action"] == "create")
+        .collect();
+    assert_eq!(created_columns.len(), 1);
+    assert_eq!(created_columns[0]["after"]["name"], "Review");
+
+    send_json(&app, "PUT", &format!("/api/projects/{}", project_id), Some(json!({ "wip_mode": "hard" }))).await;
+    let (status, body) = send_json(&app, "POST", &import_uri, Some(json!({ "csv": "Title,Column\nFive,In Progress\n" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "wip_limit_exceeded");
+    let (_, tasks) = send_json(&app, "GET", &format!("/api/projects/{}/tasks", project_id), None).await;
+    assert_eq!(tasks["tasks"].as_array().unwrap().len(), 4, "a refused import adds nothing");
+}
+
+#[tokio::test]
+async fn test_csv_import_validates_every_row_and_round_trips() {
+    std::env::set_var("KANBAN_API_KEY", "test-secret-key");
+    let pool = setup_test_db().await;
+    let app = create_test_router(pool);
+    let (project_id, _) = create_project_with_columns(&app, "Spreadsheet").await;
+    let import_uri = format!("/api/projects/{}/tasks/import", project_id);
+
+    // Two bad rows: nothing is written and both are reported with their lines.
+    let bad = "Name,Status,Priority,Due\nShip it,Done,high,2026-03-01\n,To Do,low,\nPolish,To Do,critical,soon\n";
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        &import_uri,
+        Some(json!({ "csv": bad, "mapping": { "Name": "title", "Status": "column", "Due": "due_at" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "invalid_csv");
+    let details: Vec<(u64, &str)> = body["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| (d["line"].as_u64().unwrap(), d["field"].as_str().unwrap()))
+        .collect();
+    assert_eq!(details, vec![(3, "title"), (4, "priority"), (4, "due_at")]);
+    let (_, tasks) = send_json(&app, "GET", &format!("/api/projects/{}/tasks", project_id), None).await;
+    assert_eq!(tasks["tasks"].as_array().unwrap().len(), 0);
+
+    let (status, body) = send_json(
+        &app,
+        "POST",
+        &import_uri,
+        Some(json!({ "csv": "Title,Owner\nOne,ann\n", "mapping": { "Name": "title" } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["details"][0]["field"], "mapping");
+
+    // Case is folded the same way for non-ASCII names.
+    let accented = "Title,Column,Tags\nOne,Überprüfung,Ärger\nTwo,ÜBERPRÜFUNG,ärger\n";
+    let (status, preview) = send_json(&app, "POST", &import_uri, Some(json!({ "csv": accented, "dry_run": true }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(preview["columns_created"], json!(["Überprüfung"]));
+    assert_eq!(preview["tags_created"], json!(["Ärger"]));
+
+    let good = "Name,Status,Priority,Labels,Notes,Owner\n\
+                Ship it,Done,High,\"release, ops\",,ann\n\
+                \"Write docs, again\",Review,,docs,\"multi\nline\",bob\n\
+                Triage,,low,,,\n";
+    let request = json!({
+        "csv": good,
+        "mapping": { "Name": "title", "Status": "column", "Labels": "tags", "Notes": "description", "Owner": "" },
+    });
+    let mut dry_run = request.clone();
+    dry_run["dry_run"] = json!(true);
+    let (status, preview) = send_json(&app, "POST", &import_uri, Some(dry_run)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(preview["tasks"], 3);
+    assert_eq!(preview["columns_created"], json!(["Review"]));
+    assert_eq!(preview["tags_created"], json!(["release", "ops", "docs"]));
+    assert_eq!(preview["mapping"][1], json!({ "header": "Status", "field": "column" }));
+    assert_eq!(preview["mapping"][5], json!({ "header": "Owner", "field": null }));
+    let (_, tasks) = send_json(&app, "GET", &format!("/api/projects/{}/tasks", project_id), None).await;
+    assert_eq!(tasks["tasks"].as_array().unwrap().len(), 0);
+
+    let (status, summary) = send_json(&app, "POST", &import_uri, Some(request)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(summary["tasks"], 3);
+
+    let (_, columns) = send_json(&app, "GET", &format!("/api/projects/{}/columns", project_id), None).await;
+    let review = columns.as_array().unwrap().last().unwrap();
+    assert_eq!(review["name"], "Review");
+    assert_eq!(review["kind"], "in_progress");
+
+    let request = Request::builder()
+        .uri(format!("/api/projects/{}/tasks.csv", project_id))
+        .header("X-API-Key", "test-secret-key")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/csv; charset=utf-8");
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let csv = String::from_utf8(bytes.to_vec()).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "id,title,description,column,priority,tags,subtasks_done,subtasks_total,source_tag,due_at,start_at,created_at,completed_at"
+    );
+    let triage = lines.next().unwrap();
+    assert!(triage.contains(",Triage,,To Do,low,,0,0,csv,"), "{}", triage);
+    let shipped = lines.next().unwrap();
+    assert!(shipped.contains(",Ship it,,Done,high,\"ops, release\",0,0,csv,"), "{}", shipped);
+    assert!(!shipped.ends_with(','), "done tasks carry completed_at: {}", shipped);
+    assert!(csv.contains(",\"Write docs, again\",\"multi\nline\",Review,medium,docs,"));
+
+    // The export reads back in as-is: unknown headers are skipped, the rest match by name.
+    let (status, summary) = send_json(
+        &app,
+        "POST",
+        &import_uri,
+        Some(json!({ "csv": csv, "dry_run": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary["tasks"], 3);
+    assert_eq!(summary["columns_created"], json!([]));
+    assert_eq!(summary["tags_created"], json!([]));
+    assert_eq!(summary["mapping"][0], json!({ "header": "id", "field": null }));
+}
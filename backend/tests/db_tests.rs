@@ -0,0 +1,220 @@
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+use kanban_board::db::{migration_status, revert_last_migration, run_migrations, MIGRATIONS};
+
+/// The schema.sql that shipped before `sort_order` was added to projects.
+const LEGACY_SCHEMA: &str = r#"
+CREATE TABLE projects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+CREATE TABLE columns (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    sort_order INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+CREATE TABLE tasks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL,
+    column_id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    priority TEXT CHECK(priority IN ('urgent', 'high', 'medium', 'low')) DEFAULT 'medium',
+    sort_order INTEGER NOT NULL,
+    source_tag TEXT NOT NULL DEFAULT 'manual',
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+    FOREIGN KEY (column_id) REFERENCES columns(id) ON DELETE CASCADE
+);
+CREATE TABLE subtasks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    done INTEGER NOT NULL DEFAULT 0 CHECK(done IN (0, 1)),
+    sort_order INTEGER NOT NULL,
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+);
+CREATE TABLE tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    color TEXT NOT NULL
+);
+CREATE TABLE task_tags (
+    task_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    PRIMARY KEY (task_id, tag_id),
+    FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+CREATE TABLE linked_paths (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    project_id INTEGER NOT NULL,
+    path TEXT NOT NULL UNIQUE,
+    hostname TEXT,
+    default_column_id INTEGER,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE,
+    FOREIGN KEY (default_column_id) REFERENCES columns(id) ON DELETE SET NULL
+);
+
+INSERT INTO projects (name) VALUES ('Legacy'), ('Older');
+INSERT INTO columns (project_id, name, sort_order) VALUES (1, 'Todo', 1000), (1, 'Done', 2000);
+INSERT INTO tasks (project_id, column_id, title, description, sort_order)
+VALUES (1, 1, 'Migrate the database', 'Keep every existing task', 1000);
+INSERT INTO subtasks (task_id, title, sort_order) VALUES (1, 'Write rollback notes', 1000);
+"#;
+
+async fn memory_pool() -> SqlitePool {
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect(":memory:")
+        .await
+        .expect("Failed to create in-memory database")
+}
+
+fn latest_version() -> i64 {
+    MIGRATIONS.last().unwrap().version
+}
+
+#[tokio::test]
+async fn test_fresh_database_applies_all_migrations() {
+    let pool = memory_pool().await;
+
+    let applied = run_migrations(&pool).await.unwrap();
+    assert_eq!(applied, MIGRATIONS.iter().map(|m| m.version).collect::<Vec<_>>());
+
+    let applied_again = run_migrations(&pool).await.unwrap();
+    assert!(applied_again.is_empty());
+
+    let status = migration_status(&pool).await.unwrap();
+    assert!(status.iter().all(|s| s.applied_at.is_some()));
+}
+
+#[tokio::test]
+async fn test_legacy_database_is_brought_up_to_date() {
+    let pool = memory_pool().await;
+    sqlx::raw_sql(LEGACY_SCHEMA).execute(&pool).await.unwrap();
+
+    let applied = run_migrations(&pool).await.unwrap();
+    assert_eq!(applied.first(), Some(&2), "only the initial schema is adopted");
+    assert_eq!(applied.last(), Some(&latest_version()));
+
+    let sort_orders: Vec<(String, i64)> = sqlx::query_as("SELECT name, sort_order FROM projects ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(sort_orders, vec![("Legacy".to_string(), 1000), ("Older".to_string(), 2000)]);
+
+    let matches: Vec<i64> = sqlx::query_scalar("SELECT rowid FROM tasks_fts WHERE tasks_fts MATCH 'rollback'")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(matches, vec![1], "existing tasks are indexed for search");
+
+    let activity_exists: bool =
+        sqlx::query_scalar("SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'activity'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(activity_exists);
+
+    let kinds: Vec<String> = sqlx::query_scalar("SELECT kind FROM columns ORDER BY sort_order")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(kinds, ["todo", "done"], "existing boards get a done column");
+}
+
+#[tokio::test]
+async fn test_legacy_database_with_sort_order_skips_that_migration() {
+    let pool = memory_pool().await;
+    sqlx::raw_sql(LEGACY_SCHEMA).execute(&pool).await.unwrap();
+    sqlx::raw_sql("ALTER TABLE projects ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0; UPDATE projects SET sort_order = 7;")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let applied = run_migrations(&pool).await.unwrap();
+    assert_eq!(applied.first(), Some(&3));
+
+    let sort_order: i64 = sqlx::query_scalar("SELECT sort_order FROM projects WHERE id = 1")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(sort_order, 7, "existing ordering is left alone");
+}
+
+#[tokio::test]
+async fn test_column_kinds_ignore_the_case_of_column_names() {
+    let pool = memory_pool().await;
+    sqlx::raw_sql(LEGACY_SCHEMA).execute(&pool).await.unwrap();
+    sqlx::raw_sql(
+        "INSERT INTO columns (project_id, name, sort_order)
+         VALUES (2, 'backlog', 1000), (2, 'in progress', 2000), (2, 'TESTING', 3000), (2, 'done', 4000), (2, 'archive', 5000);",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    run_migrations(&pool).await.unwrap();
+
+    let kinds: Vec<String> = sqlx::query_scalar("SELECT kind FROM columns WHERE project_id = 2 ORDER BY sort_order")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(kinds, ["backlog", "in_progress", "in_progress", "done", "todo"]);
+}
+
+#[tokio::test]
+async fn test_completed_at_backfill_uses_the_move_into_done() {
+    let pool = memory_pool().await;
+    run_migrations(&pool).await.unwrap();
+    while revert_last_migration(&pool).await.unwrap() != Some(16) {}
+
+    sqlx::raw_sql(
+        r#"INSERT INTO projects (name) VALUES ('Shipped');
+           INSERT INTO columns (id, project_id, name, sort_order) VALUES (1, 1, 'Doing', 1000), (2, 1, 'Done', 2000);
+           INSERT INTO tasks (id, project_id, column_id, title, sort_order, created_at)
+           VALUES (1, 1, 2, 'Release', 1000, '2026-01-01 09:00:00');
+           INSERT INTO activity (project_id, task_id, actor, action, entity_type, entity_id, after_json, created_at)
+           VALUES (1, 1, 'ann', 'move', 'task', 1, '{"column_id": 2}', '2026-01-05 09:00:00'),
+                  (1, 1, 'ann', 'move', 'task', 1, '{"column_id": 1}', '2026-01-07 09:00:00');"#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    run_migrations(&pool).await.unwrap();
+
+    let completed_at: Option<String> = sqlx::query_scalar("SELECT completed_at FROM tasks WHERE id = 1")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(completed_at.as_deref(), Some("2026-01-05 09:00:00"), "moves between other columns don't count");
+}
+
+#[tokio::test]
+async fn test_down_then_up_round_trips() {
+    let pool = memory_pool().await;
+    run_migrations(&pool).await.unwrap();
+
+    for expected in MIGRATIONS.iter().rev() {
+        assert_eq!(revert_last_migration(&pool).await.unwrap(), Some(expected.version));
+    }
+    assert_eq!(revert_last_migration(&pool).await.unwrap(), None);
+
+    let tables: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT IN ('schema_migrations', 'sqlite_sequence')",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(tables, 0);
+
+    let applied = run_migrations(&pool).await.unwrap();
+    assert_eq!(applied.len(), MIGRATIONS.len());
+}
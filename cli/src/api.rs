@@ -1,5 +1,6 @@
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use crate::config::load_global_config;
 
@@ -14,6 +15,11 @@ pub struct Column {
     pub id: i64,
     pub name: String,
     pub sort_order: i64,
+    #[serde(default)]
+    pub wip_limit: Option<i64>,
+    /// `backlog`, `todo`, `in_progress`, `done` or `archived`.
+    #[serde(default)]
+    pub kind: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,6 +28,120 @@ pub struct Task {
     pub title: String,
     pub column_id: i64,
     pub description: Option<String>,
+    pub priority: Option<String>,
+    #[serde(default)]
+    pub due_at: Option<String>,
+    /// Set when creating or moving the task pushed a column past its WIP limit.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+    /// Only filled in by [`ApiClient::get_task`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subtasks: Vec<Subtask>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Subtask {
+    pub id: i64,
+    pub title: String,
+    pub done: bool,
+}
+
+/// A project with its columns and their tasks, in board order.
+#[derive(Debug, Deserialize)]
+pub struct Board {
+    pub name: String,
+    pub columns: Vec<BoardColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BoardColumn {
+    pub id: i64,
+    pub name: String,
+    pub kind: String,
+    pub tasks: Vec<BoardTask>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BoardTask {
+    pub id: i64,
+    pub title: String,
+    /// Absent for tasks with neither subtasks nor child tasks.
+    #[serde(default)]
+    pub progress: Option<Progress>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Progress {
+    pub subtasks_total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Warning {
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskPage {
+    tasks: Vec<Task>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+}
+
+/// Server-side filters for `GET /api/projects/{id}/tasks`.
+#[derive(Debug, Default)]
+pub struct TaskFilters {
+    pub column_id: Option<i64>,
+    pub priority: Option<String>,
+    pub tag_ids: Vec<i64>,
+    pub source_tag: Option<String>,
+    pub query: Option<String>,
+    pub order_by: Option<String>,
+}
+
+impl TaskFilters {
+    fn to_query_string(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(id) = self.column_id {
+            params.push(format!("column_id={}", id));
+        }
+        if let Some(priority) = &self.priority {
+            params.push(format!("priority={}", urlencoding::encode(priority)));
+        }
+        if !self.tag_ids.is_empty() {
+            let ids: Vec<String> = self.tag_ids.iter().map(|id| id.to_string()).collect();
+            params.push(format!("tag_ids={}", ids.join(",")));
+        }
+        if let Some(source_tag) = &self.source_tag {
+            params.push(format!("source_tag={}", urlencoding::encode(source_tag)));
+        }
+        if let Some(query) = &self.query {
+            params.push(format!("q={}", urlencoding::encode(query)));
+        }
+        if let Some(order_by) = &self.order_by {
+            params.push(format!("order_by={}", urlencoding::encode(order_by)));
+        }
+
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", params.join("&"))
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub task_id: i64,
+    pub project_id: i64,
+    pub project_name: String,
+    pub column_name: String,
+    pub title_highlight: String,
+    pub snippet: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,6 +159,127 @@ pub struct LinkedPathLookup {
     pub project_name: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: i64,
+    pub author: String,
+    pub body: String,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: i64,
+    pub filename: String,
+    pub size: i64,
+}
+
+/// What `POST /api/projects/import` created.
+#[derive(Debug, Deserialize)]
+pub struct ImportSummary {
+    pub project: Project,
+    pub tasks: usize,
+    pub tags_created: usize,
+    #[serde(default)]
+    pub skipped_linked_paths: Vec<String>,
+}
+
+/// A Trello field that held data but has no place on the board.
+#[derive(Debug, Deserialize)]
+pub struct UnmappedField {
+    pub field: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrelloImportSummary {
+    #[serde(flatten)]
+    pub summary: ImportSummary,
+    #[serde(default)]
+    pub unmapped: Vec<UnmappedField>,
+}
+
+/// How one header of an imported CSV file was read; `field` is `None` when skipped.
+#[derive(Debug, Deserialize)]
+pub struct HeaderMapping {
+    pub header: String,
+    pub field: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CsvImportSummary {
+    pub mapping: Vec<HeaderMapping>,
+    pub tasks: usize,
+    pub columns_created: Vec<String>,
+    pub tags_created: Vec<String>,
+    /// Columns the import pushed past their WIP limit.
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
+}
+
+/// Lead and cycle time percentiles, in hours; `None` when nothing was completed.
+#[derive(Debug, Deserialize)]
+pub struct DurationStats {
+    pub count: usize,
+    pub p50_hours: Option<f64>,
+    pub p85_hours: Option<f64>,
+    pub p95_hours: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WeeklyThroughput {
+    pub week_start: String,
+    pub completed: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ColumnTime {
+    pub column_id: i64,
+    pub column_name: String,
+    pub hours: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskFlow {
+    pub title: String,
+    pub column_id: i64,
+    pub completed_at: Option<String>,
+    pub time_in_column: Vec<ColumnTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProjectMetrics {
+    pub since: String,
+    pub completed: usize,
+    pub lead_time: DurationStats,
+    pub cycle_time: DurationStats,
+    pub throughput: Vec<WeeklyThroughput>,
+    pub tasks: Vec<TaskFlow>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    pub prefix: String,
+    #[serde(default)]
+    pub scopes: Option<Vec<String>>,
+    #[serde(default)]
+    pub project_ids: Option<Vec<i64>>,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+}
+
+/// A new token; `token` is the secret and the server never shows it again.
+#[derive(Debug, Deserialize)]
+pub struct CreatedToken {
+    #[serde(flatten)]
+    pub info: ApiToken,
+    pub token: String,
+}
+
 #[derive(Debug, Serialize)]
 struct CreateTaskPayload {
     column_id: i64,
@@ -47,6 +288,10 @@ struct CreateTaskPayload {
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     source_tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_id: Option<i64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -56,11 +301,69 @@ struct CreateLinkedPathPayload {
     default_column_id: Option<i64>,
 }
 
+#[derive(Debug, Serialize)]
+struct CreateTokenPayload {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scopes: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_ids: Option<Vec<i64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 struct DeleteByPathPayload {
     path: String,
 }
 
+/// The server answers every failure with `{"error": "<message>", "code": "<code>", "details": [...]}`.
+#[derive(Debug, Deserialize)]
+struct ErrorBody {
+    error: String,
+    code: Option<String>,
+    #[serde(default)]
+    details: Vec<ErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    line: Option<u64>,
+    field: String,
+    message: String,
+}
+
+/// Passes successful responses through and turns the rest into an error carrying
+/// the server's message.
+fn check_status(response: reqwest::blocking::Response) -> Result<reqwest::blocking::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().unwrap_or_default();
+    match serde_json::from_str::<ErrorBody>(&body) {
+        // Server-side failures keep the status so they read differently from user mistakes.
+        Ok(parsed) if parsed.code.as_deref() == Some("internal_error") => {
+            bail!("{} ({})", parsed.error, status)
+        }
+        // Imports report problems by line; list them all so the file can be fixed in one go.
+        Ok(parsed) if parsed.details.iter().any(|d| d.line.is_some()) => {
+            let mut message = parsed.error;
+            for detail in parsed.details {
+                match detail.line {
+                    Some(line) => message.push_str(&format!("\n  line {}: {} {}", line, detail.field, detail.message)),
+                    None => message.push_str(&format!("\n  {} {}", detail.field, detail.message)),
+                }
+            }
+            bail!("{}", message)
+        }
+        Ok(parsed) => bail!("{}", parsed.error),
+        Err(_) if body.trim().is_empty() => bail!("API error: {}", status),
+        Err(_) => bail!("API error: {} - {}", status, body.trim()),
+    }
+}
+
 pub struct ApiClient {
     client: reqwest::blocking::Client,
     base_url: String,
@@ -77,8 +380,22 @@ impl ApiClient {
             .api_key
             .context("API key not configured. Run: rk init <url> <api-key>")?;
 
+        let hostname = hostname::get()
+            .ok()
+            .and_then(|h| h.into_string().ok())
+            .unwrap_or_else(|| "unknown".to_string());
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Ok(actor) = reqwest::header::HeaderValue::from_str(&format!("rk@{}", hostname)) {
+            headers.insert("X-Actor", actor);
+        }
+
+        let client = reqwest::blocking::Client::builder()
+            .default_headers(headers)
+            .build()
+            .context("Failed to build HTTP client")?;
+
         Ok(Self {
-            client: reqwest::blocking::Client::new(),
+            client,
             base_url,
             api_key,
         })
@@ -93,14 +410,27 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() {
-            bail!("API error: {}", response.status());
-        }
+        let response = check_status(response)?;
 
         let projects: Vec<Project> = response.json()?;
         Ok(projects)
     }
 
+    pub fn get_board(&self, project_id: i64) -> Result<Board> {
+        let url = format!("{}/api/projects/{}", self.base_url, project_id);
+        let response = self
+            .client
+            .get(&url)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let board: Board = response.json()?;
+        Ok(board)
+    }
+
     pub fn get_project_columns(&self, project_id: i64) -> Result<Vec<Column>> {
         let url = format!("{}/api/projects/{}/columns", self.base_url, project_id);
         let response = self
@@ -110,9 +440,7 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() {
-            bail!("API error: {}", response.status());
-        }
+        let response = check_status(response)?;
 
         let columns: Vec<Column> = response.json()?;
         Ok(columns)
@@ -133,6 +461,8 @@ impl ApiClient {
             title: params.title,
             description: params.description,
             source_tag: params.source_tag,
+            due_at: params.due_at,
+            parent_id: params.parent_id,
         };
 
         let response = self
@@ -143,11 +473,7 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().unwrap_or_default();
-            bail!("API error: {} - {}", status, body);
-        }
+        let response = check_status(response)?;
 
         let task: Task = response.json()?;
         Ok(task)
@@ -172,11 +498,7 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().unwrap_or_default();
-            bail!("API error: {} - {}", status, body);
-        }
+        let response = check_status(response)?;
 
         let linked_path: LinkedPath = response.json()?;
         Ok(linked_path)
@@ -196,11 +518,7 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() && response.status() != reqwest::StatusCode::NO_CONTENT {
-            let status = response.status();
-            let body = response.text().unwrap_or_default();
-            bail!("API error: {} - {}", status, body);
-        }
+        check_status(response)?;
 
         Ok(())
     }
@@ -227,18 +545,23 @@ impl ApiClient {
             return Ok(None);
         }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().unwrap_or_default();
-            bail!("API error: {} - {}", status, body);
-        }
+        let response = check_status(response)?;
 
         let lookup: LinkedPathLookup = response.json()?;
         Ok(Some(lookup))
     }
 
     pub fn list_tasks(&self, project_id: i64) -> Result<Vec<Task>> {
-        let url = format!("{}/api/projects/{}/tasks", self.base_url, project_id);
+        self.list_tasks_filtered(project_id, &TaskFilters::default())
+    }
+
+    pub fn list_tasks_filtered(&self, project_id: i64, filters: &TaskFilters) -> Result<Vec<Task>> {
+        let url = format!(
+            "{}/api/projects/{}/tasks{}",
+            self.base_url,
+            project_id,
+            filters.to_query_string()
+        );
         let response = self
             .client
             .get(&url)
@@ -246,14 +569,25 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().unwrap_or_default();
-            bail!("API error: {} - {}", status, body);
-        }
+        let response = check_status(response)?;
 
-        let tasks: Vec<Task> = response.json()?;
-        Ok(tasks)
+        let page: TaskPage = response.json()?;
+        Ok(page.tasks)
+    }
+
+    pub fn list_tags(&self) -> Result<Vec<Tag>> {
+        let url = format!("{}/api/tags", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let tags: Vec<Tag> = response.json()?;
+        Ok(tags)
     }
 
     pub fn delete_task(&self, task_id: i64) -> Result<()> {
@@ -265,11 +599,7 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() && response.status() != reqwest::StatusCode::NO_CONTENT {
-            let status = response.status();
-            let body = response.text().unwrap_or_default();
-            bail!("API error: {} - {}", status, body);
-        }
+        check_status(response)?;
 
         Ok(())
     }
@@ -284,11 +614,7 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().unwrap_or_default();
-            bail!("API error: {} - {}", status, body);
-        }
+        let response = check_status(response)?;
 
         let task: Task = response.json()?;
         Ok(task)
@@ -304,11 +630,7 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().unwrap_or_default();
-            bail!("API error: {} - {}", status, body);
-        }
+        let response = check_status(response)?;
 
         let task: Task = response.json()?;
         Ok(task)
@@ -323,15 +645,282 @@ impl ApiClient {
             .send()
             .context("Failed to connect to API")?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().unwrap_or_default();
-            bail!("API error: {} - {}", status, body);
-        }
+        let response = check_status(response)?;
 
         let task: Task = response.json()?;
         Ok(task)
     }
+
+    pub fn create_subtask(&self, task_id: i64, title: &str) -> Result<Subtask> {
+        let url = format!("{}/api/tasks/{}/subtasks", self.base_url, task_id);
+        let response = self
+            .client
+            .post(&url)
+            .header("X-API-Key", &self.api_key)
+            .json(&serde_json::json!({ "title": title }))
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let subtask: Subtask = response.json()?;
+        Ok(subtask)
+    }
+
+    pub fn set_subtask_done(&self, subtask_id: i64, done: bool) -> Result<Subtask> {
+        let url = format!("{}/api/subtasks/{}", self.base_url, subtask_id);
+        let response = self
+            .client
+            .put(&url)
+            .header("X-API-Key", &self.api_key)
+            .json(&serde_json::json!({ "done": done }))
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let subtask: Subtask = response.json()?;
+        Ok(subtask)
+    }
+
+    pub fn search(&self, query: &str, project_id: Option<i64>) -> Result<Vec<SearchResult>> {
+        let mut url = format!(
+            "{}/api/search?q={}",
+            self.base_url,
+            urlencoding::encode(query)
+        );
+        if let Some(id) = project_id {
+            url.push_str(&format!("&project_id={}", id));
+        }
+
+        let response = self
+            .client
+            .get(&url)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let results: Vec<SearchResult> = response.json()?;
+        Ok(results)
+    }
+
+    /// The board as a versioned JSON document, kept as-is so nothing is lost on the way
+    /// to `import_project`.
+    pub fn export_project(&self, project_id: i64) -> Result<serde_json::Value> {
+        let url = format!("{}/api/projects/{}/export", self.base_url, project_id);
+        let response = self
+            .client
+            .get(&url)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let document: serde_json::Value = response.json()?;
+        Ok(document)
+    }
+
+    pub fn import_project(&self, document: &serde_json::Value, name: Option<&str>) -> Result<ImportSummary> {
+        let mut url = format!("{}/api/projects/import", self.base_url);
+        if let Some(name) = name {
+            url.push_str(&format!("?name={}", urlencoding::encode(name)));
+        }
+        let response = self
+            .client
+            .post(&url)
+            .header("X-API-Key", &self.api_key)
+            .json(document)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let summary: ImportSummary = response.json()?;
+        Ok(summary)
+    }
+
+    pub fn export_tasks_csv(&self, project_id: i64) -> Result<String> {
+        let url = format!("{}/api/projects/{}/tasks.csv", self.base_url, project_id);
+        let response = self
+            .client
+            .get(&url)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        Ok(response.text()?)
+    }
+
+    /// Adds the rows of a CSV file to a project as tasks. `mapping` pairs file headers
+    /// with task fields; with `dry_run` nothing is written.
+    pub fn import_tasks_csv(
+        &self,
+        project_id: i64,
+        csv: &str,
+        mapping: &HashMap<String, String>,
+        dry_run: bool,
+    ) -> Result<CsvImportSummary> {
+        let url = format!("{}/api/projects/{}/tasks/import", self.base_url, project_id);
+        let response = self
+            .client
+            .post(&url)
+            .header("X-API-Key", &self.api_key)
+            .json(&serde_json::json!({ "csv": csv, "mapping": mapping, "dry_run": dry_run }))
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let summary: CsvImportSummary = response.json()?;
+        Ok(summary)
+    }
+
+    /// Imports a Trello board's JSON export as a new project.
+    pub fn import_trello(&self, board: &serde_json::Value, name: Option<&str>) -> Result<TrelloImportSummary> {
+        let mut url = format!("{}/api/projects/import/trello", self.base_url);
+        if let Some(name) = name {
+            url.push_str(&format!("?name={}", urlencoding::encode(name)));
+        }
+        let response = self
+            .client
+            .post(&url)
+            .header("X-API-Key", &self.api_key)
+            .json(board)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let summary: TrelloImportSummary = response.json()?;
+        Ok(summary)
+    }
+
+    pub fn get_project_metrics(&self, project_id: i64, weeks: Option<u32>) -> Result<ProjectMetrics> {
+        let mut url = format!("{}/api/projects/{}/metrics", self.base_url, project_id);
+        if let Some(weeks) = weeks {
+            url.push_str(&format!("?weeks={}", weeks));
+        }
+        let response = self
+            .client
+            .get(&url)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let metrics: ProjectMetrics = response.json()?;
+        Ok(metrics)
+    }
+
+    pub fn list_comments(&self, task_id: i64) -> Result<Vec<Comment>> {
+        let url = format!("{}/api/tasks/{}/comments", self.base_url, task_id);
+        let response = self
+            .client
+            .get(&url)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let comments: Vec<Comment> = response.json()?;
+        Ok(comments)
+    }
+
+    pub fn create_comment(&self, task_id: i64, body: &str) -> Result<Comment> {
+        let url = format!("{}/api/tasks/{}/comments", self.base_url, task_id);
+        let response = self
+            .client
+            .post(&url)
+            .header("X-API-Key", &self.api_key)
+            .json(&serde_json::json!({ "body": body }))
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let comment: Comment = response.json()?;
+        Ok(comment)
+    }
+
+    pub fn upload_attachment(&self, task_id: i64, path: &std::path::Path) -> Result<Attachment> {
+        let url = format!("{}/api/tasks/{}/attachments", self.base_url, task_id);
+        let form = reqwest::blocking::multipart::Form::new()
+            .file("file", path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let response = self
+            .client
+            .post(&url)
+            .header("X-API-Key", &self.api_key)
+            .multipart(form)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let attachment: Attachment = response.json()?;
+        Ok(attachment)
+    }
+
+    /// Tokens belonging to the caller.
+    pub fn list_tokens(&self) -> Result<Vec<ApiToken>> {
+        let url = format!("{}/api/tokens", self.base_url);
+        let response = self
+            .client
+            .get(&url)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let tokens: Vec<ApiToken> = response.json()?;
+        Ok(tokens)
+    }
+
+    pub fn create_token(&self, params: CreateTokenParams) -> Result<CreatedToken> {
+        let url = format!("{}/api/tokens", self.base_url);
+        let payload = CreateTokenPayload {
+            name: params.name,
+            scopes: (!params.scopes.is_empty()).then_some(params.scopes),
+            project_ids: (!params.project_ids.is_empty()).then_some(params.project_ids),
+            expires_at: params.expires_at,
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .header("X-API-Key", &self.api_key)
+            .json(&payload)
+            .send()
+            .context("Failed to connect to API")?;
+
+        let response = check_status(response)?;
+
+        let created: CreatedToken = response.json()?;
+        Ok(created)
+    }
+
+    pub fn revoke_token(&self, token_id: i64) -> Result<()> {
+        let url = format!("{}/api/tokens/{}", self.base_url, token_id);
+        let response = self
+            .client
+            .delete(&url)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .context("Failed to connect to API")?;
+
+        check_status(response)?;
+
+        Ok(())
+    }
 }
 
 pub struct CreateLinkedPathParams {
@@ -347,4 +936,14 @@ pub struct CreateTaskParams {
     pub title: String,
     pub description: Option<String>,
     pub source_tag: Option<String>,
+    pub due_at: Option<String>,
+    pub parent_id: Option<i64>,
+}
+
+/// Empty `scopes` or `project_ids` leave the token unrestricted in that respect.
+pub struct CreateTokenParams {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub project_ids: Vec<i64>,
+    pub expires_at: Option<String>,
 }
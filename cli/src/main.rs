@@ -1,11 +1,15 @@
 mod api;
 mod config;
+mod markdown;
+mod scan;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
+use std::io::IsTerminal;
 
-use api::{ApiClient, CreateLinkedPathParams, CreateTaskParams};
+use api::{ApiClient, CreateLinkedPathParams, CreateTaskParams, CreateTokenParams, TaskFilters};
 use config::{load_global_config, save_global_config, GlobalConfig};
+use markdown::Change;
 
 #[derive(Parser)]
 #[command(name = "rk")]
@@ -50,7 +54,20 @@ enum Commands {
     Columns,
 
     #[command(about = "List tasks for the linked project")]
-    Tasks,
+    Tasks {
+        #[arg(short, long, help = "Only tasks in this column (name or ID)")]
+        column: Option<String>,
+        #[arg(short, long, help = "Only these priorities, comma-separated (e.g., 'urgent,high')")]
+        priority: Option<String>,
+        #[arg(short, long = "tag", help = "Only tasks with this tag name (repeatable)")]
+        tags: Vec<String>,
+        #[arg(long, help = "Only tasks with this source tag (e.g., 'manual')")]
+        source: Option<String>,
+        #[arg(short, long, help = "Only tasks matching this text")]
+        search: Option<String>,
+        #[arg(long, help = "Sort by sort_order, created_at, priority or title (prefix '-' to reverse)")]
+        sort: Option<String>,
+    },
 
     #[command(about = "Show current directory's linked project")]
     Status,
@@ -65,6 +82,10 @@ enum Commands {
         description: Option<String>,
         #[arg(short, long, help = "Source tag (default: 'manual')")]
         tag: Option<String>,
+        #[arg(long, help = "Due date: YYYY-MM-DD, an ISO-8601 timestamp, 'today', 'tomorrow' or +Nd/+Nw/+Nh")]
+        due: Option<String>,
+        #[arg(short, long, help = "Parent task title or ID, to file the task under an epic")]
+        parent: Option<String>,
     },
 
     #[command(about = "Remove a task by title")]
@@ -81,12 +102,20 @@ enum Commands {
         column: String,
     },
 
-    #[command(about = "Mark a task as done (move to last column)")]
+    #[command(about = "Mark a task as done (move to the project's done column)")]
     Done {
         #[arg(help = "Task title to mark as done")]
         title: String,
     },
 
+    #[command(about = "Search tasks by title, description and subtasks")]
+    Search {
+        #[arg(help = "Words to search for")]
+        query: String,
+        #[arg(short, long, help = "Search all projects, not just the linked one")]
+        all: bool,
+    },
+
     #[command(about = "Append text to a task's description")]
     Describe {
         #[arg(help = "Task title")]
@@ -94,6 +123,92 @@ enum Commands {
         #[arg(help = "Text to append to description")]
         text: String,
     },
+
+    #[command(about = "Comment on a task, or show its comments")]
+    Comment {
+        #[arg(help = "Task title")]
+        title: String,
+        #[arg(help = "Comment text (markdown); omit to show the thread")]
+        text: Option<String>,
+    },
+
+    #[command(about = "Attach a file to a task")]
+    Attach {
+        #[arg(help = "Task title")]
+        title: String,
+        #[arg(help = "File to upload (e.g., a log or screenshot)")]
+        file: std::path::PathBuf,
+    },
+
+    #[command(about = "Show lead time, cycle time and throughput for the linked project")]
+    Stats {
+        #[arg(short, long, help = "Weeks of history to cover, including this one (default: 12)")]
+        weeks: Option<u32>,
+    },
+
+    #[command(about = "Export the linked project as a JSON document, its tasks as CSV, or a markdown checklist")]
+    Export {
+        #[arg(short, long, help = "File to write (default: standard output)")]
+        output: Option<std::path::PathBuf>,
+        #[arg(short, long, default_value = "json", value_parser = ["json", "csv", "markdown"], help = "'json' for the whole board, 'csv' for a task list, 'markdown' for a checklist per column")]
+        format: String,
+    },
+
+    #[command(about = "Turn TODO, FIXME and HACK comments in the linked directory into tasks")]
+    Scan {
+        #[arg(long, help = "Show what would change without touching the board")]
+        dry_run: bool,
+    },
+
+    #[command(about = "Sync a markdown checklist with the linked project: apply its edits, then rewrite it")]
+    Sync {
+        #[arg(default_value = "TODO.md", help = "Markdown file to sync")]
+        file: std::path::PathBuf,
+    },
+
+    #[command(about = "Create a project from an exported board or Trello export, or add tasks from CSV")]
+    Import {
+        #[arg(help = "File to import")]
+        file: std::path::PathBuf,
+        #[arg(short, long, help = "Project name to use instead of the one in the file")]
+        name: Option<String>,
+        #[arg(short, long, default_value = "kanban", value_parser = ["kanban", "trello", "csv"], help = "What the file is: 'kanban' (rk export), 'trello', or 'csv' (tasks for the linked project)")]
+        format: String,
+        #[arg(short, long = "map", value_name = "HEADER=FIELD", help = "Read a CSV header as a task field; an empty FIELD skips it (repeatable)")]
+        mappings: Vec<String>,
+        #[arg(long, help = "Check a CSV file and show what it would create, without importing")]
+        dry_run: bool,
+    },
+
+    #[command(about = "Manage API tokens for scripts and CI")]
+    Token {
+        #[command(subcommand)]
+        action: TokenCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum TokenCommands {
+    #[command(about = "Create a token, optionally limited to scopes and projects")]
+    Create {
+        #[arg(short, long, help = "Token name shown in listings and the activity log", default_value = "rk")]
+        name: String,
+        #[arg(short, long = "scope", help = "projects:read, projects:write, tasks:write or admin (repeatable)")]
+        scopes: Vec<String>,
+        #[arg(short, long = "project", help = "Only allow this project ID (repeatable)")]
+        projects: Vec<i64>,
+        #[arg(long, help = "Expiry: YYYY-MM-DD, an ISO-8601 timestamp or +Nd/+Nw/+Nh")]
+        expires: Option<String>,
+    },
+
+    #[command(about = "List your active tokens")]
+    List,
+
+    #[command(about = "Revoke a token by ID")]
+    Revoke {
+        #[arg(help = "Token ID")]
+        id: i64,
+    },
 }
 
 fn main() -> Result<()> {
@@ -111,18 +226,51 @@ fn main() -> Result<()> {
         Commands::Unlink => cmd_unlink(),
         Commands::Projects => cmd_projects(),
         Commands::Columns => cmd_columns(),
-        Commands::Tasks => cmd_tasks(),
+        Commands::Tasks {
+            column,
+            priority,
+            tags,
+            source,
+            search,
+            sort,
+        } => cmd_tasks(column, priority, tags, source, search, sort),
         Commands::Status => cmd_status(),
         Commands::Add {
             title,
             column,
             description,
             tag,
-        } => cmd_add(title, column, description, tag),
+            due,
+            parent,
+        } => cmd_add(title, column, description, tag, due, parent),
         Commands::Remove { title } => cmd_remove(title),
         Commands::Move { title, column } => cmd_move(title, column),
         Commands::Done { title } => cmd_done(title),
         Commands::Describe { title, text } => cmd_describe(title, text),
+        Commands::Search { query, all } => cmd_search(query, all),
+        Commands::Comment { title, text } => cmd_comment(title, text),
+        Commands::Attach { title, file } => cmd_attach(title, file),
+        Commands::Stats { weeks } => cmd_stats(weeks),
+        Commands::Export { output, format } => cmd_export(output, &format),
+        Commands::Scan { dry_run } => cmd_scan(dry_run),
+        Commands::Sync { file } => cmd_sync(file),
+        Commands::Import {
+            file,
+            name,
+            format,
+            mappings,
+            dry_run,
+        } => cmd_import(file, name, &format, &mappings, dry_run),
+        Commands::Token { action } => match action {
+            TokenCommands::Create {
+                name,
+                scopes,
+                projects,
+                expires,
+            } => cmd_token_create(name, scopes, projects, expires),
+            TokenCommands::List => cmd_token_list(),
+            TokenCommands::Revoke { id } => cmd_token_revoke(id),
+        },
     }
 }
 
@@ -215,6 +363,582 @@ fn cmd_describe(title: String, text: String) -> Result<()> {
     Ok(())
 }
 
+fn cmd_comment(title: String, text: Option<String>) -> Result<()> {
+    let client = ApiClient::new()?;
+    let current_dir = std::env::current_dir()?;
+    let path = current_dir.to_string_lossy().to_string();
+
+    let lookup = client.lookup_linked_path(&path)?.ok_or_else(|| {
+        anyhow::anyhow!("Current directory is not linked. Run: rk link <project-id>")
+    })?;
+
+    let tasks = client.list_tasks(lookup.linked_path.project_id)?;
+    let task = find_task_by_title(&tasks, &title)?;
+
+    if let Some(text) = text {
+        client.create_comment(task.id, &text)?;
+        println!("Commented on task '{}'", task.title);
+        return Ok(());
+    }
+
+    let comments = client.list_comments(task.id)?;
+    if comments.is_empty() {
+        println!("No comments on '{}'.", task.title);
+        return Ok(());
+    }
+
+    for comment in comments {
+        let edited = if comment.updated_at.is_some() { " (edited)" } else { "" };
+        println!("{} - {}{}", comment.author, comment.created_at, edited);
+        for line in comment.body.lines() {
+            println!("  {}", line);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn cmd_attach(title: String, file: std::path::PathBuf) -> Result<()> {
+    if !file.is_file() {
+        anyhow::bail!("{} is not a file", file.display());
+    }
+
+    let client = ApiClient::new()?;
+    let current_dir = std::env::current_dir()?;
+    let path = current_dir.to_string_lossy().to_string();
+
+    let lookup = client.lookup_linked_path(&path)?.ok_or_else(|| {
+        anyhow::anyhow!("Current directory is not linked. Run: rk link <project-id>")
+    })?;
+
+    let tasks = client.list_tasks(lookup.linked_path.project_id)?;
+    let task = find_task_by_title(&tasks, &title)?;
+
+    let attachment = client.upload_attachment(task.id, &file)?;
+    println!(
+        "Attached '{}' ({}) to task '{}'",
+        attachment.filename,
+        format_size(attachment.size),
+        task.title
+    );
+    Ok(())
+}
+
+fn format_size(bytes: i64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{} bytes", b),
+    }
+}
+
+fn cmd_stats(weeks: Option<u32>) -> Result<()> {
+    let client = ApiClient::new()?;
+    let current_dir = std::env::current_dir()?;
+    let path = current_dir.to_string_lossy().to_string();
+
+    let lookup = client.lookup_linked_path(&path)?.ok_or_else(|| {
+        anyhow::anyhow!("Current directory is not linked. Run: rk link <project-id>")
+    })?;
+
+    let metrics = client.get_project_metrics(lookup.linked_path.project_id, weeks)?;
+    let since = metrics.since.split(' ').next().unwrap_or(&metrics.since);
+    println!("{} since {}", lookup.project_name, since);
+    println!("  Completed:   {}", metrics.completed);
+    println!("  Lead time:   {}", format_percentiles(&metrics.lead_time));
+    println!("  Cycle time:  {}", format_percentiles(&metrics.cycle_time));
+
+    println!();
+    println!("Throughput per week:");
+    for week in &metrics.throughput {
+        println!("  {}  {:>3} {}", week.week_start, week.completed, "#".repeat(week.completed.min(40)));
+    }
+
+    let mut waiting: Vec<(&str, &str, f64)> = metrics
+        .tasks
+        .iter()
+        .filter(|t| t.completed_at.is_none())
+        .filter_map(|t| {
+            let current = t.time_in_column.iter().find(|c| c.column_id == t.column_id)?;
+            Some((t.title.as_str(), current.column_name.as_str(), current.hours))
+        })
+        .collect();
+    waiting.sort_by(|a, b| b.2.total_cmp(&a.2));
+    if !waiting.is_empty() {
+        println!();
+        println!("Waiting longest:");
+        for (title, column, hours) in waiting.iter().take(5) {
+            println!("  {:>6}  {} ({})", format_hours(*hours), title, column);
+        }
+    }
+    Ok(())
+}
+
+/// Hours under two days, days beyond that.
+fn format_hours(hours: f64) -> String {
+    if hours < 48.0 {
+        format!("{:.0}h", hours)
+    } else {
+        format!("{:.1}d", hours / 24.0)
+    }
+}
+
+fn format_percentiles(stats: &api::DurationStats) -> String {
+    match (stats.p50_hours, stats.p85_hours, stats.p95_hours) {
+        (Some(p50), Some(p85), Some(p95)) => format!(
+            "p50 {}  p85 {}  p95 {}  ({} tasks)",
+            format_hours(p50),
+            format_hours(p85),
+            format_hours(p95),
+            stats.count
+        ),
+        _ => "-".to_string(),
+    }
+}
+
+fn cmd_export(output: Option<std::path::PathBuf>, format: &str) -> Result<()> {
+    let client = ApiClient::new()?;
+    let current_dir = std::env::current_dir()?;
+    let path = current_dir.to_string_lossy().to_string();
+
+    let lookup = client.lookup_linked_path(&path)?.ok_or_else(|| {
+        anyhow::anyhow!("Current directory is not linked. Run: rk link <project-id>")
+    })?;
+
+    let contents = match format {
+        "csv" => client.export_tasks_csv(lookup.linked_path.project_id)?,
+        "markdown" => render_markdown(&client, lookup.linked_path.project_id)?,
+        _ => {
+            let document = client.export_project(lookup.linked_path.project_id)?;
+            serde_json::to_string_pretty(&document)? + "\n"
+        }
+    };
+    match output {
+        Some(file) => {
+            std::fs::write(&file, contents)
+                .with_context(|| format!("Failed to write {}", file.display()))?;
+            eprintln!("Exported '{}' to {}", lookup.project_name, file.display());
+        }
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+fn cmd_scan(dry_run: bool) -> Result<()> {
+    let client = ApiClient::new()?;
+    let current_dir = std::env::current_dir()?;
+    let path = current_dir.to_string_lossy().to_string();
+
+    let lookup = client.lookup_linked_path(&path)?.ok_or_else(|| {
+        anyhow::anyhow!("Current directory is not linked. Run: rk link <project-id>")
+    })?;
+    let project_id = lookup.linked_path.project_id;
+    let root = std::path::PathBuf::from(&lookup.linked_path.path);
+
+    let (files, comments) = scan::scan(&root)?;
+    println!("Scanned {} files under {}: {} comments", files, root.display(), comments.len());
+
+    let filters = TaskFilters {
+        source_tag: Some("code".to_string()),
+        ..Default::default()
+    };
+    let tasks = client.list_tasks_filtered(project_id, &filters)?;
+    let by_fingerprint: std::collections::HashMap<&str, &api::Task> = tasks
+        .iter()
+        .filter_map(|task| Some((scan::fingerprint_of(task.description.as_deref()?)?, task)))
+        .collect();
+    let columns = client.get_project_columns(project_id)?;
+    let is_closed = |task: &api::Task| {
+        columns
+            .iter()
+            .any(|c| c.id == task.column_id && matches!(c.kind.as_deref(), Some("done" | "archived")))
+    };
+
+    let (mut created, mut updated, mut closed) = (0, 0, 0);
+    for comment in &comments {
+        let description = comment.description();
+        match by_fingerprint.get(comment.fingerprint.as_str()) {
+            // Comments that moved within their file only need the new line number.
+            Some(task) if task.description.as_deref() != Some(description.as_str()) => {
+                if !dry_run {
+                    client.update_task_description(task.id, &description)?;
+                }
+                updated += 1;
+            }
+            Some(_) => {}
+            None => {
+                println!("  + {} ({}:{})", comment.title(), comment.path, comment.line);
+                if !dry_run {
+                    let task = client.create_task(CreateTaskParams {
+                        project_id,
+                        column_id: lookup.linked_path.default_column_id,
+                        title: comment.title(),
+                        description: Some(description),
+                        source_tag: Some("code".to_string()),
+                        due_at: None,
+                        parent_id: None,
+                    })?;
+                    print_warnings(&task);
+                }
+                created += 1;
+            }
+        }
+    }
+
+    let found: std::collections::HashSet<&str> = comments.iter().map(|c| c.fingerprint.as_str()).collect();
+    let gone: Vec<&api::Task> = tasks
+        .iter()
+        .filter(|task| {
+            task.description
+                .as_deref()
+                .and_then(scan::fingerprint_of)
+                .is_some_and(|fingerprint| !found.contains(fingerprint))
+                && !is_closed(task)
+        })
+        .collect();
+    if !gone.is_empty() {
+        match columns.iter().find(|c| c.kind.as_deref() == Some("done")) {
+            Some(done_column) => {
+                for task in gone {
+                    println!("  - {} (comment removed)", task.title);
+                    if !dry_run {
+                        client.move_task(task.id, done_column.id)?;
+                    }
+                    closed += 1;
+                }
+            }
+            None => println!(
+                "  {} tasks whose comments were removed stay open: the project has no done column",
+                gone.len()
+            ),
+        }
+    }
+
+    if dry_run {
+        println!("Would create {} tasks, update {} and close {}", created, updated, closed);
+    } else {
+        println!("Created {} tasks, updated {} and closed {}", created, updated, closed);
+    }
+    Ok(())
+}
+
+/// The linked board as a markdown checklist, fetching subtasks for the tasks that have any.
+fn render_markdown(client: &ApiClient, project_id: i64) -> Result<String> {
+    let board = client.get_board(project_id)?;
+    let mut subtasks = std::collections::HashMap::new();
+    for task in board.columns.iter().flat_map(|c| &c.tasks) {
+        if task.progress.as_ref().is_some_and(|p| p.subtasks_total > 0) {
+            subtasks.insert(task.id, client.get_task(task.id)?.subtasks);
+        }
+    }
+    Ok(markdown::render(&board, &subtasks))
+}
+
+fn cmd_sync(file: std::path::PathBuf) -> Result<()> {
+    let client = ApiClient::new()?;
+    let current_dir = std::env::current_dir()?;
+    let path = current_dir.to_string_lossy().to_string();
+
+    let lookup = client.lookup_linked_path(&path)?.ok_or_else(|| {
+        anyhow::anyhow!("Current directory is not linked. Run: rk link <project-id>")
+    })?;
+    let project_id = lookup.linked_path.project_id;
+
+    if !file.exists() {
+        std::fs::write(&file, render_markdown(&client, project_id)?)
+            .with_context(|| format!("Failed to write {}", file.display()))?;
+        println!("Wrote '{}' to {}", lookup.project_name, file.display());
+        return Ok(());
+    }
+
+    let text = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let items = markdown::parse(&text);
+
+    let board = client.get_board(project_id)?;
+    let (mut created, mut completed, mut subtasks_created, mut subtasks_checked) = (0, 0, 0, 0);
+    for item in &items {
+        let task_id = match markdown::plan(&board, item, lookup.linked_path.default_column_id)? {
+            Change::Gone { id } => {
+                println!("  Line {}: task {} is no longer on the board, skipped", item.line, id);
+                continue;
+            }
+            Change::Unchanged { id } => id,
+            Change::Complete { id, column_id } => {
+                let moved = client.move_task(id, column_id)?;
+                print_warnings(&moved);
+                completed += 1;
+                id
+            }
+            Change::Create { column_id } => {
+                let task = client.create_task(CreateTaskParams {
+                    project_id,
+                    column_id,
+                    title: item.title.clone(),
+                    description: None,
+                    source_tag: Some("markdown".to_string()),
+                    due_at: None,
+                    parent_id: None,
+                })?;
+                print_warnings(&task);
+                created += 1;
+                task.id
+            }
+        };
+
+        if item.subtasks.is_empty() {
+            continue;
+        }
+        let existing = match item.id {
+            Some(_) => client.get_task(task_id)?.subtasks,
+            None => Vec::new(),
+        };
+        for sub in &item.subtasks {
+            match sub.id {
+                None => {
+                    let subtask = client.create_subtask(task_id, &sub.title)?;
+                    if sub.checked {
+                        client.set_subtask_done(subtask.id, true)?;
+                    }
+                    subtasks_created += 1;
+                }
+                Some(id) => match existing.iter().find(|s| s.id == id) {
+                    Some(subtask) if sub.checked && !sub.was_checked && !subtask.done => {
+                        client.set_subtask_done(id, true)?;
+                        subtasks_checked += 1;
+                    }
+                    Some(_) => {}
+                    None => println!("  Line {}: subtask {} is no longer on the board, skipped", sub.line, id),
+                },
+            }
+        }
+    }
+
+    std::fs::write(&file, render_markdown(&client, project_id)?)
+        .with_context(|| format!("Failed to write {}", file.display()))?;
+
+    println!("Synced {} with '{}'", file.display(), lookup.project_name);
+    if created > 0 {
+        println!("  Created {} tasks", created);
+    }
+    if completed > 0 {
+        let done_column = board.columns.iter().find(|c| c.kind == "done").map_or("done", |c| c.name.as_str());
+        println!("  Moved {} tasks to '{}'", completed, done_column);
+    }
+    if subtasks_created > 0 {
+        println!("  Created {} subtasks", subtasks_created);
+    }
+    if subtasks_checked > 0 {
+        println!("  Checked off {} subtasks", subtasks_checked);
+    }
+    Ok(())
+}
+
+fn cmd_import(
+    file: std::path::PathBuf,
+    name: Option<String>,
+    format: &str,
+    mappings: &[String],
+    dry_run: bool,
+) -> Result<()> {
+    if format == "csv" {
+        if name.is_some() {
+            anyhow::bail!("--name doesn't apply to CSV, which adds tasks to the linked project");
+        }
+        return cmd_import_csv(file, mappings, dry_run);
+    }
+    if !mappings.is_empty() || dry_run {
+        anyhow::bail!("--map and --dry-run only apply to --format csv");
+    }
+
+    let contents = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let document: serde_json::Value = serde_json::from_str(&contents)
+        .with_context(|| format!("{} is not a JSON file", file.display()))?;
+
+    let client = ApiClient::new()?;
+    let (summary, unmapped) = if format == "trello" {
+        let imported = client.import_trello(&document, name.as_deref())?;
+        (imported.summary, imported.unmapped)
+    } else {
+        (client.import_project(&document, name.as_deref())?, Vec::new())
+    };
+
+    println!(
+        "Imported '{}' (ID: {}) with {} tasks",
+        summary.project.name, summary.project.id, summary.tasks
+    );
+    if summary.tags_created > 0 {
+        println!("  Created {} new tags", summary.tags_created);
+    }
+    for path in &summary.skipped_linked_paths {
+        println!("  Skipped {} (already linked to another project)", path);
+    }
+    if !unmapped.is_empty() {
+        println!("  Not imported from Trello:");
+        for field in &unmapped {
+            println!("    {} ({})", field.field, field.count);
+        }
+    }
+    println!("Run: rk link {}", summary.project.id);
+    Ok(())
+}
+
+fn cmd_import_csv(file: std::path::PathBuf, mappings: &[String], dry_run: bool) -> Result<()> {
+    let csv = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let mapping = mappings
+        .iter()
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(header, field)| (header.trim().to_string(), field.trim().to_string()))
+                .ok_or_else(|| anyhow::anyhow!("Invalid --map '{}'. Use HEADER=FIELD", pair))
+        })
+        .collect::<Result<std::collections::HashMap<_, _>>>()?;
+
+    let client = ApiClient::new()?;
+    let current_dir = std::env::current_dir()?;
+    let path = current_dir.to_string_lossy().to_string();
+    let lookup = client.lookup_linked_path(&path)?.ok_or_else(|| {
+        anyhow::anyhow!("Current directory is not linked. Run: rk link <project-id>")
+    })?;
+
+    let summary = client.import_tasks_csv(lookup.linked_path.project_id, &csv, &mapping, dry_run)?;
+
+    if dry_run {
+        println!("Columns in {}:", file.display());
+        for column in &summary.mapping {
+            match &column.field {
+                Some(field) => println!("  {} -> {}", column.header, field),
+                None => println!("  {} (skipped)", column.header),
+            }
+        }
+        println!("Would import {} tasks into '{}'", summary.tasks, lookup.project_name);
+    } else {
+        println!("Imported {} tasks into '{}'", summary.tasks, lookup.project_name);
+    }
+    let verb = if dry_run { "Would create" } else { "Created" };
+    if !summary.columns_created.is_empty() {
+        println!("  {} columns: {}", verb, summary.columns_created.join(", "));
+    }
+    if !summary.tags_created.is_empty() {
+        println!("  {} tags: {}", verb, summary.tags_created.join(", "));
+    }
+    for warning in &summary.warnings {
+        eprintln!("Warning: {}", warning.message);
+    }
+    Ok(())
+}
+
+fn cmd_search(query: String, all: bool) -> Result<()> {
+    let client = ApiClient::new()?;
+
+    let project_id = if all {
+        None
+    } else {
+        let current_dir = std::env::current_dir()?;
+        let path = current_dir.to_string_lossy().to_string();
+        client
+            .lookup_linked_path(&path)?
+            .map(|lookup| lookup.linked_path.project_id)
+    };
+
+    let results = client.search(&query, project_id)?;
+
+    if results.is_empty() {
+        println!("No tasks match '{}'.", query);
+        return Ok(());
+    }
+
+    let emphasize = std::io::stdout().is_terminal();
+    for result in results {
+        let location = if project_id.is_some() {
+            result.column_name
+        } else {
+            format!("{} / {}", result.project_name, result.column_name)
+        };
+        println!(
+            "  [{}] {} ({})",
+            result.task_id,
+            render_highlight(&result.title_highlight, emphasize),
+            location
+        );
+        if result.snippet != result.title_highlight && result.snippet.contains("<mark>") {
+            println!("      {}", render_highlight(&result.snippet, emphasize));
+        }
+    }
+    Ok(())
+}
+
+fn cmd_token_create(
+    name: String,
+    scopes: Vec<String>,
+    project_ids: Vec<i64>,
+    expires: Option<String>,
+) -> Result<()> {
+    let client = ApiClient::new()?;
+    let expires_at = expires.as_deref().map(parse_due).transpose()?;
+
+    let created = client.create_token(CreateTokenParams {
+        name,
+        scopes,
+        project_ids,
+        expires_at,
+    })?;
+
+    println!("Created token [{}] {}", created.info.id, created.info.name);
+    println!("  {}", created.token);
+    println!("Store it now; it won't be shown again.");
+    Ok(())
+}
+
+fn cmd_token_list() -> Result<()> {
+    let client = ApiClient::new()?;
+    let tokens = client.list_tokens()?;
+
+    if tokens.is_empty() {
+        println!("No active tokens.");
+        return Ok(());
+    }
+
+    for token in tokens {
+        let scopes = token
+            .scopes
+            .map(|scopes| scopes.join(", "))
+            .unwrap_or_else(|| "all scopes".to_string());
+        let projects = match token.project_ids {
+            Some(ids) => {
+                let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                format!("projects {}", ids.join(", "))
+            }
+            None => "all projects".to_string(),
+        };
+        println!("  [{}] {} ({}...) - {}; {}", token.id, token.name, token.prefix, scopes, projects);
+        if let Some(expires_at) = token.expires_at {
+            println!("      expires {}", expires_at);
+        }
+        if let Some(last_used_at) = token.last_used_at {
+            println!("      last used {}", last_used_at);
+        }
+    }
+    Ok(())
+}
+
+fn cmd_token_revoke(id: i64) -> Result<()> {
+    let client = ApiClient::new()?;
+    client.revoke_token(id)?;
+    println!("Revoked token {}", id);
+    Ok(())
+}
+
+/// Replaces the server's `<mark>` tags with bold text, or drops them when not on a terminal.
+fn render_highlight(text: &str, emphasize: bool) -> String {
+    let (open, close) = if emphasize { ("\x1b[1m", "\x1b[0m") } else { ("", "") };
+    text.replace("<mark>", open)
+        .replace("</mark>", close)
+        .replace('\n', " ")
+}
+
 fn find_task_by_title<'a>(tasks: &'a [api::Task], title: &str) -> Result<&'a api::Task> {
     let matching: Vec<_> = tasks
         .iter()
@@ -259,6 +983,13 @@ fn find_column_by_name_or_id<'a>(
     }
 }
 
+/// WIP-limit and blocker warnings go to stderr so scripts reading stdout are unaffected.
+fn print_warnings(task: &api::Task) {
+    for warning in &task.warnings {
+        eprintln!("Warning: {}", warning.message);
+    }
+}
+
 fn cmd_move(title: String, column: String) -> Result<()> {
     let client = ApiClient::new()?;
     let current_dir = std::env::current_dir()?;
@@ -274,8 +1005,9 @@ fn cmd_move(title: String, column: String) -> Result<()> {
     let columns = client.get_project_columns(lookup.linked_path.project_id)?;
     let target_column = find_column_by_name_or_id(&columns, &column)?;
 
-    client.move_task(task.id, target_column.id)?;
+    let moved = client.move_task(task.id, target_column.id)?;
     println!("Moved task '{}' to '{}'", task.title, target_column.name);
+    print_warnings(&moved);
     Ok(())
 }
 
@@ -293,14 +1025,18 @@ fn cmd_done(title: String) -> Result<()> {
 
     let columns = client.get_project_columns(lookup.linked_path.project_id)?;
     let done_column = columns
-        .last()
-        .ok_or_else(|| anyhow::anyhow!("Project has no columns"))?;
+        .iter()
+        .find(|c| c.kind.as_deref() == Some("done"))
+        .ok_or_else(|| {
+            anyhow::anyhow!("Project has no done column. Set a column's kind to 'done' on the board.")
+        })?;
 
-    client.move_task(task.id, done_column.id)?;
+    let moved = client.move_task(task.id, done_column.id)?;
     println!(
         "Marked task '{}' as done (moved to '{}')",
         task.title, done_column.name
     );
+    print_warnings(&moved);
     Ok(())
 }
 
@@ -380,12 +1116,27 @@ fn cmd_columns() -> Result<()> {
         } else {
             ""
         };
-        println!("  [{}] {}{}", col.id, col.name, default_marker);
+        let wip_marker = match col.wip_limit {
+            Some(limit) => format!(" (WIP limit {})", limit),
+            None => String::new(),
+        };
+        let kind_marker = match col.kind.as_deref() {
+            Some(kind @ ("done" | "archived")) => format!(" ({})", kind),
+            _ => String::new(),
+        };
+        println!("  [{}] {}{}{}{}", col.id, col.name, kind_marker, wip_marker, default_marker);
     }
     Ok(())
 }
 
-fn cmd_tasks() -> Result<()> {
+fn cmd_tasks(
+    column: Option<String>,
+    priority: Option<String>,
+    tags: Vec<String>,
+    source: Option<String>,
+    search: Option<String>,
+    sort: Option<String>,
+) -> Result<()> {
     let client = ApiClient::new()?;
     let current_dir = std::env::current_dir()?;
     let path = current_dir.to_string_lossy().to_string();
@@ -395,26 +1146,124 @@ fn cmd_tasks() -> Result<()> {
     })?;
 
     let columns = client.get_project_columns(lookup.linked_path.project_id)?;
-    let tasks = client.list_tasks(lookup.linked_path.project_id)?;
+
+    let column_id = match &column {
+        Some(col_arg) => Some(find_column_by_name_or_id(&columns, col_arg)?.id),
+        None => None,
+    };
+
+    let tag_ids = if tags.is_empty() {
+        Vec::new()
+    } else {
+        let all_tags = client.list_tags()?;
+        tags.iter()
+            .map(|name| {
+                all_tags
+                    .iter()
+                    .find(|t| t.name.to_lowercase() == name.to_lowercase())
+                    .map(|t| t.id)
+                    .ok_or_else(|| anyhow::anyhow!("Tag '{}' not found", name))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let filtered = column_id.is_some()
+        || priority.is_some()
+        || !tag_ids.is_empty()
+        || source.is_some()
+        || search.is_some();
+    let sorted = sort.is_some();
+
+    let filters = TaskFilters {
+        column_id,
+        priority,
+        tag_ids,
+        source_tag: source,
+        query: search,
+        order_by: sort,
+    };
+    let tasks = client.list_tasks_filtered(lookup.linked_path.project_id, &filters)?;
 
     if tasks.is_empty() {
-        println!("No tasks in project '{}'.", lookup.project_name);
+        if filtered {
+            println!("No matching tasks in project '{}'.", lookup.project_name);
+        } else {
+            println!("No tasks in project '{}'.", lookup.project_name);
+        }
         return Ok(());
     }
 
     println!("Tasks in '{}':", lookup.project_name);
+
+    // An explicit sort order is kept as a flat list instead of being regrouped by column.
+    if sorted {
+        for task in &tasks {
+            let column_name = columns
+                .iter()
+                .find(|c| c.id == task.column_id)
+                .map(|c| c.name.as_str())
+                .unwrap_or("?");
+            println!("  [{}] {}{} ({})", task.id, task.title, priority_suffix(task), column_name);
+        }
+        return Ok(());
+    }
+
     for col in &columns {
         let col_tasks: Vec<_> = tasks.iter().filter(|t| t.column_id == col.id).collect();
         if !col_tasks.is_empty() {
             println!("\n  {}:", col.name);
             for task in col_tasks {
-                println!("    [{}] {}", task.id, task.title);
+                println!("    [{}] {}{}", task.id, task.title, priority_suffix(task));
             }
         }
     }
     Ok(())
 }
 
+fn priority_suffix(task: &api::Task) -> String {
+    let mut suffix = match task.priority.as_deref() {
+        Some(p @ ("urgent" | "high")) => format!(" !{}", p),
+        _ => String::new(),
+    };
+    if let Some(due) = task.due_at.as_deref().and_then(|d| d.get(..10)) {
+        suffix.push_str(&format!(" (due {})", due));
+    }
+    suffix
+}
+
+/// Turns `--due` input into something the server accepts. Relative forms
+/// (`today`, `tomorrow`, `+3d`, `+2w`) become local calendar dates and `+Nh`
+/// becomes a timestamp; anything else is passed through for the server to validate.
+fn parse_due(input: &str) -> Result<String> {
+    let input = input.trim();
+    let today = chrono::Local::now().date_naive();
+
+    match input.to_lowercase().as_str() {
+        "today" => return Ok(today.format("%Y-%m-%d").to_string()),
+        "tomorrow" => return Ok((today + chrono::Days::new(1)).format("%Y-%m-%d").to_string()),
+        _ => {}
+    }
+
+    let Some(relative) = input.strip_prefix('+') else {
+        return Ok(input.to_string());
+    };
+    let unit = relative.chars().last().unwrap_or('d');
+    let amount: u64 = relative
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid relative due date '{}'. Use e.g. +3d, +2w or +4h", input))?;
+
+    match unit {
+        'h' => {
+            let hours = chrono::Duration::hours(amount as i64);
+            Ok((chrono::Utc::now() + hours).to_rfc3339())
+        }
+        'w' => Ok((today + chrono::Days::new(amount * 7)).format("%Y-%m-%d").to_string()),
+        'd' | '0'..='9' => Ok((today + chrono::Days::new(amount)).format("%Y-%m-%d").to_string()),
+        _ => anyhow::bail!("Invalid relative due date '{}'. Use e.g. +3d, +2w or +4h", input),
+    }
+}
+
 fn cmd_status() -> Result<()> {
     let config = load_global_config()?;
 
@@ -462,7 +1311,10 @@ fn cmd_add(
     column: Option<String>,
     description: Option<String>,
     tag: Option<String>,
+    due: Option<String>,
+    parent: Option<String>,
 ) -> Result<()> {
+    let due_at = due.as_deref().map(parse_due).transpose()?;
     let client = ApiClient::new()?;
     let current_dir = std::env::current_dir()?;
     let path = current_dir.to_string_lossy().to_string();
@@ -492,18 +1344,32 @@ fn cmd_add(
         None => lookup.linked_path.default_column_id,
     };
 
+    let parent_id = match parent {
+        Some(parent_arg) => match parent_arg.parse::<i64>() {
+            Ok(id) => Some(id),
+            Err(_) => {
+                let tasks = client.list_tasks(lookup.linked_path.project_id)?;
+                Some(find_task_by_title(&tasks, &parent_arg)?.id)
+            }
+        },
+        None => None,
+    };
+
     let task = client.create_task(CreateTaskParams {
         project_id: lookup.linked_path.project_id,
         column_id,
         title: title.clone(),
         description,
         source_tag: tag,
+        due_at,
+        parent_id,
     })?;
 
     println!(
         "Created task '{}' (ID: {}) in project '{}'",
         task.title, task.id, lookup.project_name
     );
+    print_warnings(&task);
     Ok(())
 }
 
@@ -0,0 +1,334 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+use crate::api::{Board, Subtask};
+
+/// A checklist item read back from a rendered board.
+#[derive(Debug)]
+pub struct Item {
+    /// 1-based, for messages.
+    pub line: usize,
+    /// The `## heading` the item sits under, if any.
+    pub heading: Option<String>,
+    /// `None` for lines added by hand.
+    pub id: Option<i64>,
+    pub title: String,
+    pub checked: bool,
+    /// Whether the item was checked when the file was written, as its marker records;
+    /// `false` for lines added by hand.
+    pub was_checked: bool,
+    pub subtasks: Vec<SubItem>,
+}
+
+#[derive(Debug)]
+pub struct SubItem {
+    pub line: usize,
+    pub id: Option<i64>,
+    pub title: String,
+    pub checked: bool,
+    pub was_checked: bool,
+}
+
+/// What `rk sync` does with a task item.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    /// A new task in the column; `None` leaves the choice to the server.
+    Create { column_id: Option<i64> },
+    /// The task was checked off since the file was written and moves to the done column.
+    Complete { id: i64, column_id: i64 },
+    Unchanged { id: i64 },
+    /// The task has left the board since the file was written.
+    Gone { id: i64 },
+}
+
+/// Decides what happens to `item`. New items go to the column their heading names, or
+/// else `linked_column`, the board's first `todo` column or its first column; checked
+/// items go to the done column, which is an error if the board has none.
+pub fn plan(board: &Board, item: &Item, linked_column: Option<i64>) -> Result<Change> {
+    let done_column = || {
+        board.columns.iter().find(|c| c.kind == "done").map(|c| c.id).ok_or_else(|| {
+            anyhow!("Project has no done column. Set a column's kind to 'done' on the board.")
+        })
+    };
+
+    if let Some(id) = item.id {
+        let Some(column) = board.columns.iter().find(|c| c.tasks.iter().any(|t| t.id == id)) else {
+            return Ok(Change::Gone { id });
+        };
+        if item.checked && !item.was_checked && column.kind != "done" {
+            return Ok(Change::Complete { id, column_id: done_column()? });
+        }
+        return Ok(Change::Unchanged { id });
+    }
+
+    if item.checked {
+        return Ok(Change::Create { column_id: Some(done_column()?) });
+    }
+    let heading = item
+        .heading
+        .as_deref()
+        .and_then(|name| board.columns.iter().find(|c| c.name.eq_ignore_ascii_case(name)));
+    let column_id = heading
+        .map(|c| c.id)
+        .or(linked_column)
+        .or_else(|| board.columns.iter().find(|c| c.kind == "todo").map(|c| c.id))
+        .or_else(|| board.columns.first().map(|c| c.id));
+    Ok(Change::Create { column_id })
+}
+
+/// Renders the board as one `##` section per column and a checklist item per task,
+/// with its subtasks indented below it. Each item ends with an HTML comment holding its
+/// id and whether it was done, which markdown viewers hide and [`parse`] reads back, so
+/// that only items checked off in the file since count as completed.
+pub fn render(board: &Board, subtasks: &HashMap<i64, Vec<Subtask>>) -> String {
+    let mut out = format!("# {}\n\n", board.name);
+    out.push_str(
+        "<!-- Kept in sync by `rk sync`: new items become tasks, checked items move to the done column. -->\n",
+    );
+    for column in &board.columns {
+        out.push_str(&format!("\n## {}\n", column.name));
+        if !column.tasks.is_empty() {
+            out.push('\n');
+        }
+        let done = column.kind == "done";
+        for task in &column.tasks {
+            out.push_str(&format!(
+                "- {} {} <!-- task:{}{} -->\n",
+                checkbox(done),
+                single_line(&task.title),
+                task.id,
+                done_state(done)
+            ));
+            for subtask in subtasks.get(&task.id).into_iter().flatten() {
+                out.push_str(&format!(
+                    "  - {} {} <!-- subtask:{}{} -->\n",
+                    checkbox(subtask.done),
+                    single_line(&subtask.title),
+                    subtask.id,
+                    done_state(subtask.done)
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Reads the checklist items out of a file written by [`render`] and edited since.
+/// Indented items belong to the item above them in the same section; lines that aren't
+/// checklist items are ignored.
+pub fn parse(text: &str) -> Vec<Item> {
+    let mut items: Vec<Item> = Vec::new();
+    let mut heading = None;
+    // Index of the item indented lines attach to; a new heading starts without one.
+    let mut parent: Option<usize> = None;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        if let Some(name) = line.strip_prefix("## ") {
+            heading = Some(name.trim().to_string());
+            parent = None;
+            continue;
+        }
+        let indented = line.starts_with([' ', '\t']);
+        let Some((checked, rest)) = checklist_item(line.trim_start()) else {
+            continue;
+        };
+        let (title, marker) = split_marker(rest);
+        if title.is_empty() {
+            continue;
+        }
+
+        match parent.map(|index| &mut items[index]) {
+            Some(parent) if indented => {
+                let marker = marker.filter(|(kind, _, _)| *kind == "subtask");
+                parent.subtasks.push(SubItem {
+                    line: line_number,
+                    id: marker.map(|(_, id, _)| id),
+                    title,
+                    checked,
+                    was_checked: marker.is_some_and(|(_, _, done)| done),
+                });
+            }
+            _ => {
+                let marker = marker.filter(|(kind, _, _)| *kind == "task");
+                parent = Some(items.len());
+                items.push(Item {
+                    line: line_number,
+                    heading: heading.clone(),
+                    id: marker.map(|(_, id, _)| id),
+                    title,
+                    checked,
+                    was_checked: marker.is_some_and(|(_, _, done)| done),
+                    subtasks: Vec::new(),
+                });
+            }
+        }
+    }
+    items
+}
+
+fn checkbox(checked: bool) -> &'static str {
+    if checked {
+        "[x]"
+    } else {
+        "[ ]"
+    }
+}
+
+fn done_state(done: bool) -> &'static str {
+    if done {
+        " done"
+    } else {
+        ""
+    }
+}
+
+fn single_line(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `- [ ] text` or `* [x] text`, returning whether it is checked and the text.
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let rest = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))?
+        .trim_start();
+    let checked = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    Some((checked, rest[3..].trim()))
+}
+
+/// Splits a trailing `<!-- task:12 -->`, `<!-- task:12 done -->` or `<!-- subtask:40 -->`
+/// off the item text, returning its kind, id and whether it was done.
+fn split_marker(text: &str) -> (String, Option<(&str, i64, bool)>) {
+    let marker = text
+        .strip_suffix("-->")
+        .and_then(|rest| rest.rfind("<!--").map(|start| (start, &rest[start + 4..])))
+        .and_then(|(start, inner)| {
+            let (kind, rest) = inner.trim().split_once(':')?;
+            let mut words = rest.split_whitespace();
+            let id = words.next()?.parse().ok()?;
+            let done = match words.next() {
+                None => false,
+                Some("done") => true,
+                Some(_) => return None,
+            };
+            Some((start, (kind, id, done)))
+        });
+    match marker {
+        Some((start, marker)) => (text[..start].trim().to_string(), Some(marker)),
+        None => (text.to_string(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board() -> Board {
+        serde_json::from_value(serde_json::json!({
+            "name": "Website",
+            "columns": [
+                { "id": 1, "name": "Backlog", "kind": "backlog", "tasks": [] },
+                { "id": 2, "name": "To Do", "kind": "todo", "tasks": [{ "id": 12, "title": "Write docs" }] },
+                { "id": 3, "name": "Done", "kind": "done", "tasks": [{ "id": 13, "title": "Ship" }] },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn item(heading: Option<&str>, id: Option<i64>, checked: bool) -> Item {
+        Item {
+            line: 1,
+            heading: heading.map(String::from),
+            id,
+            title: "Something".to_string(),
+            checked,
+            was_checked: false,
+            subtasks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parse_reads_back_rendered_markers() {
+        let subtasks = HashMap::from([(12, vec![Subtask { id: 40, title: "Outline".to_string(), done: true }])]);
+        let items = parse(&render(&board(), &subtasks));
+
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].id, items[0].title.as_str(), items[0].checked), (Some(12), "Write docs", false));
+        assert!(!items[0].was_checked);
+        assert_eq!(items[0].heading.as_deref(), Some("To Do"));
+        assert_eq!(items[0].subtasks.len(), 1);
+        assert_eq!((items[0].subtasks[0].id, items[0].subtasks[0].checked), (Some(40), true));
+        assert!(items[0].subtasks[0].was_checked);
+        assert_eq!((items[1].id, items[1].checked, items[1].was_checked), (Some(13), true, true));
+        assert_eq!(items[1].heading.as_deref(), Some("Done"));
+    }
+
+    #[test]
+    fn parse_nests_indented_items() {
+        let items = parse("## To Do\n\n- [ ] Release\n  - [x] Tag it\n\t* [ ] Announce\nNot an item\n- [ ] Next\n");
+
+        assert_eq!(items.len(), 2);
+        let titles: Vec<_> = items[0].subtasks.iter().map(|s| (s.title.as_str(), s.checked, s.id)).collect();
+        assert_eq!(titles, [("Tag it", true, None), ("Announce", false, None)]);
+        assert!(items[1].subtasks.is_empty());
+    }
+
+    #[test]
+    fn parse_does_not_nest_across_headings() {
+        let items = parse("## To Do\n- [ ] First\n\n## Done\n  - [x] Indented\n");
+
+        assert_eq!(items.len(), 2);
+        assert!(items[0].subtasks.is_empty());
+        assert_eq!(items[1].title, "Indented");
+        assert_eq!(items[1].heading.as_deref(), Some("Done"));
+    }
+
+    #[test]
+    fn parse_reads_the_done_state_of_markers() {
+        let items = parse("- [x] Ship <!-- task:13 done -->\n- [x] Docs <!-- task:12 -->\n- [ ] Odd <!-- task:14 later -->\n");
+
+        assert_eq!((items[0].id, items[0].was_checked), (Some(13), true));
+        assert_eq!((items[1].id, items[1].was_checked), (Some(12), false));
+        assert_eq!((items[2].id, items[2].title.as_str()), (None, "Odd <!-- task:14 later -->"));
+    }
+
+    #[test]
+    fn plan_places_new_items_by_heading() {
+        let board = board();
+
+        assert_eq!(plan(&board, &item(Some("backlog"), None, false), None).unwrap(), Change::Create { column_id: Some(1) });
+        assert_eq!(plan(&board, &item(Some("Someday"), None, false), None).unwrap(), Change::Create { column_id: Some(2) });
+        assert_eq!(plan(&board, &item(Some("Someday"), None, false), Some(1)).unwrap(), Change::Create { column_id: Some(1) });
+        assert_eq!(plan(&board, &item(None, None, true), None).unwrap(), Change::Create { column_id: Some(3) });
+    }
+
+    #[test]
+    fn plan_moves_checked_tasks_to_the_done_column() {
+        let board = board();
+
+        assert_eq!(plan(&board, &item(None, Some(12), true), None).unwrap(), Change::Complete { id: 12, column_id: 3 });
+        assert_eq!(plan(&board, &item(None, Some(12), false), None).unwrap(), Change::Unchanged { id: 12 });
+        assert_eq!(plan(&board, &item(None, Some(13), true), None).unwrap(), Change::Unchanged { id: 13 });
+        assert_eq!(plan(&board, &item(None, Some(99), true), None).unwrap(), Change::Gone { id: 99 });
+
+        let mut no_done = board;
+        no_done.columns.pop();
+        assert!(plan(&no_done, &item(None, Some(12), true), None).is_err());
+    }
+
+    #[test]
+    fn plan_leaves_tasks_reopened_since_the_file_was_written() {
+        // Task 12 sits in To Do, but the file was written while it was done and still
+        // says so: nothing was checked off in the file, so it isn't moved back.
+        let mut reopened = item(None, Some(12), true);
+        reopened.was_checked = true;
+
+        assert_eq!(plan(&board(), &reopened, None).unwrap(), Change::Unchanged { id: 12 });
+    }
+}
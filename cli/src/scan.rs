@@ -0,0 +1,275 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const KEYWORDS: [&str; 3] = ["TODO", "FIXME", "HACK"];
+
+/// Files bigger than this are generated or vendored more often than not.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Tasks `rk scan` created carry their fingerprint in the description, in this comment.
+const FINGERPRINT_PREFIX: &str = "<!-- rk-scan:";
+
+/// A `TODO`, `FIXME` or `HACK` comment found in the tree.
+#[derive(Debug)]
+pub struct CodeComment {
+    /// Relative to the scanned directory, with `/` separators.
+    pub path: String,
+    pub line: usize,
+    pub keyword: &'static str,
+    pub text: String,
+    /// Stays the same while the comment's file and text do, wherever it moves in the file.
+    pub fingerprint: String,
+}
+
+impl CodeComment {
+    pub fn title(&self) -> String {
+        if self.text.is_empty() {
+            format!("{} in {}", self.keyword, self.path)
+        } else {
+            format!("{}: {}", self.keyword, self.text)
+        }
+    }
+
+    pub fn description(&self) -> String {
+        format!(
+            "`{}:{}`\n\n{}\n\n{}{} -->",
+            self.path,
+            self.line,
+            self.title(),
+            FINGERPRINT_PREFIX,
+            self.fingerprint
+        )
+    }
+}
+
+/// The fingerprint in a task description written by [`CodeComment::description`].
+pub fn fingerprint_of(description: &str) -> Option<&str> {
+    let start = description.rfind(FINGERPRINT_PREFIX)? + FINGERPRINT_PREFIX.len();
+    let rest = &description[start..];
+    Some(rest[..rest.find("-->")?].trim())
+}
+
+/// Finds the marked comments under `root`, returning them with the number of files read.
+pub fn scan(root: &Path) -> Result<(usize, Vec<CodeComment>)> {
+    let files = match git_files(root) {
+        Some(files) => files,
+        None => {
+            let mut files = Vec::new();
+            walk(root, root, &mut files)?;
+            files
+        }
+    };
+
+    let mut comments = Vec::new();
+    let mut scanned = 0;
+    for relative in files {
+        let full = root.join(&relative);
+        if full.metadata().map(|m| !m.is_file() || m.len() > MAX_FILE_SIZE).unwrap_or(true) {
+            continue;
+        }
+        // Binary and non-UTF-8 files are skipped.
+        let Ok(contents) = std::fs::read_to_string(&full) else {
+            continue;
+        };
+        scanned += 1;
+
+        let path = relative.to_string_lossy().replace('\\', "/");
+        comments.extend(comments_in(&path, &contents));
+    }
+    Ok((scanned, comments))
+}
+
+/// The marked comments in one file's contents.
+fn comments_in(path: &str, contents: &str) -> Vec<CodeComment> {
+    let markers = comment_markers(path);
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut comments = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let Some((keyword, text)) = marked_comment(line, markers) else {
+            continue;
+        };
+        // Identical comments in one file are told apart by their order.
+        let occurrence = seen.entry(format!("{}{}", keyword, text)).or_default();
+        *occurrence += 1;
+        let fingerprint = fingerprint(path, keyword, &text, *occurrence);
+        comments.push(CodeComment {
+            path: path.to_string(),
+            line: index + 1,
+            keyword,
+            text,
+            fingerprint,
+        });
+    }
+    comments
+}
+
+/// How comments start in the file's language, judged by its name. Files of unknown
+/// types are checked for all of them.
+fn comment_markers(path: &str) -> &'static [&'static str] {
+    const C_LIKE: &[&str] = &["//", "/*"];
+    const HASH: &[&str] = &["#"];
+    const SQL: &[&str] = &["--", "/*"];
+    const MARKUP: &[&str] = &["<!--"];
+    const ALL: &[&str] = &["//", "/*", "<!--", "#", "--"];
+
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if matches!(name, "Makefile" | "Dockerfile" | "Gemfile" | "Rakefile") {
+        return HASH;
+    }
+    let Some((_, extension)) = name.rsplit_once('.') else {
+        return ALL;
+    };
+    match extension.to_ascii_lowercase().as_str() {
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "go" | "java" | "kt" | "kts" | "scala" | "swift"
+        | "dart" | "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "css" | "scss" | "less" | "proto" => C_LIKE,
+        "py" | "rb" | "sh" | "bash" | "zsh" | "fish" | "pl" | "r" | "ex" | "exs" | "toml" | "yaml" | "yml"
+        | "nix" | "cmake" | "tf" | "dockerfile" => HASH,
+        "sql" | "lua" | "hs" | "elm" => SQL,
+        "md" | "markdown" | "html" | "htm" | "xml" | "svg" => MARKUP,
+        "php" => &["//", "/*", "#"],
+        "vue" | "svelte" => &["//", "/*", "<!--"],
+        _ => ALL,
+    }
+}
+
+/// Tracked and untracked files that `.gitignore` doesn't exclude, or `None` outside a
+/// git work tree.
+fn git_files(root: &Path) -> Option<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["ls-files", "-z", "--cached", "--others", "--exclude-standard"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let listing = String::from_utf8(output.stdout).ok()?;
+    Some(listing.split('\0').filter(|p| !p.is_empty()).map(PathBuf::from).collect())
+}
+
+/// Every file below `dir`, skipping hidden directories.
+fn walk(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                walk(root, &path, files)?;
+            }
+        } else if file_type.is_file() {
+            if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The first comment on the line that starts with one of [`KEYWORDS`], as the keyword
+/// and the text after it; `(who)` after the keyword is dropped. A comment marker only
+/// counts at the start of the line or after whitespace, so `"#TODO"` in a string or the
+/// `--` of `x--` is not taken for a comment.
+fn marked_comment(line: &str, markers: &[&str]) -> Option<(&'static str, String)> {
+    let mut starts: Vec<usize> = markers
+        .iter()
+        .flat_map(|marker| line.match_indices(marker).map(|(i, _)| i))
+        .filter(|&i| line[..i].chars().next_back().is_none_or(char::is_whitespace))
+        .collect();
+    if markers.contains(&"/*") && line.trim_start().starts_with('*') {
+        // A line inside a block comment.
+        starts.push(0);
+    }
+    starts.sort_unstable();
+    starts.into_iter().find_map(|start| keyword_comment(&line[start..]))
+}
+
+fn keyword_comment(comment: &str) -> Option<(&'static str, String)> {
+    let body = comment.trim_start_matches(['/', '*', '#', '-', '!', '<', ' ', '\t']);
+    let keyword = *KEYWORDS.iter().find(|k| body.starts_with(**k))?;
+    let rest = &body[keyword.len()..];
+    if !(rest.is_empty() || rest.starts_with([':', '(', ' ', '\t'])) {
+        return None;
+    }
+    let rest = match rest.strip_prefix('(') {
+        Some(after) => &after[after.find(')').map_or(0, |end| end + 1)..],
+        None => rest,
+    };
+    let text = rest
+        .trim_start_matches([':', ' ', '\t', '-'])
+        .trim_end()
+        .trim_end_matches("*/")
+        .trim_end_matches("-->")
+        .trim_end();
+    Some((keyword, text.to_string()))
+}
+
+fn fingerprint(path: &str, keyword: &str, text: &str, occurrence: usize) -> String {
+    let digest = Sha256::digest(format!("{}\n{}\n{}\n{}", path, keyword, text, occurrence));
+    digest.iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marked_comment_finds_comments_in_each_language() {
+        let rust = comment_markers("src/main.rs");
+        assert_eq!(marked_comment("    // TODO: handle errors", rust), Some(("TODO", "handle errors".to_string())));
+        assert_eq!(marked_comment("let x = 1; /* HACK until 2.0 */", rust), Some(("HACK", "until 2.0".to_string())));
+        assert_eq!(marked_comment("     * FIXME wrap this", rust), Some(("FIXME", "wrap this".to_string())));
+
+        let python = comment_markers("tools/build.py");
+        assert_eq!(marked_comment("x = 1  # FIXME(ann): off by one", python), Some(("FIXME", "off by one".to_string())));
+        assert_eq!(marked_comment("-- TODO: index this", comment_markers("schema.sql")), Some(("TODO", "index this".to_string())));
+        assert_eq!(marked_comment("<!-- TODO: screenshots -->", comment_markers("README.md")), Some(("TODO", "screenshots".to_string())));
+    }
+
+    #[test]
+    fn marked_comment_skips_markers_outside_comments() {
+        let rust = comment_markers("src/lib.rs");
+        assert_eq!(marked_comment(r##"let s = "#TODO";"##, rust), None);
+        assert_eq!(marked_comment("x--; // ok", rust), None);
+        assert_eq!(marked_comment("let url = \"http://example.com/TODO\";", rust), None);
+        assert_eq!(marked_comment("x--; // ok", comment_markers("notes")), None);
+        assert_eq!(marked_comment("# TODO list", comment_markers("docs/plan.md")), None);
+        assert_eq!(marked_comment("echo \"#TODO\"", comment_markers("run.sh")), None);
+    }
+
+    #[test]
+    fn keyword_comment_reads_keyword_and_text() {
+        assert_eq!(keyword_comment("// TODO(name): rename"), Some(("TODO", "rename".to_string())));
+        assert_eq!(keyword_comment("# HACK(ann)"), Some(("HACK", String::new())));
+        assert_eq!(keyword_comment("/* FIXME */"), Some(("FIXME", String::new())));
+        assert_eq!(keyword_comment("<!-- TODO - later -->"), Some(("TODO", "later".to_string())));
+        assert_eq!(keyword_comment("// TODOS are tracked elsewhere"), None);
+        assert_eq!(keyword_comment("// todo: lower case"), None);
+        assert_eq!(keyword_comment("// see the TODO below"), None);
+    }
+
+    #[test]
+    fn fingerprint_of_reads_back_the_description() {
+        let comment = &comments_in("src/api.rs", "// TODO: retry on 503\n")[0];
+        assert_eq!(fingerprint_of(&comment.description()), Some(comment.fingerprint.as_str()));
+        assert_eq!(fingerprint_of("Written by hand"), None);
+        assert_eq!(fingerprint_of("<!-- rk-scan: abc"), None);
+    }
+
+    #[test]
+    fn fingerprint_follows_the_comment_not_its_line() {
+        let before = comments_in("src/api.rs", "fn a() {}\n// TODO: retry\n// TODO: retry\n");
+        let after = comments_in("src/api.rs", "use x;\n\nfn a() {}\n\n// TODO: retry\n// TODO: retry\n");
+
+        assert_eq!((before[0].line, after[0].line), (2, 5));
+        assert_eq!(before[0].fingerprint, after[0].fingerprint);
+        assert_eq!(before[1].fingerprint, after[1].fingerprint);
+        assert_ne!(before[0].fingerprint, before[1].fingerprint, "repeats are told apart");
+        assert_ne!(before[0].fingerprint, comments_in("src/other.rs", "// TODO: retry\n")[0].fingerprint);
+    }
+}
@@ -5,6 +5,8 @@ export interface Project {
   id: number;
   name: string;
   sort_order: number;
+  wip_mode: 'soft' | 'hard';
+  blocker_mode: 'soft' | 'hard';
   created_at: string;
 }
 
@@ -13,9 +15,14 @@ export interface Column {
   project_id: number;
   name: string;
   sort_order: number;
+  wip_limit: number | null;
+  /** Tasks in `done` columns count as finished, wherever the column sits. */
+  kind: ColumnKind;
   created_at: string;
 }
 
+export type ColumnKind = 'backlog' | 'todo' | 'in_progress' | 'done' | 'archived';
+
 export interface Task {
   id: number;
   project_id: number;
@@ -26,6 +33,21 @@ export interface Task {
   sort_order: number;
   source_tag?: string | null;
   created_at?: string | null;
+  due_at?: string | null;
+  start_at?: string | null;
+  assignee_id?: number | null;
+  parent_id?: number | null;
+  completed_at?: string | null;
+  /** On board payloads, for tasks with child tasks or subtasks. */
+  progress?: Progress;
+}
+
+/** Rolled up over every task below a task, plus the subtasks of all of them. */
+export interface Progress {
+  tasks_done: number;
+  tasks_total: number;
+  subtasks_done: number;
+  subtasks_total: number;
 }
 
 export interface ColumnWithTasks extends Column {
@@ -44,6 +66,38 @@ export interface Subtask {
   created_at: string;
 }
 
+export interface Comment {
+  id: number;
+  task_id: number;
+  author_id: number | null;
+  author: string;
+  /** Markdown, as written. */
+  body: string;
+  created_at: string;
+  updated_at: string | null;
+}
+
+export type DependencyKind = 'blocks' | 'blocked_by' | 'relates_to' | 'duplicates' | 'duplicated_by';
+
+/** Read as "source `kind` target"; `blocked_by` and `duplicated_by` are stored reversed. */
+export interface Dependency {
+  id: number;
+  source_id: number;
+  target_id: number;
+  kind: 'blocks' | 'relates_to' | 'duplicates';
+  created_at: string;
+}
+
+export interface LinkedTask {
+  dependency_id: number;
+  id: number;
+  title: string;
+  column_id: number;
+  done: boolean;
+}
+
+export type TaskLinks = Record<DependencyKind, LinkedTask[]>;
+
 export interface Tag {
   id: number;
   name: string;
@@ -78,6 +132,7 @@ async function fetchApi<T>(
 ): Promise<T> {
   const headers: HeadersInit = {
     'Content-Type': 'application/json',
+    'X-Actor': 'web',
     ...(API_KEY && { 'X-API-Key': API_KEY }),
     ...options.headers,
   };
@@ -188,7 +243,7 @@ export const api = {
         body: JSON.stringify({ name }),
       }),
     
-    update: (id: number, data: { name?: string; sort_order?: number }) =>
+    update: (id: number, data: { name?: string; sort_order?: number; kind?: ColumnKind }) =>
       fetchApi<Column>(`/columns/${id}`, {
         method: 'PUT',
         body: JSON.stringify(data),
@@ -227,6 +282,42 @@ export const api = {
       }),
   },
 
+  comments: {
+    list: (taskId: number) => fetchApi<Comment[]>(`/tasks/${taskId}/comments`),
+
+    create: (taskId: number, body: string) =>
+      fetchApi<Comment>(`/tasks/${taskId}/comments`, {
+        method: 'POST',
+        body: JSON.stringify({ body }),
+      }),
+
+    update: (id: number, body: string) =>
+      fetchApi<Comment>(`/comments/${id}`, {
+        method: 'PUT',
+        body: JSON.stringify({ body }),
+      }),
+
+    delete: (id: number) =>
+      fetchApi<void>(`/comments/${id}`, {
+        method: 'DELETE',
+      }),
+  },
+
+  dependencies: {
+    list: (taskId: number) => fetchApi<TaskLinks>(`/tasks/${taskId}/dependencies`),
+
+    create: (taskId: number, otherTaskId: number, kind: DependencyKind) =>
+      fetchApi<Dependency>(`/tasks/${taskId}/dependencies`, {
+        method: 'POST',
+        body: JSON.stringify({ task_id: otherTaskId, kind }),
+      }),
+
+    delete: (id: number) =>
+      fetchApi<void>(`/dependencies/${id}`, {
+        method: 'DELETE',
+      }),
+  },
+
   tags: {
     list: () => fetchApi<Tag[]>('/tags'),
     